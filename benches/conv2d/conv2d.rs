@@ -1,36 +1,29 @@
 use criterion::Criterion;
-use rand::distributions::{Distribution, Normal, Uniform};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
 use log::debug;
 
-use std::fs;
-use std::io;
-use std::path::{Path, PathBuf};
-
 extern crate lair;
 extern crate nalgebra as na;
 
-use lair::img::{
-    range_matrix, range_vector, read_luma, read_lumad, unit_range_from_img, write_luma_matrix, write_luma_vector,
+use lair::img::augment::{Augmentation, GaussianNoise};
+use lair::img::synth::read_images;
+use lair::img::{Colormap, Labeling, Mapping, Montage, PlacementGenerator, Renderer};
+use lair::{
+    setup_logging, Conv2d, Fxx, IntersectionOverUnion, LayeredModel, LinearModel, Logit, Metric,
+    Model, Regression, RegressionMetric, Relu, SGDTrainer, UpdateParams,
 };
-use lair::{Conv2d, Fxx, LayeredModel, LinearModel, Logit, Model, Relu, SGDTrainer, setup_logging, UpdateParams};
 
-use na::allocator::Allocator;
-use na::storage::Owned;
-use na::{DefaultAllocator, VectorN};
+use na::VectorN;
 use na::{DimDiff, DimName, DimProd, DimSum, U1, U32, U4};
 use typenum::{U300, U400};
 
-struct TrainParams<'a> {
-    num_targets: (f64, f64), // mean, stdev
-    sz_targets: (f64, f64),
-    test_backs: &'a Path,
-    test_targets: &'a Path,
-    train_backs: &'a Path,
-    train_targets: &'a Path,
+struct TrainParams {
+    generator: PlacementGenerator<InputD, OutputD1>,
+    noise: Fxx, // stdev of additive Gaussian noise
 }
 
-type Dim2d = (usize, usize);
 type Width = U400;
 type Height = U300;
 type InputD = DimProd<Width, Height>;
@@ -50,140 +43,33 @@ const LEARNING_PARAMS: UpdateParams = UpdateParams {
     l2_reg: 0.01,
 };
 
-fn choose_img(dir: &Path) -> io::Result<PathBuf> {
-    fn is_img<'r>(entry: &'r Result<fs::DirEntry, io::Error>) -> bool {
-        match entry {
-            Ok(f) => {
-                match f.path().extension() {
-                    Some(ext) => ext == "png",
-                    None => false,
-                }
-            },
-            _ => false,
-        }
-    }
-
-    debug!("choosing image from {}", dir.to_string_lossy());
-    if !dir.is_dir() {
-        Err(io::Error::new(
-            io::ErrorKind::InvalidInput,
-            format!("not a directory: {}", dir.to_string_lossy()),
-        ))
-    } else {
-        let count = dir.read_dir()?.filter(is_img).count();
-        debug!("found {} images in {:#?}", count, dir);
-        if count <= 0 {
-            Err(io::Error::new(
-                io::ErrorKind::NotFound,
-                format!("no valid image found: {}", dir.to_string_lossy()),
-            ))
-        } else {
-            let dist = Uniform::from(0..count);
-            let choice = dist.sample(&mut rand::thread_rng());
-            dir.read_dir()?
-                .filter(is_img)
-                .nth(choice)
-                .ok_or_else(|| {
-                    io::Error::new(
-                        io::ErrorKind::NotFound,
-                        format!("no valid image found: {}", dir.to_string_lossy()),
-                    )
-                })
-                .map(|entry| entry.unwrap().path())
-        }
-    }
-}
-
-fn compose_example<M, N>(
-    target_img: &Vec<(PathBuf, Dim2d)>,
-    back_img: (&Path, Dim2d),
-) -> (VectorN<Fxx, M>, VectorN<Fxx, N>)
-where
-    M: DimName,
-    N: DimName,
-    DefaultAllocator: Allocator<Fxx, M> + Allocator<Fxx, N>,
-    Owned<Fxx, M>: Copy,
-    Owned<Fxx, N>: Copy,
-{
-    debug!("reading background: {}", back_img.0.to_string_lossy());
-    let mut back_mat = read_luma::<_, Height, Width>(&mut io::BufReader::new(
-        fs::File::open(back_img.0.as_os_str()).unwrap(),
-    ))
-    .unwrap();
-    let mut positions = VectorN::<Fxx, N>::zeros();
-
-    for i in target_img {
-        let file_path = &i.0;
-        let target_dim = i.1;
-        let x_dist = Uniform::from(0..Width::dim() - target_dim.1);
-        let x = x_dist.sample(&mut rand::thread_rng());
-        let y_dist = Uniform::from(0..Height::dim() - target_dim.0);
-        let y = y_dist.sample(&mut rand::thread_rng());
-        debug!("reading target: {}", file_path.to_string_lossy());
-        let target_mat = read_lumad(
-            &mut io::BufReader::new(fs::File::open(file_path.as_os_str()).unwrap()),
-            target_dim,
-        )
-        .unwrap();
-        // Copy the target into the background
-        lair::img::overlay_matrix(&target_mat, y, x, &mut back_mat);
-
-        // calculate ouput positions by interpolation.
-        let y0 = (Output1Rows::dim() as Fxx * y as Fxx / Height::dim() as Fxx).floor() as usize;
-        let y1 = (Output1Rows::dim() as Fxx * (y + target_dim.0) as Fxx / Height::dim() as Fxx).floor() as usize;
-        let x0 = (Output1Cols::dim() as Fxx * x as Fxx / Width::dim() as Fxx).floor() as usize;
-        let x1 = (Output1Cols::dim() as Fxx * (x + target_dim.1) as Fxx / Width::dim() as Fxx).floor() as usize;
-        debug!("({},{}) ({},{}) -> ({},{}) ({},{})", x, y, x+target_dim.1, y+target_dim.0, x0, y0, x1, y1);
-        debug!("{}x{} -> {}", Output1Rows::dim(), Output1Cols::dim(), positions.nrows());
-        for col in x0..x1 {
-            let col_off = col * Output1Rows::dim();
-            for row in y0..y1 {
-                let flat = col_off + row;
-                positions[flat] = 1.0;
-            }
-        }
-    }
-
-    // flatten the matrix
-    back_mat = unit_range_from_img(&back_mat);
-    let back_rows = VectorN::<Fxx, M>::from_row_slice(back_mat.as_slice());
-    (back_rows, positions)
+const SEED: u64 = 20210311;
+
+fn create_example<R: Rng>(
+    params: &TrainParams,
+    rng: &mut R,
+) -> (VectorN<Fxx, InputD>, VectorN<Fxx, OutputD1>) {
+    let placement = params.generator.generate(rng);
+    let mut image = placement.image;
+    GaussianNoise::new(params.noise).augment(&mut image, None, rng);
+    let x = VectorN::<Fxx, InputD>::from_column_slice(image.as_slice());
+    (x, params.generator.label(&placement.boxes))
 }
 
-fn create_example<M, N>(params: &TrainParams) -> (VectorN<Fxx, M>, VectorN<Fxx, N>)
-where
-    M: DimName,
-    N: DimName,
-    DefaultAllocator: Allocator<Fxx, M> + Allocator<Fxx, N>,
-    Owned<Fxx, M>: Copy,
-    Owned<Fxx, N>: Copy,
-{
-    let norm_num = Normal::new(params.num_targets.0, params.num_targets.1);
-    let norm_sz = Normal::new(params.sz_targets.0, params.sz_targets.1);
-    let num_targets = norm_num.sample(&mut rand::thread_rng()).round().max(0.0) as usize;
-    let targets = [0..num_targets]
-        .iter()
-        .map(|_| {
-            let sz = norm_sz.sample(&mut rand::thread_rng()).round().max(1.0) as usize;
-            (choose_img(params.test_targets).unwrap(), (sz, sz))
-        })
-        .collect();
-    let back_img = choose_img(params.test_backs).unwrap(); // XXX test or train? and above
-    let back_dim = (Height::dim(), Width::dim());
-    compose_example(&targets, (&back_img, back_dim))
-}
-
-fn find_target(params: &TrainParams) {
+fn find_target<R: Rng>(params: &TrainParams, rng: &mut R) {
     debug!("find_target");
     let mut train0 = SGDTrainer::new(&LEARNING_PARAMS);
-    let mut pooler0 = LinearModel::<DimProd<PoolS, PoolS>, Pool0>::new_random(&mut train0);
+    let mut pooler0 =
+        LinearModel::<DimProd<PoolS, PoolS>, Pool0>::new_random_with_rng(&mut train0, rng);
     let mut cnn0 =
         Conv2d::<PoolS, PoolS, U1, Pool0, Height, Width, InputD, OutputD0>::new(&mut pooler0);
     let mut layer0 = Relu::new(&mut cnn0);
 
     let mut train1 = SGDTrainer::new(&LEARNING_PARAMS);
-    let mut pooler1 =
-        LinearModel::<DimProd<Pool0, DimProd<PoolS, PoolS>>, U1>::new_random(&mut train1);
+    let mut pooler1 = LinearModel::<DimProd<Pool0, DimProd<PoolS, PoolS>>, U1>::new_random_with_rng(
+        &mut train1,
+        rng,
+    );
     let mut cnn1 =
         Conv2d::<PoolS, PoolS, Pool0, U1, Output0Rows, Output0Cols, OutputD0, OutputD1>::new(
             &mut pooler1,
@@ -192,40 +78,73 @@ fn find_target(params: &TrainParams) {
 
     let mut model = LayeredModel::new(&mut layer0, &mut layer1);
 
-    let examples = [0..64]
-        .iter()
-        .map(|_| create_example::<InputD, OutputD1>(params));
+    let examples = (0..64).map(|_| create_example(params, rng));
     examples.for_each(|ex| {
         model.update(&ex.0, &ex.1);
     });
 
-    // Evaluate, log error XXX
-
-    // Output pooler states
-    for i in 0..Pool0::dim() {
-        let path = format!("ws0{}.png", i);
-        let slice = pooler0.get_ws().row(i).transpose();
-        write_luma_vector(
-            &range_vector(&slice),
-            PoolS::dim(),
-            PoolS::dim(),
-            &path,
+    let mut iou = IntersectionOverUnion::new();
+    let mut mse = RegressionMetric::new(Regression::Mse);
+    (0..16)
+        .map(|_| create_example(params, rng))
+        .for_each(|(x, y)| {
+            let yh = model.predict(&x);
+            Metric::<OutputD1>::update(&mut iou, &yh, &y);
+            Metric::<OutputD1>::update(&mut mse, &yh, &y);
+        });
+    debug!(
+        "iou {} mse {}",
+        Metric::<OutputD1>::value(&iou),
+        Metric::<OutputD1>::value(&mse)
+    );
+
+    // Output the pooler filters, negative weights blue and positive red, and
+    // the first layer's activations for an example
+    let (x, _) = create_example(params, rng);
+    let activations = cnn0.predict(&x);
+    let mut renderer = Renderer::new(Mapping::Symmetric);
+    renderer.set_colormap(Colormap::Diverging);
+    let mut filters = Montage::new(renderer);
+    filters.set_scale(4);
+    filters.set_columns(Pool0::dim());
+    filters
+        .add_filters(pooler0.get_ws(), 1, PoolS::dim(), PoolS::dim())
+        .unwrap();
+    filters
+        .add_filters(pooler1.get_ws(), Pool0::dim(), PoolS::dim(), PoolS::dim())
+        .unwrap();
+    filters.write("filters.png").unwrap();
+
+    let mut channels = Montage::new(Renderer::new(Mapping::AutoRange));
+    channels.set_columns(2);
+    channels
+        .add_channels(
+            &activations,
+            Pool0::dim(),
+            Output0Rows::dim(),
+            Output0Cols::dim(),
         )
         .unwrap();
-    }
-    write_luma_matrix(&range_matrix(pooler1.get_ws()), &"ws1.png").unwrap();
+    channels.write("activations.png").unwrap();
 }
 
 pub fn find_targets(c: &mut Criterion) {
     setup_logging();
+    let mut generator = PlacementGenerator::new(
+        Height::dim(),
+        Width::dim(),
+        read_images("./benches/conv2d/train_backs").unwrap(),
+        read_images("./benches/conv2d/train_targets").unwrap(),
+        Labeling::Grid(Output1Rows::dim(), Output1Cols::dim()),
+    )
+    .unwrap();
+    generator.set_num_targets(1.0, 1.0);
+    generator.set_target_size(32.0, 5.0);
     let params = TrainParams {
-        num_targets: (1.0, 1.0),
-        sz_targets: (32.0, 5.0),
-        test_backs: &Path::new("./benches/conv2d/train_backs"),
-        test_targets: &Path::new("./benches/conv2d/train_targets"),
-        train_backs: &Path::new("./benches/conv2d/train_backs"),
-        train_targets: &Path::new("./benches/conv2d/train_targets"),
+        generator,
+        noise: 0.05,
     };
 
-    c.bench_function("find_simple", |b| b.iter(|| find_target(&params)));
+    let mut rng = StdRng::seed_from_u64(SEED);
+    c.bench_function("find_simple", |b| b.iter(|| find_target(&params, &mut rng)));
 }
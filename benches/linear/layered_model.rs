@@ -3,15 +3,22 @@ use log::debug;
 
 extern crate nalgebra as na;
 
-use lair::{Fxx, LayeredModel, LinearModel, Model, SGDTrainer, setup_logging, UpdateParams};
+use lair::{
+    setup_logging, EarlyStopping, Fxx, LayeredModel, LinearModel, Model, SGDTrainer, UpdateParams,
+};
 
 use na::allocator::Allocator;
 use na::DefaultAllocator;
 use na::{DimName, U1, U2};
 use na::{Matrix, Matrix1, Matrix2x1, MatrixMN};
 
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
-use rand::thread_rng;
+use rand::{Rng, SeedableRng};
+
+const SEED: u64 = 20210207;
+// epochs without improvement of the test error before giving up
+const PATIENCE: usize = 100;
 
 pub fn has_nan<M: DimName, N: DimName>(x: &MatrixMN<Fxx, M, N>) -> bool
 where
@@ -25,17 +32,17 @@ where
     }
     false
 }
-fn sample_input(sz: usize) -> Vec<Matrix2x1<Fxx>> {
-    fn shuffled_xs(sz: usize) -> Vec<Fxx> {
+fn sample_input<R: Rng>(sz: usize, rng: &mut R) -> Vec<Matrix2x1<Fxx>> {
+    fn shuffled_xs<R: Rng>(sz: usize, rng: &mut R) -> Vec<Fxx> {
         let mx = 0.5 * (sz as Fxx);
         let mut xs: Vec<Fxx> = (0..sz).map(|x| 0.1 * ((x as Fxx) - mx)).collect();
-        xs.shuffle(&mut thread_rng());
+        xs.shuffle(rng);
         xs
     }
 
-    shuffled_xs(sz)
+    shuffled_xs(sz, rng)
         .iter()
-        .zip(shuffled_xs(sz).iter())
+        .zip(shuffled_xs(sz, rng).iter())
         .map(|(&x0, &x1)| Matrix2x1::<Fxx>::new(x0, x1))
         .collect()
 }
@@ -45,17 +52,20 @@ fn optimize_quadratic(learning_rate: &UpdateParams, tol: Fxx) {
         Matrix1::<Fxx>::new(0.5 * x[0] - 4.0 * x[1] - 6.0)
     }
 
+    let mut rng = StdRng::seed_from_u64(SEED);
     let mut train0 = SGDTrainer::new(&learning_rate);
-    let mut m0 = LinearModel::<U2, U2>::new_normal(&mut train0, 10.0);
+    let mut m0 = LinearModel::<U2, U2>::new_normal_with_rng(&mut train0, 10.0, &mut rng);
     let mut train1 = SGDTrainer::new(&learning_rate);
-    let mut m1 = LinearModel::<U2, U1>::new_normal(&mut train1, 10.0);
+    let mut m1 = LinearModel::<U2, U1>::new_normal_with_rng(&mut train1, 10.0, &mut rng);
     let mut model = LayeredModel::<U2, U2, U1>::new(&mut m0, &mut m1);
 
     let num_samples = 100;
     let num_test = 20;
     let num_train = num_samples - num_test;
+    let mut stopping = EarlyStopping::new(PATIENCE);
+    stopping.set_target(tol);
     loop {
-        let sample = sample_input(num_samples);
+        let sample = sample_input(num_samples, &mut rng);
         let (train, test) = sample.split_at(num_train);
         for x in train {
             debug_assert!(!has_nan(&x), "invalid input {}", x);
@@ -75,14 +85,16 @@ fn optimize_quadratic(learning_rate: &UpdateParams, tol: Fxx) {
             .sum::<Fxx>()
             / (num_test as Fxx);
         debug!("optimize_quadratic {} mean_error={}", me < tol, me);
-        if me.is_nan() {
-            panic!("NaN error");
-        }
-        if me < tol {
+        if !stopping.step(&model, me) {
             break;
         }
     }
-    debug!("complete optimize_quadratic");
+    stopping.restore(&mut model);
+    debug!(
+        "complete optimize_quadratic {:?} best={:?}",
+        stopping.get_stop_reason(),
+        stopping.get_best()
+    );
 }
 
 // XXX Example of need to improve numeric stability in training.
@@ -1,5 +1,7 @@
 use criterion::Criterion;
 use rand::distributions::{Distribution, Normal};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
 extern crate lair;
 extern crate nalgebra as na;
@@ -8,6 +10,8 @@ use lair::{Fxx, LinearModel, Model, SGDTrainer, UpdateParams};
 use na::{Matrix1x3, Matrix2x1, Matrix2x3};
 use na::{U1, U2};
 
+const SEED: u64 = 20210130;
+
 // Solve a simple 2-variable linear equation using least squares for
 // 3 noisy observations.
 //
@@ -15,7 +19,8 @@ use na::{U1, U2};
 //
 //  * `sigma` - the amount of Gaussian noise to apply to the actual value
 //  to derive observations.
-fn solve_simple_linear(sigma: f64) -> () {
+//  * `rng` - the source of the noise and initial model.
+fn solve_simple_linear<R: Rng>(sigma: f64, rng: &mut R) -> () {
     let learning_params = UpdateParams {
         l2_reg: 0.0,
         step_size: 0.01,
@@ -23,24 +28,22 @@ fn solve_simple_linear(sigma: f64) -> () {
     let mut train = SGDTrainer::new(&learning_params);
     let normal = Normal::new(0.0, sigma);
 
-    let mut model = LinearModel::<U2, U1>::new_random(&mut train);
+    let mut model = LinearModel::<U2, U1>::new_random_with_rng(&mut train, rng);
     let x = Matrix2x3::new(2.0, 3.0, 4.0, 1.0, 4.0, 5.0);
 
     let mut y = Matrix1x3::new(6.0, 11.0, 14.0);
     // Half of the benchmark time is spent here.
-    y = Matrix1x3::from_iterator(
-        y.iter()
-            .map(|&x| x + normal.sample(&mut rand::thread_rng()) as Fxx),
-    );
+    y = Matrix1x3::from_iterator(y.iter().map(|&x| x + normal.sample(rng) as Fxx));
 
-    assert_eq!(model.update_bulk(&x, &y), Ok(()));
+    model.update_bulk(&x, &y).unwrap();
     let x0 = Matrix2x1::new(0.5, 1.0);
     let _yh = model.predict(&x0);
 }
 
 // A wrapper for optimizing a 2-variable linear model through least squares.
 pub fn solve_simple_linear_benchmark(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(SEED);
     c.bench_function("solve_simple_linear", |b| {
-        b.iter(|| solve_simple_linear(0.1))
+        b.iter(|| solve_simple_linear(0.1, &mut rng))
     });
 }
@@ -0,0 +1,259 @@
+extern crate nalgebra as na;
+
+use log::debug;
+
+use na::allocator::Allocator;
+use na::{DefaultAllocator, DimName, Dynamic, VectorN, U1};
+
+use crate::model::{Batch, Fxx, Model};
+use crate::trainer::GradientTrainer;
+
+const EPSILON: Fxx = 1e-5;
+
+///
+/// Batch normalization, Ioffe and Szegedy 2015, Goodfellow, Bengio, Courville S8.7.1.
+/// Each input is standardized by its mean and variance over a mini-batch,
+/// then scaled and shifted by learned parameters.  The scale and shift are
+/// trained as the (diagonal) weights and bias of the given trainer.
+///
+/// Batch statistics are only computed by the `*_batch` methods.  The
+/// single-sample methods, and the batch methods in evaluation mode, normalize
+/// with running averages of the batch statistics collected during training.
+///
+pub struct BatchNorm<'a, N>
+where
+    N: DimName,
+    DefaultAllocator: Allocator<Fxx, N>,
+{
+    trainer: &'a mut dyn GradientTrainer<U1, N>,
+    scale: VectorN<Fxx, N>,
+    shift: VectorN<Fxx, N>,
+    running_mean: VectorN<Fxx, N>,
+    running_var: VectorN<Fxx, N>,
+    momentum: Fxx,
+    training: bool,
+}
+
+impl<'a, N> BatchNorm<'a, N>
+where
+    N: DimName,
+    DefaultAllocator: Allocator<Fxx, N> + Allocator<Fxx, N, Dynamic>,
+{
+    ///
+    /// Create a batch normalization layer with unit scale and zero shift.
+    ///
+    /// # Arguments
+    /// * `trainer` - updates the scale (as weights) and shift (as bias).
+    /// * `momentum` - the weight, in (0, 1], of each new batch's statistics
+    ///   in the running averages used for evaluation.
+    ///
+    pub fn new(trainer: &'a mut dyn GradientTrainer<U1, N>, momentum: Fxx) -> Self {
+        BatchNorm {
+            trainer,
+            scale: VectorN::<Fxx, N>::from_element(1.0),
+            shift: VectorN::<Fxx, N>::zeros(),
+            running_mean: VectorN::<Fxx, N>::zeros(),
+            running_var: VectorN::<Fxx, N>::from_element(1.0),
+            momentum,
+            training: true,
+        }
+    }
+
+    pub fn get_running_mean(&self) -> &VectorN<Fxx, N> {
+        &self.running_mean
+    }
+
+    pub fn get_running_var(&self) -> &VectorN<Fxx, N> {
+        &self.running_var
+    }
+
+    pub fn get_scale(&self) -> &VectorN<Fxx, N> {
+        &self.scale
+    }
+
+    pub fn get_shift(&self) -> &VectorN<Fxx, N> {
+        &self.shift
+    }
+
+    fn train(&mut self, de_dscale: &VectorN<Fxx, N>, de_dshift: &VectorN<Fxx, N>) {
+        if let Some((scale, shift)) =
+            self.trainer
+                .train(&self.scale, &self.shift, de_dscale, de_dshift)
+        {
+            self.scale = scale;
+            self.shift = shift;
+        }
+    }
+}
+
+///
+/// The mean and (biased) variance of each row over the columns of x.
+///
+fn batch_statistics<N>(x: &Batch<N>) -> (VectorN<Fxx, N>, VectorN<Fxx, N>)
+where
+    N: DimName,
+    DefaultAllocator: Allocator<Fxx, N> + Allocator<Fxx, N, Dynamic>,
+{
+    let d = x.ncols() as Fxx;
+    let mean = x.column_sum() / d;
+    let var = VectorN::<Fxx, N>::from_fn(|i, _| {
+        x.row(i)
+            .iter()
+            .map(|xij| (xij - mean[i]).powi(2))
+            .sum::<Fxx>()
+            / d
+    });
+    (mean, var)
+}
+
+fn standardize<N>(x: &Batch<N>, mean: &VectorN<Fxx, N>, var: &VectorN<Fxx, N>) -> Batch<N>
+where
+    N: DimName,
+    DefaultAllocator: Allocator<Fxx, N> + Allocator<Fxx, N, Dynamic>,
+{
+    Batch::<N>::from_fn(x.ncols(), |i, j| {
+        (x[(i, j)] - mean[i]) / (var[i] + EPSILON).sqrt()
+    })
+}
+
+impl<'a, N> Model<N, N> for BatchNorm<'a, N>
+where
+    N: DimName,
+    DefaultAllocator: Allocator<Fxx, N> + Allocator<Fxx, N, Dynamic>,
+{
+    fn backpropagate(&mut self, x: &VectorN<Fxx, N>, de_dy: &VectorN<Fxx, N>) -> VectorN<Fxx, N>
+    where
+        DefaultAllocator: Allocator<Fxx, N> + Allocator<Fxx, N>,
+    {
+        debug!("batch norm backprop {}->{}", N::dim(), N::dim());
+        let std = self.running_var.map(|v| (v + EPSILON).sqrt());
+        let x_hat = (x - &self.running_mean).component_div(&std);
+        let de_dscale = de_dy.component_mul(&x_hat);
+        let de_dx = de_dy.component_mul(&self.scale).component_div(&std);
+        self.train(&de_dscale, de_dy);
+        de_dx
+    }
+
+    ///
+    /// Normalize a batch of inputs using the statistics of the batch while
+    /// training, or the running statistics otherwise.
+    ///
+    fn predict_batch(&self, x: &Batch<N>) -> Batch<N>
+    where
+        DefaultAllocator: Allocator<Fxx, N>
+            + Allocator<Fxx, N>
+            + Allocator<Fxx, N, Dynamic>
+            + Allocator<Fxx, N, Dynamic>,
+    {
+        let (mean, var) = if self.training {
+            batch_statistics(x)
+        } else {
+            (self.running_mean.clone(), self.running_var.clone())
+        };
+        let x_hat = standardize(x, &mean, &var);
+        Batch::<N>::from_fn(x.ncols(), |i, j| {
+            self.scale[i] * x_hat[(i, j)] + self.shift[i]
+        })
+    }
+
+    ///
+    /// Backpropagate the error for a batch of inputs, updating the running
+    /// statistics (while training) and the learned scale and shift from the
+    /// batch mean gradient.
+    ///
+    fn backpropagate_batch(&mut self, x: &Batch<N>, de_dy: &Batch<N>) -> Batch<N>
+    where
+        DefaultAllocator: Allocator<Fxx, N>
+            + Allocator<Fxx, N>
+            + Allocator<Fxx, N, Dynamic>
+            + Allocator<Fxx, N, Dynamic>,
+    {
+        debug!("batch norm backprop {}x{}", N::dim(), x.ncols());
+        let d = x.ncols() as Fxx;
+        let (mean, var) = if self.training {
+            batch_statistics(x)
+        } else {
+            (self.running_mean.clone(), self.running_var.clone())
+        };
+        let x_hat = standardize(x, &mean, &var);
+        let de_dscale = de_dy.component_mul(&x_hat).column_sum() / d;
+        let de_dshift = de_dy.column_sum() / d;
+
+        let de_dx = if self.training {
+            // The batch statistics depend upon every input in the batch.
+            Batch::<N>::from_fn(x.ncols(), |i, j| {
+                self.scale[i] / (var[i] + EPSILON).sqrt()
+                    * (de_dy[(i, j)] - de_dshift[i] - x_hat[(i, j)] * de_dscale[i])
+            })
+        } else {
+            Batch::<N>::from_fn(x.ncols(), |i, j| {
+                self.scale[i] / (var[i] + EPSILON).sqrt() * de_dy[(i, j)]
+            })
+        };
+
+        if self.training {
+            let unbiased = if x.ncols() > 1 { d / (d - 1.0) } else { 1.0 };
+            self.running_mean = (1.0 - self.momentum) * &self.running_mean + self.momentum * mean;
+            self.running_var =
+                (1.0 - self.momentum) * &self.running_var + (self.momentum * unbiased) * var;
+        }
+        self.train(&de_dscale, &de_dshift);
+        de_dx
+    }
+
+    #[inline]
+    fn num_inputs(&self) -> usize {
+        N::dim()
+    }
+
+    #[inline]
+    fn num_outputs(&self) -> usize {
+        N::dim()
+    }
+
+    fn predict(&self, x: &VectorN<Fxx, N>) -> VectorN<Fxx, N>
+    where
+        DefaultAllocator: Allocator<Fxx, N> + Allocator<Fxx, N>,
+    {
+        let std = self.running_var.map(|v| (v + EPSILON).sqrt());
+        let x_hat = (x - &self.running_mean).component_div(&std);
+        x_hat.component_mul(&self.scale) + &self.shift
+    }
+
+    fn update(&mut self, x: &VectorN<Fxx, N>, y: &VectorN<Fxx, N>) -> VectorN<Fxx, N>
+    where
+        DefaultAllocator: Allocator<Fxx, N> + Allocator<Fxx, N>,
+    {
+        let yh = self.predict(x);
+        let err = yh - y;
+        self.backpropagate(x, &err)
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.training = training;
+    }
+
+    ///
+    /// The scale and shift, followed by the running statistics so that
+    /// replicas evaluate consistently.
+    ///
+    fn get_params(&self, params: &mut Vec<Fxx>) {
+        params.extend(self.scale.iter());
+        params.extend(self.shift.iter());
+        params.extend(self.running_mean.iter());
+        params.extend(self.running_var.iter());
+    }
+
+    fn set_params<'p>(&mut self, params: &'p [Fxx]) -> &'p [Fxx] {
+        let n = N::dim();
+        self.scale.copy_from_slice(&params[..n]);
+        self.shift.copy_from_slice(&params[n..2 * n]);
+        self.running_mean.copy_from_slice(&params[2 * n..3 * n]);
+        self.running_var.copy_from_slice(&params[3 * n..4 * n]);
+        &params[4 * n..]
+    }
+}
+
+#[cfg(test)]
+#[path = "./batch_norm_test.rs"]
+mod batch_norm_test;
@@ -0,0 +1,123 @@
+use super::*;
+
+use assert_approx_eq::assert_approx_eq;
+
+use na::{Vector2, U2};
+
+use crate::{Model, SGDTrainer, UpdateParams};
+
+const FIXED_PARAMS: UpdateParams = UpdateParams {
+    step_size: 0.0,
+    l2_reg: 0.0,
+};
+
+const LEARNING_PARAMS: UpdateParams = UpdateParams {
+    step_size: 0.1,
+    l2_reg: 0.0,
+};
+
+fn batch() -> Batch<U2> {
+    Batch::<U2>::from_row_slice(&[
+        1.0, 2.0, 3.0, 6.0, //
+        -4.0, 0.0, 2.0, 10.0,
+    ])
+}
+
+#[test]
+fn create_batch_norm() {
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let model = BatchNorm::<U2>::new(&mut trainer, 0.1);
+    assert_eq!(model.num_inputs(), 2);
+    assert_eq!(model.num_outputs(), 2);
+}
+
+#[test]
+fn normalizes_over_batch() {
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let model = BatchNorm::<U2>::new(&mut trainer, 0.1);
+    let y = model.predict_batch(&batch());
+    for i in 0..2 {
+        let row = y.row(i);
+        assert_approx_eq!(row.sum() / 4.0, 0.0);
+        assert_approx_eq!(row.iter().map(|v| v * v).sum::<Fxx>() / 4.0, 1.0, 1e-3);
+    }
+}
+
+#[test]
+fn tracks_running_statistics() {
+    let mut trainer = SGDTrainer::new(&FIXED_PARAMS);
+    let mut model = BatchNorm::<U2>::new(&mut trainer, 0.5);
+    let x = batch();
+    model.backpropagate_batch(&x, &Batch::<U2>::zeros(4));
+    // means 3 and 2, unbiased variances 14/3 and 104/3
+    assert_approx_eq!(model.get_running_mean()[0], 1.5);
+    assert_approx_eq!(model.get_running_mean()[1], 1.0);
+    assert_approx_eq!(model.get_running_var()[0], 0.5 + 0.5 * 14.0 / 3.0, 1e-4);
+    assert_approx_eq!(model.get_running_var()[1], 0.5 + 0.5 * 104.0 / 3.0, 1e-3);
+
+    // evaluation uses the running statistics, as do single samples
+    model.set_training(false);
+    let y = model.predict_batch(&x);
+    for j in 0..4 {
+        let yj = model.predict(&x.column(j).into_owned());
+        assert_approx_eq!(y[(0, j)], yj[0]);
+        assert_approx_eq!(y[(1, j)], yj[1]);
+    }
+    let before = *model.get_running_mean();
+    model.backpropagate_batch(&x, &Batch::<U2>::zeros(4));
+    assert_eq!(*model.get_running_mean(), before);
+}
+
+#[test]
+fn computes_batch_gradient() {
+    let mut trainer = SGDTrainer::new(&FIXED_PARAMS);
+    let mut model = BatchNorm::<U2>::new(&mut trainer, 0.1);
+    let x = batch();
+    let w = Batch::<U2>::from_row_slice(&[
+        1.0, -2.0, 0.5, 3.0, //
+        0.0, 1.0, -1.0, 2.0,
+    ]);
+    let loss = |m: &BatchNorm<U2>, x: &Batch<U2>| m.predict_batch(x).component_mul(&w).sum();
+
+    let h = 1e-2;
+    let mut numeric = Batch::<U2>::zeros(4);
+    for i in 0..x.len() {
+        let mut x0 = x.clone();
+        let mut x1 = x.clone();
+        x0[i] -= h;
+        x1[i] += h;
+        numeric[i] = (loss(&model, &x1) - loss(&model, &x0)) / (2.0 * h);
+    }
+
+    let de_dx = model.backpropagate_batch(&x, &w);
+    for i in 0..x.len() {
+        assert_approx_eq!(de_dx[i], numeric[i], 1e-2);
+    }
+}
+
+#[test]
+fn learns_scale_and_shift() {
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut model = BatchNorm::<U2>::new(&mut trainer, 0.1);
+    let x = batch();
+    // target: standardized inputs scaled by 2 and shifted by 1
+    let y = 2.0 * model.predict_batch(&x).add_scalar(0.5);
+    for _ in 0..200 {
+        model.update_batch(&x, &y);
+    }
+    assert_approx_eq!(model.get_scale()[0], 2.0, 1e-2);
+    assert_approx_eq!(model.get_shift()[1], 1.0, 1e-2);
+}
+
+#[test]
+fn single_sample_update_uses_running_statistics() {
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut model = BatchNorm::<U2>::new(&mut trainer, 0.1);
+    let x = Vector2::<Fxx>::new(1.0, -1.0);
+    let y = Vector2::<Fxx>::new(3.0, -1.0);
+    let e0 = (model.predict(&x) - y).norm();
+    model.update(&x, &y);
+    let e1 = (model.predict(&x) - y).norm();
+    assert!(e1 < e0, "failed to improve on update {} -> {}", e0, e1);
+    assert_eq!(*model.get_running_mean(), Vector2::<Fxx>::zeros());
+}
@@ -1,15 +1,24 @@
 extern crate nalgebra as na;
 
+use lair::data::{fit_transform, Standardize, Transform};
 use lair::{
-    BatchTrainer, Fxx, GradientTrainer, LayeredModel, LinearModel, Model, Relu, SGDTrainer,
-    UpdateParams,
+    Action, Activation, Batch, Callback, Control, DataLoader, Dataset, EarlyStopping, EventWriter,
+    Fit, FitState, Fxx, Health, History, InMemoryDataset, LayeredModel, LinearModel, Model,
+    Monitor, ParallelTrainer, Relu, SGDTrainer, UpdateParams,
 };
 use log::debug;
 use na::{Matrix, Matrix1, Matrix2x1};
 use na::{U1, U2};
 use rand::distributions::{Distribution, Uniform};
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
-use rand::thread_rng;
+use rand::{Rng, SeedableRng};
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use structopt::clap::{Error, ErrorKind};
 use structopt::StructOpt;
 
 #[derive(Debug, StructOpt)]
@@ -30,98 +39,296 @@ struct OptimizeParams {
     train_batch: usize,
     #[structopt(short = "l", long = "l2", default_value = "0.0")]
     l2: Fxx,
+    /// Number of threads training replicas of the model on shards of each
+    /// training batch, averaging parameters after each batch.  Train on the
+    /// main thread if zero.  Replicas record no history and aren't
+    /// monitored, so this can't be combined with --history or --health.
+    #[structopt(short = "t", long = "threads", default_value = "0")]
+    threads: usize,
+    /// Standardize inputs and targets by statistics fit to an initial
+    /// sample, reporting errors in the original units.
+    #[structopt(short = "z", long = "standardize")]
+    standardize: bool,
+    /// Stop after this many iterations without improvement of the mean test
+    /// error, restoring the best parameters.  Run every iteration if zero.
+    #[structopt(short = "p", long = "patience", default_value = "0")]
+    patience: usize,
+    /// Least decrease of the mean test error counted as an improvement.
+    #[structopt(long = "min-delta", default_value = "0.0")]
+    min_delta: Fxx,
+    /// Seed for model initialization and sampling, chosen at random if absent.
+    #[structopt(long = "seed")]
+    seed: Option<u64>,
+    /// Write the loss of each update (in standardized units if `-z`) and the
+    /// test errors of each iteration to this file, as JSON Lines if it ends
+    /// in .jsonl and CSV otherwise.
+    #[structopt(long = "history", parse(from_os_str))]
+    history: Option<PathBuf>,
+    /// Write the test errors and a histogram of the model parameters of each
+    /// iteration, by training samples, to a TensorBoard event file in this
+    /// directory.
+    #[structopt(long = "events", parse(from_os_str))]
+    events: Option<PathBuf>,
+    /// What to do when an update leaves the parameters or gradients of a
+    /// layer NaN or infinite: warn, skip the update, or rollback to recent
+    /// healthy parameters.  Warn if absent.
+    #[structopt(long = "health")]
+    health: Option<Action>,
 }
 
+const STANDARDIZE_SAMPLES: usize = 1000;
+
 fn f(x: &Matrix2x1<Fxx>) -> Matrix1<Fxx> {
     // Matrix1::<Fxx>::new(0.5 * x[0] - 4.0 * x[1] - 6.0)
     Matrix1::<Fxx>::new(0.5 * x[0] * x[0] + 2.0 * x[0] * x[1] - 4.0 * x[1] * x[1] - 6.0)
 }
 
-fn sample_input(sz: usize) -> Vec<Matrix2x1<Fxx>> {
-    fn shuffled_xs(sz: usize) -> Vec<Fxx> {
+fn sample_input<R: Rng>(sz: usize, rng: &mut R) -> Vec<Matrix2x1<Fxx>> {
+    fn shuffled_xs<R: Rng>(sz: usize, rng: &mut R) -> Vec<Fxx> {
         const MX_ABS: Fxx = 10.0;
-        let mut rng = thread_rng();
         let dist = Uniform::from(-MX_ABS..MX_ABS);
-        let mut xs: Vec<Fxx> = (0..sz).map(|_| dist.sample(&mut rng)).collect();
-        xs.shuffle(&mut rng);
+        let mut xs: Vec<Fxx> = (0..sz).map(|_| dist.sample(rng)).collect();
+        xs.shuffle(rng);
         xs
     }
 
-    shuffled_xs(sz)
+    shuffled_xs(sz, rng)
         .iter()
-        .zip(shuffled_xs(sz).iter())
+        .zip(shuffled_xs(sz, rng).iter())
         .map(|(&x0, &x1)| Matrix2x1::<Fxx>::new(x0, x1))
         .collect()
 }
 
-fn optimize_quadratic(params: &OptimizeParams) {
+///
+/// Log the loss of each update, recording it to the history, if any.  Unlike
+/// the history's own callback, records no epochs: each iteration records its
+/// test errors.
+///
+struct StepRecorder<'a>(Option<&'a mut History>);
+
+impl<'a> Callback<U2, U1> for StepRecorder<'a> {
+    fn on_step(&mut self, model: &mut dyn Model<U2, U1>, state: &mut FitState) -> Control {
+        if let Some(history) = self.0.as_mut() {
+            history.record_step(&*model, state.loss, state.gradient_norm);
+        }
+        debug!(
+            "step {} loss {} gradient norm {}",
+            state.step, state.loss, state.gradient_norm
+        );
+        Control::Continue
+    }
+}
+
+///
+/// Train the model on an epoch of examples, one at a time or in mini-batches,
+/// recording the mean squared error of each update to the history, if any.
+///
+fn train_model(
+    model: &mut dyn Model<U2, U1>,
+    train: &dyn Dataset<U2, U1>,
+    mini_batch: usize,
+    history: Option<&mut History>,
+    rng: StdRng,
+) {
+    let mut recorder = StepRecorder(history);
+    let mut fit = Fit::new(1);
+    fit.set_batch_size(mini_batch);
+    fit.add_callback(&mut recorder);
+    fit.run(model, train, rng).expect("Failed to train");
+}
+
+///
+/// Train a replica of the network, loaded with the given parameters, on a
+/// shard of the training data, returning the replica's parameters.
+///
+fn train_replica(
+    params: &[Fxx],
+    x: &Batch<U2>,
+    y: &Batch<U1>,
+    learning_rate: &UpdateParams,
+    mini_batch: usize,
+) -> Vec<Fxx> {
+    let mut train0 = SGDTrainer::new(learning_rate);
+    let mut m0 = LinearModel::<U2, U2>::new_random(&mut train0);
+    let mut relu = Relu::<U2, U2>::new(&mut m0);
+    let mut train1 = SGDTrainer::new(learning_rate);
+    let mut m1 = LinearModel::<U2, U1>::new_random(&mut train1);
+    let mut model = LayeredModel::<U2, U2, U1>::new(&mut relu, &mut m1);
+    model.set_params(params);
+
+    let shard = InMemoryDataset::new(
+        x.column_iter()
+            .zip(y.column_iter())
+            .map(|(xj, yj)| (xj.into_owned(), yj.into_owned()))
+            .collect(),
+    );
+    train_model(
+        &mut model,
+        &shard,
+        mini_batch,
+        None,
+        StdRng::seed_from_u64(0),
+    );
+    let mut result = Vec::new();
+    model.get_params(&mut result);
+    result
+}
+
+fn optimize_quadratic(params: &OptimizeParams, seed: u64) {
+    let mut rng = StdRng::seed_from_u64(seed);
     let learning_rate = UpdateParams {
         step_size: params.step_size,
         l2_reg: params.l2,
     };
-    let mut train0: Box<dyn GradientTrainer<U2, U2>> = if params.mini_batch > 0 {
-        Box::new(BatchTrainer::<U2, U2>::new(
-            &learning_rate,
-            params.mini_batch,
-        ))
-    } else {
-        Box::new(SGDTrainer::new(&learning_rate))
-    };
-    let mut m0 = LinearModel::<U2, U2>::new_random(&mut *train0);
+    let mut train0 = SGDTrainer::new(&learning_rate);
+    let mut m0 = LinearModel::<U2, U2>::new_random_with_rng(&mut train0, &mut rng);
     let mut relu = Relu::<U2, U2>::new(&mut m0);
 
-    let mut train1: Box<dyn GradientTrainer<U2, U1>> = if params.mini_batch > 0 {
-        Box::new(BatchTrainer::<U2, U1>::new(
-            &learning_rate,
-            params.mini_batch,
-        ))
+    let mut train1 = SGDTrainer::new(&learning_rate);
+    let mut m1 = LinearModel::<U2, U1>::new_random_with_rng(&mut train1, &mut rng);
+
+    let health = RefCell::new(Health::new(params.health.unwrap_or(Action::Warn)));
+    let mut layer0 = Monitor::new("relu", &mut relu, &health, Activation::Relu);
+    let mut layer1 = Monitor::new("linear", &mut m1, &health, Activation::Linear);
+    let mut model = LayeredModel::<U2, U2, U1>::new(&mut layer0, &mut layer1);
+
+    let parallel = if params.threads > 0 {
+        Some(ParallelTrainer::new(params.threads).expect("Failed to create workers"))
     } else {
-        Box::new(SGDTrainer::new(&learning_rate))
+        None
     };
-    let mut m1 = LinearModel::<U2, U1>::new_random(&mut *train1);
-    let mut model = LayeredModel::<U2, U2, U1>::new(&mut relu, &mut m1);
+    let mut train_time = Duration::default();
+
+    // the identity unless fit
+    let mut x_scale = Standardize::<U2>::new();
+    let mut y_scale = Standardize::<U1>::new();
+    if params.standardize {
+        let calibration = InMemoryDataset::from_fn(sample_input(STANDARDIZE_SAMPLES, &mut rng), f);
+        fit_transform(&calibration, Some(&mut x_scale), Some(&mut y_scale))
+            .expect("Failed to fit scaling");
+    }
+
+    let mut history = params.history.as_ref().map(|_| {
+        let mut history = History::new();
+        history.set_learning_rate(params.step_size);
+        history
+    });
+
+    let mut events = params
+        .events
+        .as_ref()
+        .map(|dir| EventWriter::create(dir).expect("Failed to create event file"));
+
+    let mut stopping = EarlyStopping::new(params.patience);
+    stopping.set_min_delta(params.min_delta);
 
     let mut i = 0;
     while i < params.max_iter {
         i += 1;
-        let sample = sample_input(params.test_batch + params.train_batch);
-        let (train, test) = sample.split_at(params.train_batch);
-        for x in train {
-            let y = f(&x);
-            let yh = model.predict(&x);
-            let e = model.update(&x, &y);
-            let yh1 = model.predict(&x);
-            debug!(
-                "({},{}) -> {}/{} e={} de={} delta={}",
-                x[0],
-                x[1],
-                y[0],
-                yh[0],
-                Matrix::norm(&(yh - y)),
-                Matrix::norm(&e),
-                Matrix::norm(&(yh - y)) - Matrix::norm(&(yh1 - y)),
-            );
+        let sample = sample_input(params.test_batch + params.train_batch, &mut rng);
+        let (train, test) = InMemoryDataset::new(
+            sample
+                .iter()
+                .map(|x| (x_scale.apply(x), y_scale.apply(&f(x))))
+                .collect(),
+        )
+        .split_at(params.train_batch);
+        let train_rng = StdRng::seed_from_u64(rng.gen());
+        let start = Instant::now();
+        match &parallel {
+            Some(parallel) => {
+                let loader = DataLoader::new(&train, train.len(), train_rng)
+                    .expect("Failed to create loader");
+                let (x, y) = loader.batch(&(0..train.len()).collect::<Vec<usize>>());
+                parallel
+                    .train(&mut model, &x, &y, |ps, xs, ys| {
+                        train_replica(ps, xs, ys, &learning_rate, params.mini_batch)
+                    })
+                    .expect("Parallel training failed");
+            }
+            None => train_model(
+                &mut model,
+                &train,
+                params.mini_batch,
+                history.as_mut(),
+                train_rng,
+            ),
         }
+        train_time += start.elapsed();
 
         let error_sums = test
+            .get_examples()
             .iter()
-            .map(|x| {
-                let y = f(x);
-                let yh = model.predict(x);
-                let n = Matrix::norm(&(yh - y));
+            .map(|(x, y)| {
+                let yh = y_scale.invert(&model.predict(x));
+                let n = Matrix::norm(&(yh - y_scale.invert(y)));
                 (n, n * n)
             })
             .fold((0.0, 0.0), |sum, i| (sum.0 + i.0, sum.1 + i.1));
 
         let mean_error = error_sums.0 / (params.test_batch as Fxx);
         let mean2_error = error_sums.1 / (params.test_batch as Fxx);
+        let error_std = (mean2_error - mean_error.powi(2)).sqrt();
+        println!("{} {} {}", i * params.train_batch, mean_error, error_std);
+        if let Some(history) = history.as_mut() {
+            history.record_epoch(
+                &model,
+                &[
+                    ("samples", (i * params.train_batch) as Fxx),
+                    ("mean_error", mean_error),
+                    ("error_std", error_std),
+                ],
+            );
+        }
+        if let Some(events) = events.as_mut() {
+            let samples = i * params.train_batch;
+            let mut model_params = Vec::new();
+            model.get_params(&mut model_params);
+            events
+                .add_scalar("mean_error", mean_error, samples)
+                .and_then(|_| events.add_scalar("error_std", error_std, samples))
+                .and_then(|_| events.add_histogram("params", &model_params, samples))
+                .expect("Failed to write events");
+        }
+        if params.patience > 0 && !stopping.step(&model, mean_error) {
+            break;
+        }
+    }
+
+    let secs = train_time.as_secs_f64();
+    println!(
+        "# trained {} samples in {:.3}s, {:.0} samples/s",
+        i * params.train_batch,
+        secs,
+        (i * params.train_batch) as f64 / secs
+    );
+    if parallel.is_none() {
+        for line in health.borrow().to_string().lines() {
+            println!("# {}", line);
+        }
+    }
+    if params.patience > 0 {
+        stopping.restore(&mut model);
         println!(
-            "{} {} {}",
-            i * params.train_batch,
-            mean_error,
-            (mean2_error - mean_error.powi(2)).sqrt()
+            "# stopped after {} iterations ({:?}), restored iteration {} mean error {:?}",
+            stopping.get_epoch(),
+            stopping.get_stop_reason(),
+            stopping.get_best_epoch(),
+            stopping.get_best()
         );
     }
+    if let Some(mut events) = events {
+        events.flush().expect("Failed to write events");
+    }
+    if let (Some(history), Some(path)) = (history, &params.history) {
+        let mut writer = BufWriter::new(File::create(path).expect("Failed to create history"));
+        let written = if path.extension().is_some_and(|ext| ext == "jsonl") {
+            history.write_jsonl(&mut writer)
+        } else {
+            history.write_csv(&mut writer)
+        };
+        written.expect("Failed to write history");
+    }
 }
 
 //
@@ -130,9 +337,22 @@ fn optimize_quadratic(params: &OptimizeParams) {
 fn main() {
     env_logger::init();
     let params = OptimizeParams::from_args();
+    if params.threads > 0 && (params.history.is_some() || params.health.is_some()) {
+        Error::with_description(
+            "--threads can't be combined with --history or --health",
+            ErrorKind::ArgumentConflict,
+        )
+        .exit();
+    }
+    let seed = params.seed.unwrap_or_else(|| rand::thread_rng().gen());
     println!(
-        "# step={} n_train={} n_test={} mini_batch={}",
-        params.step_size, params.train_batch, params.test_batch, params.mini_batch
+        "# step={} n_train={} n_test={} mini_batch={} threads={} seed={}",
+        params.step_size,
+        params.train_batch,
+        params.test_batch,
+        params.mini_batch,
+        params.threads,
+        seed
     );
-    optimize_quadratic(&params);
+    optimize_quadratic(&params, seed);
 }
@@ -0,0 +1,98 @@
+use lair::{Error, Fxx, History, Phase, Plot, Scale};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "plot history",
+    about = "Plot a value recorded in training histories, one series per run."
+)]
+struct PlotParams {
+    /// The recorded value to plot.
+    #[structopt(short = "y", long = "value", default_value = "mean_error")]
+    value: String,
+    /// A recorded value to plot against, e.g. samples, rather than the step
+    /// or epoch number.
+    #[structopt(short = "x", long = "versus")]
+    versus: Option<String>,
+    /// Plot records of each update rather than of each epoch.
+    #[structopt(long = "steps")]
+    steps: bool,
+    #[structopt(long = "log-x")]
+    log_x: bool,
+    #[structopt(long = "log-y")]
+    log_y: bool,
+    #[structopt(long = "title", default_value = "")]
+    title: String,
+    #[structopt(long = "width", default_value = "640")]
+    width: u32,
+    #[structopt(long = "height", default_value = "480")]
+    height: u32,
+    /// The plot to write, as SVG if it ends in .svg and otherwise as an image
+    /// in the format of its extension, e.g. PNG.
+    #[structopt(short = "o", long = "output", default_value = "history.svg")]
+    output: String,
+    /// Histories written by `fit_quad --history`, labeled by file name.
+    #[structopt(parse(from_os_str), required = true)]
+    histories: Vec<PathBuf>,
+}
+
+fn read_history(path: &Path) -> Result<History, Error> {
+    let file = File::open(path)?;
+    if path.extension().is_some_and(|ext| ext == "jsonl") {
+        History::read_jsonl(BufReader::new(file))
+    } else {
+        History::read_csv(file)
+    }
+}
+
+fn main() {
+    let params = PlotParams::from_args();
+    let phase = if params.steps {
+        Phase::Step
+    } else {
+        Phase::Epoch
+    };
+    let mut plot = Plot::new(params.width, params.height);
+    plot.set_title(&params.title);
+    plot.set_y_label(&params.value);
+    if params.log_x {
+        plot.set_x_scale(Scale::Log);
+    }
+    if params.log_y {
+        plot.set_y_scale(Scale::Log);
+    }
+
+    for path in &params.histories {
+        let history = read_history(path)
+            .unwrap_or_else(|err| panic!("Failed to read {}: {}", path.display(), err));
+        let label = path
+            .file_stem()
+            .map_or(String::new(), |stem| stem.to_string_lossy().into_owned());
+        match &params.versus {
+            Some(versus) => {
+                let points: Vec<(Fxx, Fxx)> = history
+                    .get_records()
+                    .iter()
+                    .filter(|r| r.phase == phase)
+                    .filter_map(|r| Some((r.get(versus)?, r.get(&params.value)?)))
+                    .collect();
+                plot.add_series(&label, &points);
+            }
+            None => plot.add_history(&label, &history, phase, &params.value),
+        }
+    }
+    plot.set_x_label(params.versus.as_deref().unwrap_or(match phase {
+        Phase::Step => "step",
+        Phase::Epoch => "epoch",
+    }));
+
+    let written = if params.output.ends_with(".svg") {
+        plot.write_svg(&params.output)
+    } else {
+        plot.write_image(&params.output)
+    };
+    written.expect("Failed to write plot");
+}
@@ -0,0 +1,468 @@
+extern crate nalgebra as na;
+
+use std::collections::BTreeSet;
+use std::error;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use log::debug;
+
+use na::allocator::Allocator;
+use na::{DVector, DefaultAllocator, DimName, VectorN};
+
+use crate::data::InMemoryDataset;
+use crate::error::Error;
+use crate::model::Fxx;
+
+/// Field values, besides the empty string, read as missing.
+const MISSING_VALUES: [&str; 3] = ["NA", "NaN", "?"];
+
+///
+/// Identify a CSV column by its header or its 0-based position.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub enum ColumnRef {
+    Index(usize),
+    Name(String),
+}
+
+///
+/// How the values of a column become vector components.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub enum Encoding {
+    /// A single component parsed from the field.
+    Numeric,
+    /// One component per category, 1 for the field's category and 0 for the
+    /// rest.  An empty list uses the sorted distinct values of the column.
+    OneHot(Vec<String>),
+}
+
+///
+/// A column to load and its encoding.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct CsvColumn {
+    pub column: ColumnRef,
+    pub encoding: Encoding,
+}
+
+impl CsvColumn {
+    pub fn numeric(column: ColumnRef) -> Self {
+        CsvColumn {
+            column,
+            encoding: Encoding::Numeric,
+        }
+    }
+
+    pub fn one_hot(column: ColumnRef, categories: Vec<String>) -> Self {
+        CsvColumn {
+            column,
+            encoding: Encoding::OneHot(categories),
+        }
+    }
+}
+
+///
+/// The treatment of rows with missing (empty, "NA", "NaN" or "?") fields.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Missing {
+    /// Skip the row.
+    Drop,
+    /// Replace missing numeric fields with the value.  Missing categorical
+    /// fields encode as all zeros.
+    Impute(Fxx),
+    /// Replace missing numeric fields with the mean of the column's present
+    /// values.  Missing categorical fields encode as all zeros.
+    ImputeMean,
+}
+
+///
+/// Errors reading CSV data, located by 1-based line number and column, and
+/// reported by `CsvLoader` as `Error::Csv`.
+///
+#[derive(Debug)]
+pub enum CsvError {
+    Io(io::Error),
+    /// Malformed CSV, e.g. rows of differing length.
+    Format(String),
+    /// A column reference not found in the header.
+    UnknownColumn(String),
+    /// A row too short to hold a selected column.
+    MissingField {
+        line: u64,
+        column: String,
+    },
+    Parse {
+        line: u64,
+        column: String,
+        value: String,
+    },
+    UnknownCategory {
+        line: u64,
+        column: String,
+        value: String,
+    },
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CsvError::Io(err) => write!(f, "CSV read failed: {}", err),
+            CsvError::Format(msg) => write!(f, "malformed CSV: {}", msg),
+            CsvError::UnknownColumn(column) => write!(f, "unknown CSV column {}", column),
+            CsvError::MissingField { line, column } => {
+                write!(f, "line {} column {}: missing field", line, column)
+            }
+            CsvError::Parse {
+                line,
+                column,
+                value,
+            } => write!(
+                f,
+                "line {} column {}: cannot parse '{}' as a number",
+                line, column, value
+            ),
+            CsvError::UnknownCategory {
+                line,
+                column,
+                value,
+            } => write!(
+                f,
+                "line {} column {}: unknown category '{}'",
+                line, column, value
+            ),
+        }
+    }
+}
+
+impl error::Error for CsvError {}
+
+impl From<io::Error> for CsvError {
+    fn from(err: io::Error) -> Self {
+        CsvError::Io(err)
+    }
+}
+
+impl From<csv::Error> for CsvError {
+    fn from(err: csv::Error) -> Self {
+        if err.is_io_error() {
+            match err.into_kind() {
+                csv::ErrorKind::Io(err) => CsvError::Io(err),
+                _ => unreachable!(),
+            }
+        } else {
+            CsvError::Format(err.to_string())
+        }
+    }
+}
+
+impl From<csv::Error> for Error {
+    fn from(err: csv::Error) -> Self {
+        CsvError::from(err).into()
+    }
+}
+
+///
+/// Rows encoded as feature and target components.
+///
+pub type CsvRows = Vec<(Vec<Fxx>, Vec<Fxx>)>;
+
+/// An example of dimensions determined when read.
+pub type DynamicExample = (DVector<Fxx>, DVector<Fxx>);
+
+///
+/// Read examples from CSV data, selecting and encoding feature and target
+/// columns.
+///
+pub struct CsvLoader {
+    features: Vec<CsvColumn>,
+    targets: Vec<CsvColumn>,
+    has_header: bool,
+    delimiter: u8,
+    missing: Missing,
+}
+
+/// A selected column resolved to its position in the row.
+struct Selected {
+    index: usize,
+    label: String,
+    encoding: Encoding,
+}
+
+impl CsvLoader {
+    ///
+    /// Create a loader of comma-delimited data with a header row, dropping
+    /// rows with missing fields.
+    ///
+    /// # Arguments
+    /// * `features` - the columns encoded, in order, into model inputs.
+    /// * `targets` - the columns encoded, in order, into model outputs.
+    ///
+    pub fn new(features: Vec<CsvColumn>, targets: Vec<CsvColumn>) -> Self {
+        CsvLoader {
+            features,
+            targets,
+            has_header: true,
+            delimiter: b',',
+            missing: Missing::Drop,
+        }
+    }
+
+    pub fn set_has_header(&mut self, has_header: bool) {
+        self.has_header = has_header;
+    }
+
+    pub fn set_delimiter(&mut self, delimiter: u8) {
+        self.delimiter = delimiter;
+    }
+
+    pub fn set_missing(&mut self, missing: Missing) {
+        self.missing = missing;
+    }
+
+    ///
+    /// Read examples into fixed-size vectors, failing with `Error::Shape` if
+    /// the encoded features or targets don't have M or N components.
+    ///
+    pub fn load<M, N, R>(&self, reader: R) -> Result<InMemoryDataset<M, N>, Error>
+    where
+        M: DimName,
+        N: DimName,
+        R: io::Read,
+        DefaultAllocator: Allocator<Fxx, M> + Allocator<Fxx, N>,
+    {
+        let (rows, (num_features, num_targets)) = self.read_rows(reader)?;
+        check_dimension("features", M::dim(), num_features)?;
+        check_dimension("targets", N::dim(), num_targets)?;
+        Ok(InMemoryDataset::new(
+            rows.into_iter()
+                .map(|(x, y)| {
+                    (
+                        VectorN::<Fxx, M>::from_vec(x),
+                        VectorN::<Fxx, N>::from_vec(y),
+                    )
+                })
+                .collect(),
+        ))
+    }
+
+    ///
+    /// Read examples from a CSV file into fixed-size vectors.
+    ///
+    pub fn load_path<M, N, P>(&self, path: P) -> Result<InMemoryDataset<M, N>, Error>
+    where
+        M: DimName,
+        N: DimName,
+        P: AsRef<Path>,
+        DefaultAllocator: Allocator<Fxx, M> + Allocator<Fxx, N>,
+    {
+        self.load(io::BufReader::new(File::open(path)?))
+    }
+
+    ///
+    /// Read examples into vectors sized by the encoded columns.
+    ///
+    pub fn load_dynamic<R: io::Read>(&self, reader: R) -> Result<Vec<DynamicExample>, Error> {
+        let (rows, _) = self.read_rows(reader)?;
+        Ok(rows
+            .into_iter()
+            .map(|(x, y)| (DVector::<Fxx>::from_vec(x), DVector::<Fxx>::from_vec(y)))
+            .collect())
+    }
+
+    ///
+    /// Read and encode the selected columns of each row, returning the rows
+    /// and the number of feature and target components.
+    ///
+    pub fn read_rows<R: io::Read>(&self, reader: R) -> Result<(CsvRows, (usize, usize)), Error> {
+        let mut csv_reader = csv::ReaderBuilder::new()
+            .has_headers(self.has_header)
+            .delimiter(self.delimiter)
+            .from_reader(reader);
+        let headers = if self.has_header {
+            Some(csv_reader.headers()?.clone())
+        } else {
+            None
+        };
+
+        let resolve = |columns: &[CsvColumn]| -> Result<Vec<Selected>, CsvError> {
+            columns
+                .iter()
+                .map(|c| resolve_column(c, headers.as_ref()))
+                .collect()
+        };
+        let mut features = resolve(&self.features)?;
+        let mut targets = resolve(&self.targets)?;
+
+        // Collect the selected fields, None if missing, with line numbers.
+        let mut fields: Vec<(u64, Vec<Option<String>>)> = Vec::new();
+        for record in csv_reader.records() {
+            let record = record?;
+            let line = record.position().map_or(0, |p| p.line());
+            let row = features
+                .iter()
+                .chain(targets.iter())
+                .map(|c| match record.get(c.index) {
+                    Some(value) => Ok(field_value(value)),
+                    None => Err(CsvError::MissingField {
+                        line,
+                        column: c.label.clone(),
+                    }),
+                })
+                .collect::<Result<Vec<Option<String>>, CsvError>>()?;
+            if self.missing == Missing::Drop && row.iter().any(Option::is_none) {
+                debug!("dropping line {} with missing values", line);
+            } else {
+                fields.push((line, row));
+            }
+        }
+
+        // Discover categories and column means.
+        let num_features = features.len();
+        let mut means = Vec::new();
+        for (j, c) in features.iter_mut().chain(targets.iter_mut()).enumerate() {
+            let present = fields
+                .iter()
+                .filter_map(|(line, row)| row[j].as_ref().map(|value| (*line, value)));
+            match &mut c.encoding {
+                Encoding::OneHot(categories) if categories.is_empty() => {
+                    let distinct: BTreeSet<&String> = present.map(|(_, value)| value).collect();
+                    *categories = distinct.into_iter().cloned().collect();
+                    means.push(0.0);
+                }
+                Encoding::Numeric if self.missing == Missing::ImputeMean => {
+                    let mut sum = 0.0;
+                    let mut count = 0;
+                    for (line, value) in present {
+                        sum += parse_field(line, &c.label, value)?;
+                        count += 1;
+                    }
+                    means.push(if count > 0 { sum / count as Fxx } else { 0.0 });
+                }
+                _ => means.push(0.0),
+            }
+        }
+
+        let width = |columns: &[Selected]| columns.iter().map(encoded_width).sum();
+        let dims = (width(&features), width(&targets));
+        let mut rows = Vec::with_capacity(fields.len());
+        for (line, row) in fields.iter() {
+            let mut x = Vec::with_capacity(dims.0);
+            let mut y = Vec::with_capacity(dims.1);
+            for (j, c) in features.iter().chain(targets.iter()).enumerate() {
+                let dest = if j < num_features { &mut x } else { &mut y };
+                let imputed = match self.missing {
+                    Missing::Impute(value) => value,
+                    _ => means[j],
+                };
+                self.encode(*line, c, row[j].as_ref(), imputed, dest)?;
+            }
+            rows.push((x, y));
+        }
+        debug!(
+            "read {} rows of {}+{} components",
+            rows.len(),
+            dims.0,
+            dims.1
+        );
+        Ok((rows, dims))
+    }
+
+    fn encode(
+        &self,
+        line: u64,
+        column: &Selected,
+        value: Option<&String>,
+        imputed: Fxx,
+        dest: &mut Vec<Fxx>,
+    ) -> Result<(), CsvError> {
+        match (&column.encoding, value) {
+            (Encoding::Numeric, Some(value)) => dest.push(parse_field(line, &column.label, value)?),
+            (Encoding::Numeric, None) => dest.push(imputed),
+            (Encoding::OneHot(categories), value) => {
+                let mut one_hot = vec![0.0; categories.len()];
+                if let Some(value) = value {
+                    match categories.iter().position(|c| c == value) {
+                        Some(k) => one_hot[k] = 1.0,
+                        None => {
+                            return Err(CsvError::UnknownCategory {
+                                line,
+                                column: column.label.clone(),
+                                value: value.clone(),
+                            })
+                        }
+                    }
+                }
+                dest.extend(one_hot);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn check_dimension(name: &str, expected: usize, found: usize) -> Result<(), Error> {
+    if expected == found {
+        Ok(())
+    } else {
+        Err(Error::Shape {
+            name: name.to_string(),
+            expected,
+            found,
+        })
+    }
+}
+
+fn encoded_width(column: &Selected) -> usize {
+    match &column.encoding {
+        Encoding::Numeric => 1,
+        Encoding::OneHot(categories) => categories.len(),
+    }
+}
+
+fn field_value(value: &str) -> Option<String> {
+    let value = value.trim();
+    if value.is_empty() || MISSING_VALUES.contains(&value) {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+fn parse_field(line: u64, column: &str, value: &str) -> Result<Fxx, CsvError> {
+    value.parse::<Fxx>().map_err(|_| CsvError::Parse {
+        line,
+        column: column.to_string(),
+        value: value.to_string(),
+    })
+}
+
+fn resolve_column(
+    column: &CsvColumn,
+    headers: Option<&csv::StringRecord>,
+) -> Result<Selected, CsvError> {
+    let (index, label) = match (&column.column, headers) {
+        (ColumnRef::Index(i), Some(headers)) => match headers.get(*i) {
+            Some(name) => (*i, name.to_string()),
+            None => return Err(CsvError::UnknownColumn(i.to_string())),
+        },
+        (ColumnRef::Index(i), None) => (*i, i.to_string()),
+        (ColumnRef::Name(name), Some(headers)) => match headers.iter().position(|h| h == name) {
+            Some(i) => (i, name.clone()),
+            None => return Err(CsvError::UnknownColumn(name.clone())),
+        },
+        (ColumnRef::Name(name), None) => return Err(CsvError::UnknownColumn(name.clone())),
+    };
+    Ok(Selected {
+        index,
+        label,
+        encoding: column.encoding.clone(),
+    })
+}
+
+#[cfg(test)]
+#[path = "./csv_dataset_test.rs"]
+mod csv_dataset_test;
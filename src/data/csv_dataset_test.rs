@@ -0,0 +1,163 @@
+use super::*;
+
+use na::{U1, U2, U4};
+
+use crate::data::Dataset;
+use crate::Error;
+
+const IRIS: &str = "\
+sepal_length,sepal_width,species,weight
+5.1,3.5,setosa,1.0
+4.9,,versicolor,2.0
+6.2,2.9,virginica,NA
+5.9,3.0,setosa,4.0
+";
+
+fn name(column: &str) -> ColumnRef {
+    ColumnRef::Name(column.to_string())
+}
+
+#[test]
+fn loads_named_columns() {
+    let loader = CsvLoader::new(
+        vec![
+            CsvColumn::numeric(name("sepal_length")),
+            CsvColumn::numeric(ColumnRef::Index(1)),
+        ],
+        vec![CsvColumn::numeric(name("weight"))],
+    );
+    let dataset = loader.load::<U2, U1, _>(IRIS.as_bytes()).unwrap();
+    // rows with missing values are dropped
+    assert_eq!(dataset.len(), 2);
+    let (x, y) = dataset.get(1);
+    assert_eq!((x[0], x[1], y[0]), (5.9, 3.0, 4.0));
+}
+
+#[test]
+fn imputes_missing_values() {
+    let mut loader = CsvLoader::new(
+        vec![CsvColumn::numeric(name("sepal_width"))],
+        vec![CsvColumn::numeric(name("weight"))],
+    );
+    loader.set_missing(Missing::Impute(-1.0));
+    let rows = loader.load_dynamic(IRIS.as_bytes()).unwrap();
+    assert_eq!(rows.len(), 4);
+    assert_eq!(rows[1].0[0], -1.0);
+    assert_eq!(rows[2].1[0], -1.0);
+
+    loader.set_missing(Missing::ImputeMean);
+    let rows = loader.load_dynamic(IRIS.as_bytes()).unwrap();
+    assert_eq!(rows[1].0[0], (3.5 + 2.9 + 3.0) / 3.0);
+    assert_eq!(rows[2].1[0], (1.0 + 2.0 + 4.0) / 3.0);
+}
+
+#[test]
+fn one_hot_encodes_categories() {
+    let mut loader = CsvLoader::new(
+        vec![
+            CsvColumn::numeric(name("sepal_length")),
+            CsvColumn::one_hot(name("species"), Vec::new()),
+        ],
+        vec![CsvColumn::numeric(name("weight"))],
+    );
+    loader.set_missing(Missing::Impute(0.0));
+    let dataset = loader.load::<U4, U1, _>(IRIS.as_bytes()).unwrap();
+    assert_eq!(dataset.len(), 4);
+    let (x, _) = dataset.get(2);
+    assert_eq!(x.as_slice(), &[6.2, 0.0, 0.0, 1.0]);
+
+    let explicit = CsvLoader::new(
+        vec![CsvColumn::one_hot(
+            name("species"),
+            vec!["setosa".to_string(), "virginica".to_string()],
+        )],
+        vec![],
+    );
+    match explicit.load_dynamic(IRIS.as_bytes()) {
+        Err(Error::Csv(CsvError::UnknownCategory {
+            line,
+            column,
+            value,
+        })) => {
+            assert_eq!(
+                (line, column.as_str(), value.as_str()),
+                (3, "species", "versicolor")
+            );
+        }
+        result => panic!("expected unknown category, got {:?}", result),
+    }
+}
+
+#[test]
+fn reads_without_header() {
+    let mut loader = CsvLoader::new(
+        vec![CsvColumn::numeric(ColumnRef::Index(1))],
+        vec![CsvColumn::numeric(ColumnRef::Index(0))],
+    );
+    loader.set_has_header(false);
+    loader.set_delimiter(b';');
+    let rows = loader.load_dynamic("1;2\n3;4\n".as_bytes()).unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!((rows[1].0[0], rows[1].1[0]), (4.0, 3.0));
+
+    // columns beyond the rows are located
+    let mut loader = CsvLoader::new(vec![CsvColumn::numeric(ColumnRef::Index(2))], vec![]);
+    loader.set_has_header(false);
+    let err = loader.load_dynamic("1,2\n3,4\n".as_bytes()).unwrap_err();
+    match &err {
+        Error::Csv(CsvError::MissingField { line, column }) => {
+            assert_eq!((*line, column.as_str()), (1, "2"));
+        }
+        _ => panic!("expected missing field, got {:?}", err),
+    }
+    assert_eq!(err.to_string(), "line 1 column 2: missing field");
+}
+
+#[test]
+fn locates_parse_errors() {
+    let loader = CsvLoader::new(vec![CsvColumn::numeric(name("a"))], vec![]);
+    let err = loader
+        .load_dynamic("a,b\n1,2\nx1,3\n".as_bytes())
+        .unwrap_err();
+    match &err {
+        Error::Csv(CsvError::Parse {
+            line,
+            column,
+            value,
+        }) => {
+            assert_eq!((*line, column.as_str(), value.as_str()), (3, "a", "x1"));
+        }
+        _ => panic!("expected parse error, got {:?}", err),
+    }
+    assert_eq!(
+        err.to_string(),
+        "line 3 column a: cannot parse 'x1' as a number"
+    );
+}
+
+#[test]
+fn reports_bad_columns_and_dimensions() {
+    let loader = CsvLoader::new(vec![CsvColumn::numeric(name("missing"))], vec![]);
+    assert!(matches!(
+        loader.load_dynamic(IRIS.as_bytes()),
+        Err(Error::Csv(CsvError::UnknownColumn(_)))
+    ));
+
+    let loader = CsvLoader::new(
+        vec![CsvColumn::numeric(name("sepal_length"))],
+        vec![CsvColumn::numeric(name("weight"))],
+    );
+    assert!(matches!(
+        loader.load::<U2, U1, _>(IRIS.as_bytes()),
+        Err(Error::Shape {
+            expected: 2,
+            found: 1,
+            ..
+        })
+    ));
+
+    assert!(matches!(
+        loader.load_dynamic("sepal_length,weight\n1,2\n3\n".as_bytes()),
+        Err(Error::Csv(CsvError::Format(_)))
+    ));
+}
@@ -0,0 +1,209 @@
+extern crate nalgebra as na;
+
+use log::debug;
+
+use na::allocator::Allocator;
+use na::{DefaultAllocator, DimName, Dynamic};
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+
+use crate::data::{Dataset, Example};
+use crate::error::Error;
+use crate::model::{Batch, Fxx};
+
+///
+/// Iterate over the examples of a dataset in epochs, each visiting every
+/// example once, optionally in shuffled order, either one example at a time
+/// for `Model::update` or in mini-batches for `Model::update_batch`.
+///
+pub struct DataLoader<'a, M: DimName, N: DimName> {
+    dataset: &'a dyn Dataset<M, N>,
+    batch_size: usize,
+    drop_last: bool,
+    shuffle: bool,
+    rng: StdRng,
+}
+
+impl<'a, M, N> DataLoader<'a, M, N>
+where
+    M: DimName,
+    N: DimName,
+    DefaultAllocator: Allocator<Fxx, M>
+        + Allocator<Fxx, N>
+        + Allocator<Fxx, M, Dynamic>
+        + Allocator<Fxx, N, Dynamic>,
+{
+    ///
+    /// Create a loader shuffling each epoch and keeping the final partial
+    /// mini-batch, failing if the batch size is zero.
+    ///
+    /// # Arguments
+    /// * `dataset` - the examples to load.
+    /// * `batch_size` - the number of examples per mini-batch.
+    /// * `rng` - the source of the shuffled orders.
+    ///
+    pub fn new(
+        dataset: &'a dyn Dataset<M, N>,
+        batch_size: usize,
+        rng: StdRng,
+    ) -> Result<Self, Error> {
+        if batch_size == 0 {
+            return Err(Error::Config("batch size must be positive".to_string()));
+        }
+        Ok(DataLoader {
+            dataset,
+            batch_size,
+            drop_last: false,
+            shuffle: true,
+            rng,
+        })
+    }
+
+    pub fn get_batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    ///
+    /// Skip the final mini-batch of each epoch if it would hold fewer than
+    /// `batch_size` examples.
+    ///
+    pub fn set_drop_last(&mut self, drop_last: bool) {
+        self.drop_last = drop_last;
+    }
+
+    ///
+    /// Visit examples in a new random order each epoch, or in index order.
+    ///
+    pub fn set_shuffle(&mut self, shuffle: bool) {
+        self.shuffle = shuffle;
+    }
+
+    ///
+    /// The number of mini-batches per epoch.
+    ///
+    pub fn num_batches(&self) -> usize {
+        let n = self.dataset.len();
+        if self.drop_last {
+            n / self.batch_size
+        } else {
+            n.div_ceil(self.batch_size)
+        }
+    }
+
+    fn epoch_order(&mut self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.dataset.len()).collect();
+        if self.shuffle {
+            order.shuffle(&mut self.rng);
+        }
+        order
+    }
+
+    ///
+    /// Gather the examples at the given indices into a mini-batch, one
+    /// example per column.
+    ///
+    pub fn batch(&self, indices: &[usize]) -> (Batch<M>, Batch<N>) {
+        gather(self.dataset, indices)
+    }
+
+    ///
+    /// The mini-batches of one epoch.
+    ///
+    pub fn epoch(&mut self) -> impl Iterator<Item = (Batch<M>, Batch<N>)> + 'a {
+        let dataset = self.dataset;
+        let batch_size = self.batch_size;
+        let drop_last = self.drop_last;
+        let order = self.epoch_order();
+        debug!("epoch of {} examples by {}", order.len(), batch_size);
+        let batches: Vec<Vec<usize>> = order
+            .chunks(batch_size)
+            .filter(|chunk| !drop_last || chunk.len() == batch_size)
+            .map(|chunk| chunk.to_vec())
+            .collect();
+        batches
+            .into_iter()
+            .map(move |indices| gather(dataset, &indices))
+    }
+
+    ///
+    /// The examples of one epoch, one at a time.
+    ///
+    pub fn samples(&mut self) -> impl Iterator<Item = Example<M, N>> + 'a {
+        let dataset = self.dataset;
+        self.epoch_order().into_iter().map(move |i| dataset.get(i))
+    }
+
+    ///
+    /// An endless sequence of mini-batches, starting a new epoch whenever the
+    /// previous one is exhausted.  Ends only if the dataset can't fill a
+    /// mini-batch.
+    ///
+    pub fn stream(&mut self) -> Stream<'_, 'a, M, N> {
+        Stream {
+            loader: self,
+            order: Vec::new(),
+            pos: 0,
+        }
+    }
+}
+
+fn gather<M, N>(dataset: &dyn Dataset<M, N>, indices: &[usize]) -> (Batch<M>, Batch<N>)
+where
+    M: DimName,
+    N: DimName,
+    DefaultAllocator: Allocator<Fxx, M>
+        + Allocator<Fxx, N>
+        + Allocator<Fxx, M, Dynamic>
+        + Allocator<Fxx, N, Dynamic>,
+{
+    let mut xs = Batch::<M>::zeros(indices.len());
+    let mut ys = Batch::<N>::zeros(indices.len());
+    for (j, &i) in indices.iter().enumerate() {
+        let (x, y) = dataset.get(i);
+        xs.set_column(j, &x);
+        ys.set_column(j, &y);
+    }
+    (xs, ys)
+}
+
+///
+/// Mini-batches over successive epochs, see `DataLoader::stream`.
+///
+pub struct Stream<'b, 'a, M: DimName, N: DimName> {
+    loader: &'b mut DataLoader<'a, M, N>,
+    order: Vec<usize>,
+    pos: usize,
+}
+
+impl<'b, 'a, M, N> Iterator for Stream<'b, 'a, M, N>
+where
+    M: DimName,
+    N: DimName,
+    DefaultAllocator: Allocator<Fxx, M>
+        + Allocator<Fxx, N>
+        + Allocator<Fxx, M, Dynamic>
+        + Allocator<Fxx, N, Dynamic>,
+{
+    type Item = (Batch<M>, Batch<N>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let batch_size = self.loader.batch_size;
+        let remaining = self.order.len() - self.pos;
+        if remaining == 0 || (self.loader.drop_last && remaining < batch_size) {
+            if self.loader.num_batches() == 0 {
+                return None;
+            }
+            self.order = self.loader.epoch_order();
+            self.pos = 0;
+        }
+        let end = (self.pos + batch_size).min(self.order.len());
+        let batch = self.loader.batch(&self.order[self.pos..end]);
+        self.pos = end;
+        Some(batch)
+    }
+}
+
+#[cfg(test)]
+#[path = "./data_loader_test.rs"]
+mod data_loader_test;
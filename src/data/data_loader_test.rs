@@ -0,0 +1,106 @@
+use super::*;
+
+use na::{Vector1, Vector2, U1, U2};
+use rand::SeedableRng;
+
+use crate::data::InMemoryDataset;
+use crate::{LinearModel, Model, SGDTrainer, UpdateParams};
+
+fn examples(n: usize) -> InMemoryDataset<U2, U1> {
+    let xs = (0..n).map(|i| Vector2::<Fxx>::new(i as Fxx, 1.0)).collect();
+    InMemoryDataset::from_fn(xs, |x| Vector1::<Fxx>::new(x[0]))
+}
+
+#[test]
+fn visits_each_example_per_epoch() {
+    let dataset = examples(10);
+    let mut loader = DataLoader::new(&dataset, 3, StdRng::seed_from_u64(1)).unwrap();
+    assert_eq!(loader.num_batches(), 4);
+
+    let batches: Vec<_> = loader.epoch().collect();
+    let sizes: Vec<usize> = batches.iter().map(|(x, _)| x.ncols()).collect();
+    assert_eq!(sizes, vec![3, 3, 3, 1]);
+
+    let mut seen: Vec<Fxx> = batches
+        .iter()
+        .flat_map(|(x, y)| {
+            assert_eq!(x.row(0), y.row(0));
+            x.row(0).iter().cloned().collect::<Vec<Fxx>>()
+        })
+        .collect();
+    assert_ne!(seen, (0..10).map(|i| i as Fxx).collect::<Vec<Fxx>>());
+    seen.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    assert_eq!(seen, (0..10).map(|i| i as Fxx).collect::<Vec<Fxx>>());
+}
+
+#[test]
+fn rejects_empty_batches() {
+    let dataset = examples(3);
+    let loader = DataLoader::new(&dataset, 0, StdRng::seed_from_u64(1));
+    assert!(matches!(loader, Err(Error::Config(_))));
+}
+
+#[test]
+fn drops_last_partial_batch() {
+    let dataset = examples(10);
+    let mut loader = DataLoader::new(&dataset, 3, StdRng::seed_from_u64(2)).unwrap();
+    loader.set_drop_last(true);
+    assert_eq!(loader.num_batches(), 3);
+    assert!(loader.epoch().all(|(x, _)| x.ncols() == 3));
+    assert_eq!(loader.epoch().count(), 3);
+}
+
+#[test]
+fn keeps_order_without_shuffle() {
+    let dataset = examples(4);
+    let mut loader = DataLoader::new(&dataset, 2, StdRng::seed_from_u64(3)).unwrap();
+    loader.set_shuffle(false);
+    let xs: Vec<Fxx> = loader.samples().map(|(x, _)| x[0]).collect();
+    assert_eq!(xs, vec![0.0, 1.0, 2.0, 3.0]);
+}
+
+#[test]
+fn streams_across_epochs() {
+    let dataset = examples(5);
+    let mut loader = DataLoader::new(&dataset, 2, StdRng::seed_from_u64(4)).unwrap();
+    let sizes: Vec<usize> = loader.stream().take(6).map(|(x, _)| x.ncols()).collect();
+    assert_eq!(sizes, vec![2, 2, 1, 2, 2, 1]);
+
+    loader.set_drop_last(true);
+    let sizes: Vec<usize> = loader.stream().take(4).map(|(x, _)| x.ncols()).collect();
+    assert_eq!(sizes, vec![2, 2, 2, 2]);
+}
+
+#[test]
+fn ends_stream_without_full_batch() {
+    let dataset = examples(1);
+    let mut loader = DataLoader::new(&dataset, 2, StdRng::seed_from_u64(5)).unwrap();
+    loader.set_drop_last(true);
+    assert_eq!(loader.stream().count(), 0);
+}
+
+#[test]
+fn trains_model() {
+    let params = UpdateParams {
+        step_size: 0.01,
+        l2_reg: 0.0,
+    };
+    let mut rng = StdRng::seed_from_u64(6);
+    let mut trainer = SGDTrainer::new(&params);
+    let mut model = LinearModel::<U2, U1>::new_random_with_rng(&mut trainer, &mut rng);
+    let dataset = examples(8);
+    let mut loader = DataLoader::new(&dataset, 4, rng).unwrap();
+
+    let (x, y) = loader.batch(&[0, 1, 2, 3, 4, 5, 6, 7]);
+    let e0 = (model.predict_batch(&x) - &y).norm();
+    for _ in 0..5 {
+        for (x, y) in loader.samples() {
+            model.update(&x, &y);
+        }
+        for (x, y) in loader.epoch() {
+            model.update_batch(&x, &y);
+        }
+    }
+    let e1 = (model.predict_batch(&x) - &y).norm();
+    assert!(e1 < e0, "failed to improve {} -> {}", e0, e1);
+}
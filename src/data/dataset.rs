@@ -0,0 +1,111 @@
+extern crate nalgebra as na;
+
+use na::allocator::Allocator;
+use na::{DefaultAllocator, DimName, VectorN};
+
+use crate::model::Fxx;
+
+/// A training example, a model input and its observed output.
+pub type Example<M, N> = (VectorN<Fxx, M>, VectorN<Fxx, N>);
+
+///
+/// An indexed collection of training examples, pairs of model input and
+/// observed output.
+///
+pub trait Dataset<M: DimName, N: DimName> {
+    /// The number of examples.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The example at index i, in [0, len()).
+    ///
+    /// # Arguments
+    /// * `i` - the index of the example.
+    fn get(&self, i: usize) -> Example<M, N>
+    where
+        DefaultAllocator: Allocator<Fxx, M> + Allocator<Fxx, N>;
+}
+
+///
+/// A dataset holding all of its examples in memory.
+///
+pub struct InMemoryDataset<M, N>
+where
+    M: DimName,
+    N: DimName,
+    DefaultAllocator: Allocator<Fxx, M> + Allocator<Fxx, N>,
+{
+    examples: Vec<Example<M, N>>,
+}
+
+impl<M, N> InMemoryDataset<M, N>
+where
+    M: DimName,
+    N: DimName,
+    DefaultAllocator: Allocator<Fxx, M> + Allocator<Fxx, N>,
+{
+    pub fn new(examples: Vec<Example<M, N>>) -> Self {
+        InMemoryDataset { examples }
+    }
+
+    ///
+    /// Label each input with the output of f, e.g. to sample a known function.
+    ///
+    /// # Arguments
+    /// * `xs` - the inputs.
+    /// * `f` - computes the observation for each input.
+    ///
+    pub fn from_fn<F>(xs: Vec<VectorN<Fxx, M>>, f: F) -> Self
+    where
+        F: Fn(&VectorN<Fxx, M>) -> VectorN<Fxx, N>,
+    {
+        InMemoryDataset {
+            examples: xs
+                .into_iter()
+                .map(|x| {
+                    let y = f(&x);
+                    (x, y)
+                })
+                .collect(),
+        }
+    }
+
+    pub fn get_examples(&self) -> &[Example<M, N>] {
+        &self.examples
+    }
+
+    pub fn push(&mut self, x: VectorN<Fxx, M>, y: VectorN<Fxx, N>) {
+        self.examples.push((x, y));
+    }
+
+    ///
+    /// Split into the first n examples and the rest, e.g. into training and
+    /// test sets.
+    ///
+    pub fn split_at(mut self, n: usize) -> (Self, Self) {
+        let rest = self.examples.split_off(n);
+        (self, InMemoryDataset { examples: rest })
+    }
+}
+
+impl<M, N> Dataset<M, N> for InMemoryDataset<M, N>
+where
+    M: DimName,
+    N: DimName,
+    DefaultAllocator: Allocator<Fxx, M> + Allocator<Fxx, N>,
+{
+    fn len(&self) -> usize {
+        self.examples.len()
+    }
+
+    fn get(&self, i: usize) -> Example<M, N> {
+        self.examples[i].clone()
+    }
+}
+
+#[cfg(test)]
+#[path = "./dataset_test.rs"]
+mod dataset_test;
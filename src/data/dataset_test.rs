@@ -0,0 +1,35 @@
+use super::*;
+
+use na::{Vector1, Vector2, U1, U2};
+
+fn examples() -> InMemoryDataset<U2, U1> {
+    let xs = (0..5).map(|i| Vector2::<Fxx>::new(i as Fxx, 1.0)).collect();
+    InMemoryDataset::from_fn(xs, |x| Vector1::<Fxx>::new(2.0 * x[0] + x[1]))
+}
+
+#[test]
+fn labels_inputs() {
+    let dataset = examples();
+    assert_eq!(dataset.len(), 5);
+    assert!(!dataset.is_empty());
+    let (x, y) = dataset.get(3);
+    assert_eq!(x, Vector2::<Fxx>::new(3.0, 1.0));
+    assert_eq!(y[0], 7.0);
+}
+
+#[test]
+fn pushes_examples() {
+    let mut dataset = InMemoryDataset::<U2, U1>::new(Vec::new());
+    assert!(dataset.is_empty());
+    dataset.push(Vector2::<Fxx>::new(1.0, 2.0), Vector1::<Fxx>::new(3.0));
+    assert_eq!(dataset.len(), 1);
+    assert_eq!(dataset.get_examples()[0].1[0], 3.0);
+}
+
+#[test]
+fn splits_examples() {
+    let (train, test) = examples().split_at(4);
+    assert_eq!(train.len(), 4);
+    assert_eq!(test.len(), 1);
+    assert_eq!(test.get(0).0[0], 4.0);
+}
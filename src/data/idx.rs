@@ -0,0 +1,183 @@
+extern crate nalgebra as na;
+
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::path::Path;
+
+use log::debug;
+
+use na::allocator::Allocator;
+use na::{DefaultAllocator, DimName, VectorN};
+
+use crate::data::InMemoryDataset;
+use crate::error::Error;
+use crate::model::Fxx;
+
+const UBYTE_MAX: Fxx = 255.0;
+
+///
+/// An array read from the IDX format used by MNIST and Fashion-MNIST,
+/// http://yann.lecun.com/exdb/mnist/, its values in row-major order.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct IdxArray {
+    pub dims: Vec<usize>,
+    pub data: Vec<Fxx>,
+    /// The type code from the header, e.g. 0x08 for unsigned bytes.
+    pub type_code: u8,
+}
+
+/// The most elements allocated before they're read.
+const MAX_PREALLOCATED: usize = 1 << 20;
+
+fn invalid(msg: String) -> Error {
+    Error::Io(io::Error::new(io::ErrorKind::InvalidData, msg))
+}
+
+fn read_be<R: Read, const B: usize>(reader: &mut R) -> io::Result<[u8; B]> {
+    let mut buf = [0u8; B];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+///
+/// Read an IDX array of any element type from uncompressed data.
+///
+pub fn read_idx<R: Read>(reader: &mut R) -> Result<IdxArray, Error> {
+    let magic = read_be::<_, 4>(reader)?;
+    if magic[0] != 0 || magic[1] != 0 {
+        return Err(invalid(format!("invalid IDX magic number {:?}", magic)));
+    }
+    let type_code = magic[2];
+    let num_dims = magic[3] as usize;
+    let dims = (0..num_dims)
+        .map(|_| read_be::<_, 4>(reader).map(|b| u32::from_be_bytes(b) as usize))
+        .collect::<io::Result<Vec<usize>>>()?;
+    let len = dims
+        .iter()
+        .try_fold(1usize, |len, &d| len.checked_mul(d))
+        .ok_or_else(|| invalid(format!("IDX dimensions {:?} overflow", dims)))?;
+    debug!("reading IDX type {:#x} dims {:?}", type_code, dims);
+
+    // the header may claim more than the data holds, so grow as it's read
+    let mut data = Vec::with_capacity(len.min(MAX_PREALLOCATED));
+    for _ in 0..len {
+        let x = match type_code {
+            0x08 => read_be::<_, 1>(reader)?[0] as Fxx,
+            0x09 => read_be::<_, 1>(reader)?[0] as i8 as Fxx,
+            0x0B => i16::from_be_bytes(read_be(reader)?) as Fxx,
+            0x0C => i32::from_be_bytes(read_be(reader)?) as Fxx,
+            0x0D => f32::from_be_bytes(read_be(reader)?) as Fxx,
+            0x0E => f64::from_be_bytes(read_be(reader)?) as Fxx,
+            _ => return Err(invalid(format!("unknown IDX type {:#x}", type_code))),
+        };
+        data.push(x);
+    }
+    Ok(IdxArray {
+        dims,
+        data,
+        type_code,
+    })
+}
+
+///
+/// Read IDX images, e.g. MNIST's 28x28 unsigned byte images, into vectors
+/// scaled to [0, 1] in the column-major layout of `img::read_luma`
+/// matrices.
+///
+pub fn read_idx_images<M, R>(reader: &mut R) -> Result<Vec<VectorN<Fxx, M>>, Error>
+where
+    M: DimName,
+    R: Read,
+    DefaultAllocator: Allocator<Fxx, M>,
+{
+    let array = read_idx(reader)?;
+    if array.dims.len() != 3 {
+        return Err(invalid(format!(
+            "expected images of 3 dimensions, found {:?}",
+            array.dims
+        )));
+    }
+    let (rows, cols) = (array.dims[1], array.dims[2]);
+    if rows.checked_mul(cols) != Some(M::dim()) {
+        return Err(Error::Shape {
+            name: format!("{}x{} IDX image", rows, cols),
+            expected: M::dim(),
+            found: rows.saturating_mul(cols),
+        });
+    }
+    let scale = if array.type_code == 0x08 {
+        1.0 / UBYTE_MAX
+    } else {
+        1.0
+    };
+    Ok(array
+        .data
+        .chunks(M::dim())
+        .map(|img| VectorN::<Fxx, M>::from_fn(|i, _| scale * img[(i % rows) * cols + i / rows]))
+        .collect())
+}
+
+///
+/// Read IDX labels, integers counting from 0, as one-hot vectors.
+///
+pub fn read_idx_labels<N, R>(reader: &mut R) -> Result<Vec<VectorN<Fxx, N>>, Error>
+where
+    N: DimName,
+    R: Read,
+    DefaultAllocator: Allocator<Fxx, N>,
+{
+    let array = read_idx(reader)?;
+    if array.dims.len() != 1 {
+        return Err(invalid(format!(
+            "expected labels of 1 dimension, found {:?}",
+            array.dims
+        )));
+    }
+    array
+        .data
+        .iter()
+        .map(|&label| {
+            // NaN and fractions aren't classes
+            if label.fract() != 0.0 || label < 0.0 || label >= N::dim() as Fxx {
+                Err(invalid(format!(
+                    "label {} isn't one of {} classes",
+                    label,
+                    N::dim()
+                )))
+            } else {
+                let mut y = VectorN::<Fxx, N>::zeros();
+                y[label as usize] = 1.0;
+                Ok(y)
+            }
+        })
+        .collect()
+}
+
+///
+/// Read uncompressed IDX image and label files, e.g. MNIST's
+/// train-images-idx3-ubyte and train-labels-idx1-ubyte, as examples.
+///
+pub fn read_mnist<M, N, P>(images: P, labels: P) -> Result<InMemoryDataset<M, N>, Error>
+where
+    M: DimName,
+    N: DimName,
+    P: AsRef<Path>,
+    DefaultAllocator: Allocator<Fxx, M> + Allocator<Fxx, N>,
+{
+    let xs = read_idx_images::<M, _>(&mut io::BufReader::new(File::open(images)?))?;
+    let ys = read_idx_labels::<N, _>(&mut io::BufReader::new(File::open(labels)?))?;
+    if xs.len() != ys.len() {
+        return Err(invalid(format!(
+            "{} images but {} labels",
+            xs.len(),
+            ys.len()
+        )));
+    }
+    Ok(InMemoryDataset::new(xs.into_iter().zip(ys).collect()))
+}
+
+#[cfg(test)]
+#[path = "./idx_test.rs"]
+mod idx_test;
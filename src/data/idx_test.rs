@@ -0,0 +1,125 @@
+use super::*;
+
+use std::fs;
+
+use assert_approx_eq::assert_approx_eq;
+
+use na::{MatrixMN, U10, U2, U3, U6};
+
+use crate::data::Dataset;
+
+/// Two 2x3 unsigned byte images.
+fn image_bytes() -> Vec<u8> {
+    let mut bytes = vec![0, 0, 0x08, 3, 0, 0, 0, 2, 0, 0, 0, 2, 0, 0, 0, 3];
+    bytes.extend(&[0, 51, 102, 153, 204, 255]);
+    bytes.extend(&[255, 0, 0, 0, 0, 0]);
+    bytes
+}
+
+fn label_bytes() -> Vec<u8> {
+    vec![0, 0, 0x08, 1, 0, 0, 0, 2, 7, 0]
+}
+
+#[test]
+fn reads_array() {
+    let bytes = vec![
+        0, 0, 0x0B, 2, 0, 0, 0, 1, 0, 0, 0, 2, 0xFF, 0xFE, 0x01, 0x00,
+    ];
+    let array = read_idx(&mut bytes.as_slice()).unwrap();
+    assert_eq!(array.dims, vec![1, 2]);
+    assert_eq!(array.data, vec![-2.0, 256.0]);
+}
+
+#[test]
+fn rejects_bad_header() {
+    let bytes = vec![1, 0, 0x08, 1, 0, 0, 0, 0];
+    assert!(read_idx(&mut bytes.as_slice()).is_err());
+    let bytes = vec![0, 0, 0x0A, 1, 0, 0, 0, 1, 0];
+    assert!(read_idx(&mut bytes.as_slice()).is_err());
+    let truncated = &image_bytes()[..20];
+    assert!(read_idx(&mut &truncated[..]).is_err());
+}
+
+#[test]
+fn rejects_oversized_header() {
+    // dimensions whose product overflows
+    let mut bytes = vec![0, 0, 0x08, 3];
+    bytes.extend_from_slice(&[0xFF; 12]);
+    match read_idx(&mut bytes.as_slice()) {
+        Err(Error::Io(err)) => assert_eq!(err.kind(), io::ErrorKind::InvalidData),
+        result => panic!("expected invalid data, got {:?}", result),
+    }
+    // dimensions far larger than the data, which must not be allocated
+    let bytes = vec![
+        0, 0, 0x08, 2, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 1,
+    ];
+    match read_idx(&mut bytes.as_slice()) {
+        Err(Error::Io(err)) => assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof),
+        result => panic!("expected end of file, got {:?}", result),
+    }
+}
+
+#[test]
+fn reads_images_column_major() {
+    let images = read_idx_images::<U6, _>(&mut image_bytes().as_slice()).unwrap();
+    assert_eq!(images.len(), 2);
+    let expected = MatrixMN::<Fxx, U2, U3>::from_row_slice(&[0.0, 0.2, 0.4, 0.6, 0.8, 1.0]);
+    for (x, e) in images[0].iter().zip(expected.iter()) {
+        assert_approx_eq!(x, e);
+    }
+    assert_eq!(images[1][0], 1.0);
+
+    assert!(matches!(
+        read_idx_images::<U3, _>(&mut image_bytes().as_slice()),
+        Err(Error::Shape {
+            expected: 3,
+            found: 6,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn reads_one_hot_labels() {
+    let labels = read_idx_labels::<U10, _>(&mut label_bytes().as_slice()).unwrap();
+    assert_eq!(labels.len(), 2);
+    assert_eq!(labels[0][7], 1.0);
+    assert_eq!(labels[0].sum(), 1.0);
+    assert_eq!(labels[1][0], 1.0);
+
+    assert!(read_idx_labels::<U3, _>(&mut label_bytes().as_slice()).is_err());
+}
+
+#[test]
+fn rejects_non_integer_labels() {
+    for label in [2.7f32, f32::NAN, -0.5, f32::INFINITY] {
+        let mut bytes = vec![0, 0, 0x0D, 1, 0, 0, 0, 1];
+        bytes.extend_from_slice(&label.to_be_bytes());
+        assert!(
+            read_idx_labels::<U10, _>(&mut bytes.as_slice()).is_err(),
+            "label {}",
+            label
+        );
+    }
+    let mut bytes = vec![0, 0, 0x0D, 1, 0, 0, 0, 1];
+    bytes.extend_from_slice(&2.0f32.to_be_bytes());
+    let labels = read_idx_labels::<U10, _>(&mut bytes.as_slice()).unwrap();
+    assert_eq!(labels[0][2], 1.0);
+}
+
+#[test]
+fn reads_mnist_files() {
+    let dir = std::env::temp_dir().join(format!("lair-idx-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let images = dir.join("images-idx3-ubyte");
+    let labels = dir.join("labels-idx1-ubyte");
+    fs::write(&images, image_bytes()).unwrap();
+    fs::write(&labels, label_bytes()).unwrap();
+
+    let dataset = read_mnist::<U6, U10, _>(&images, &labels).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(dataset.len(), 2);
+    let (x, y) = dataset.get(1);
+    assert_eq!(x[0], 1.0);
+    assert_eq!(y[0], 1.0);
+}
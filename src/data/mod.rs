@@ -0,0 +1,19 @@
+pub mod csv_dataset;
+pub mod data_loader;
+pub mod dataset;
+pub mod idx;
+pub mod split;
+pub mod transform;
+pub use csv_dataset::{
+    ColumnRef, CsvColumn, CsvError, CsvLoader, DynamicExample, Encoding, Missing,
+};
+pub use data_loader::{DataLoader, Stream};
+pub use dataset::{Dataset, Example, InMemoryDataset};
+pub use idx::{read_idx, read_idx_images, read_idx_labels, read_mnist, IdxArray};
+pub use split::{
+    class_of, cross_validate, k_folds, stratified_k_folds, stratified_split,
+    train_validation_test_split, CrossValidation, Scores, Split, Subset,
+};
+pub use transform::{
+    fit_transform, read_params, write_params, MinMaxScale, PcaWhiten, Standardize, Transform,
+};
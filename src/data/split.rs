@@ -0,0 +1,355 @@
+extern crate nalgebra as na;
+
+use na::allocator::Allocator;
+use na::{DefaultAllocator, DimName, VectorN};
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use log::debug;
+
+use crate::data::{Dataset, Example};
+use crate::error::Error;
+use crate::model::Fxx;
+
+///
+/// The examples of a dataset at the given indices, without copying.
+///
+pub struct Subset<'a, M: DimName, N: DimName> {
+    dataset: &'a dyn Dataset<M, N>,
+    indices: Vec<usize>,
+}
+
+impl<'a, M: DimName, N: DimName> Subset<'a, M, N> {
+    pub fn new(dataset: &'a dyn Dataset<M, N>, indices: Vec<usize>) -> Self {
+        Subset { dataset, indices }
+    }
+
+    /// The indices into the underlying dataset.
+    pub fn get_indices(&self) -> &[usize] {
+        &self.indices
+    }
+}
+
+impl<'a, M: DimName, N: DimName> Dataset<M, N> for Subset<'a, M, N> {
+    fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    fn get(&self, i: usize) -> Example<M, N>
+    where
+        DefaultAllocator: Allocator<Fxx, M> + Allocator<Fxx, N>,
+    {
+        self.dataset.get(self.indices[i])
+    }
+}
+
+///
+/// The class of a one-hot, or score, output vector, the index of its
+/// greatest component, or for a single output, e.g. a binary label, its
+/// value thresholded at 0.5.
+///
+pub fn class_of<N>(y: &VectorN<Fxx, N>) -> usize
+where
+    N: DimName,
+    DefaultAllocator: Allocator<Fxx, N>,
+{
+    if N::dim() == 1 {
+        (y[0] >= 0.5) as usize
+    } else {
+        y.imax()
+    }
+}
+
+///
+/// The indices of the examples of each class, in index order.
+///
+fn indices_by_class<M, N>(dataset: &dyn Dataset<M, N>) -> Vec<Vec<usize>>
+where
+    M: DimName,
+    N: DimName,
+    DefaultAllocator: Allocator<Fxx, M> + Allocator<Fxx, N>,
+{
+    let mut classes = vec![Vec::new(); N::dim().max(2)];
+    for i in 0..dataset.len() {
+        classes[class_of(&dataset.get(i).1)].push(i);
+    }
+    classes
+}
+
+///
+/// Indices of a dataset partitioned into training, validation and test sets.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct Split {
+    pub train: Vec<usize>,
+    pub validation: Vec<usize>,
+    pub test: Vec<usize>,
+}
+
+impl Split {
+    ///
+    /// Views of the training, validation and test examples.
+    ///
+    pub fn subsets<'a, M: DimName, N: DimName>(
+        &self,
+        dataset: &'a dyn Dataset<M, N>,
+    ) -> (Subset<'a, M, N>, Subset<'a, M, N>, Subset<'a, M, N>) {
+        (
+            Subset::new(dataset, self.train.clone()),
+            Subset::new(dataset, self.validation.clone()),
+            Subset::new(dataset, self.test.clone()),
+        )
+    }
+
+    fn extend(&mut self, mut indices: Vec<usize>, validation: Fxx, test: Fxx) {
+        let n = indices.len() as Fxx;
+        let num_test = (test * n).round() as usize;
+        let num_validation = ((validation * n).round() as usize).min(indices.len() - num_test);
+        self.test.extend(indices.drain(..num_test));
+        self.validation.extend(indices.drain(..num_validation));
+        self.train.extend(indices);
+    }
+}
+
+fn check_fractions(validation: Fxx, test: Fxx) -> Result<(), Error> {
+    if validation >= 0.0 && test >= 0.0 && validation + test <= 1.0 {
+        Ok(())
+    } else {
+        Err(Error::Config(format!(
+            "invalid split fractions {} and {}",
+            validation, test
+        )))
+    }
+}
+
+fn check_folds(n: usize, k: usize) -> Result<(), Error> {
+    if k > 1 && k <= n {
+        Ok(())
+    } else {
+        Err(Error::Config(format!(
+            "cannot split {} examples into {} folds",
+            n, k
+        )))
+    }
+}
+
+///
+/// Shuffle the indices of n examples and split them into training,
+/// validation and test sets, failing if the fractions are negative or sum
+/// to more than 1.
+///
+/// # Arguments
+/// * `n` - the number of examples.
+/// * `validation` - the fraction of examples to validate with.
+/// * `test` - the fraction of examples to test with.
+/// * `rng` - the source of the shuffled order.
+///
+pub fn train_validation_test_split<R: Rng>(
+    n: usize,
+    validation: Fxx,
+    test: Fxx,
+    rng: &mut R,
+) -> Result<Split, Error> {
+    check_fractions(validation, test)?;
+    let mut indices: Vec<usize> = (0..n).collect();
+    indices.shuffle(rng);
+    let mut split = Split {
+        train: Vec::new(),
+        validation: Vec::new(),
+        test: Vec::new(),
+    };
+    split.extend(indices, validation, test);
+    Ok(split)
+}
+
+///
+/// Split the examples of a classification dataset, by the class of their
+/// one-hot outputs, so that each class is represented in each set in about
+/// the proportions of the whole dataset.  Fails as
+/// `train_validation_test_split` does.
+///
+pub fn stratified_split<M, N, R>(
+    dataset: &dyn Dataset<M, N>,
+    validation: Fxx,
+    test: Fxx,
+    rng: &mut R,
+) -> Result<Split, Error>
+where
+    M: DimName,
+    N: DimName,
+    R: Rng,
+    DefaultAllocator: Allocator<Fxx, M> + Allocator<Fxx, N>,
+{
+    check_fractions(validation, test)?;
+    let mut split = Split {
+        train: Vec::new(),
+        validation: Vec::new(),
+        test: Vec::new(),
+    };
+    for mut indices in indices_by_class(dataset) {
+        indices.shuffle(rng);
+        split.extend(indices, validation, test);
+    }
+    Ok(split)
+}
+
+///
+/// Shuffle the indices of n examples into k folds of sizes differing by at
+/// most one, failing unless 1 < k <= n.
+///
+pub fn k_folds<R: Rng>(n: usize, k: usize, rng: &mut R) -> Result<Vec<Vec<usize>>, Error> {
+    check_folds(n, k)?;
+    let mut indices: Vec<usize> = (0..n).collect();
+    indices.shuffle(rng);
+    let mut folds = vec![Vec::new(); k];
+    for (i, j) in indices.into_iter().enumerate() {
+        folds[i % k].push(j);
+    }
+    Ok(folds)
+}
+
+///
+/// Shuffle the indices of a classification dataset into k folds, dealing the
+/// examples of each class across the folds in turn so that each fold has
+/// about the class proportions of the whole dataset.  Fails as `k_folds`
+/// does.
+///
+pub fn stratified_k_folds<M, N, R>(
+    dataset: &dyn Dataset<M, N>,
+    k: usize,
+    rng: &mut R,
+) -> Result<Vec<Vec<usize>>, Error>
+where
+    M: DimName,
+    N: DimName,
+    R: Rng,
+    DefaultAllocator: Allocator<Fxx, M> + Allocator<Fxx, N>,
+{
+    check_folds(dataset.len(), k)?;
+    let mut folds = vec![Vec::new(); k];
+    let mut next = 0;
+    for mut indices in indices_by_class(dataset) {
+        indices.shuffle(rng);
+        for j in indices {
+            folds[next % k].push(j);
+            next += 1;
+        }
+    }
+    Ok(folds)
+}
+
+/// Named evaluation metrics, e.g. "mse" or "accuracy", of a trained model.
+pub type Scores = BTreeMap<String, Fxx>;
+
+///
+/// The scores of each fold of a cross-validation.
+///
+#[derive(Clone, Debug, Default)]
+pub struct CrossValidation {
+    pub folds: Vec<Scores>,
+}
+
+impl CrossValidation {
+    /// The mean of a metric over the folds reporting it.
+    pub fn mean(&self, metric: &str) -> Option<Fxx> {
+        let values = self.values(metric);
+        if values.is_empty() {
+            None
+        } else {
+            Some(values.iter().sum::<Fxx>() / values.len() as Fxx)
+        }
+    }
+
+    /// The sample standard deviation of a metric over the folds reporting it.
+    pub fn std(&self, metric: &str) -> Option<Fxx> {
+        let values = self.values(metric);
+        let mean = self.mean(metric)?;
+        if values.len() < 2 {
+            return Some(0.0);
+        }
+        let ss: Fxx = values.iter().map(|v| (v - mean).powi(2)).sum();
+        Some((ss / (values.len() - 1) as Fxx).sqrt())
+    }
+
+    /// The mean and standard deviation of each metric.
+    pub fn summary(&self) -> BTreeMap<String, (Fxx, Fxx)> {
+        self.folds
+            .iter()
+            .flat_map(|scores| scores.keys())
+            .filter_map(|metric| Some((metric.clone(), (self.mean(metric)?, self.std(metric)?))))
+            .collect()
+    }
+
+    fn values(&self, metric: &str) -> Vec<Fxx> {
+        self.folds
+            .iter()
+            .filter_map(|scores| scores.get(metric).copied())
+            .collect()
+    }
+}
+
+impl fmt::Display for CrossValidation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, scores) in self.folds.iter().enumerate() {
+            write!(f, "fold {}:", i)?;
+            for (metric, value) in scores {
+                write!(f, " {}={}", metric, value)?;
+            }
+            writeln!(f)?;
+        }
+        write!(f, "mean:")?;
+        for (metric, (mean, std)) in self.summary() {
+            write!(f, " {}={}±{}", metric, mean, std)?;
+        }
+        Ok(())
+    }
+}
+
+///
+/// Cross-validate over the folds, holding out each fold in turn as the
+/// validation set of a model trained on the others.
+///
+/// Models borrow their trainers, so rather than returning a model the
+/// closure builds a fresh one, trains it on the training subset and returns
+/// its scores on the validation subset.
+///
+/// # Arguments
+/// * `dataset` - the examples indexed by the folds.
+/// * `folds` - disjoint index sets, e.g. from `k_folds`.
+/// * `fit_and_score` - trains a new model and scores it, given the training
+///   and validation subsets.
+///
+pub fn cross_validate<M, N, F>(
+    dataset: &dyn Dataset<M, N>,
+    folds: &[Vec<usize>],
+    mut fit_and_score: F,
+) -> CrossValidation
+where
+    M: DimName,
+    N: DimName,
+    F: FnMut(&Subset<M, N>, &Subset<M, N>) -> Scores,
+{
+    let mut result = CrossValidation::default();
+    for (i, fold) in folds.iter().enumerate() {
+        let train = folds
+            .iter()
+            .enumerate()
+            .filter(|&(j, _)| j != i)
+            .flat_map(|(_, f)| f.iter().copied())
+            .collect();
+        let train = Subset::new(dataset, train);
+        let validation = Subset::new(dataset, fold.clone());
+        let scores = fit_and_score(&train, &validation);
+        debug!("fold {} of {}: {:?}", i, folds.len(), scores);
+        result.folds.push(scores);
+    }
+    result
+}
+
+#[cfg(test)]
+#[path = "./split_test.rs"]
+mod split_test;
@@ -0,0 +1,147 @@
+use super::*;
+
+use assert_approx_eq::assert_approx_eq;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use na::{Vector1, Vector2, U1, U2};
+
+use crate::data::InMemoryDataset;
+
+/// 30 examples of class 0 and 10 of class 1, with input equal to the index.
+fn classes() -> InMemoryDataset<U1, U2> {
+    InMemoryDataset::from_fn((0..40).map(|i| Vector1::new(i as Fxx)).collect(), |x| {
+        if x[0] < 30.0 {
+            Vector2::new(1.0, 0.0)
+        } else {
+            Vector2::new(0.0, 1.0)
+        }
+    })
+}
+
+/// The same classes as binary labels of a single output.
+fn binary_classes() -> InMemoryDataset<U1, U1> {
+    InMemoryDataset::from_fn((0..40).map(|i| Vector1::new(i as Fxx)).collect(), |x| {
+        Vector1::new((x[0] >= 30.0) as usize as Fxx)
+    })
+}
+
+fn sorted(indices: &[usize]) -> Vec<usize> {
+    let mut indices = indices.to_vec();
+    indices.sort_unstable();
+    indices
+}
+
+#[test]
+fn views_subsets() {
+    let dataset = classes();
+    let subset = Subset::new(&dataset, vec![3, 35]);
+    assert_eq!(subset.len(), 2);
+    assert_eq!(subset.get(1), dataset.get(35));
+    assert_eq!(subset.get_indices(), &[3, 35]);
+    assert_eq!(class_of(&subset.get(1).1), 1);
+}
+
+#[test]
+fn splits_three_ways() {
+    let mut rng = StdRng::seed_from_u64(1);
+    let split = train_validation_test_split(40, 0.25, 0.1, &mut rng).unwrap();
+    assert_eq!(split.test.len(), 4);
+    assert_eq!(split.validation.len(), 10);
+    assert_eq!(split.train.len(), 26);
+    let all: Vec<usize> = split
+        .train
+        .iter()
+        .chain(split.validation.iter())
+        .chain(split.test.iter())
+        .copied()
+        .collect();
+    assert_eq!(sorted(&all), (0..40).collect::<Vec<usize>>());
+    assert_ne!(sorted(&split.train), split.train);
+}
+
+#[test]
+fn stratifies_splits() {
+    let mut rng = StdRng::seed_from_u64(2);
+    let dataset = classes();
+    let split = stratified_split(&dataset, 0.2, 0.2, &mut rng).unwrap();
+    let (train, validation, test) = split.subsets(&dataset);
+    assert_eq!((train.len(), validation.len(), test.len()), (24, 8, 8));
+    for subset in [validation, test].iter() {
+        let minority = (0..subset.len())
+            .filter(|&i| class_of(&subset.get(i).1) == 1)
+            .count();
+        assert_eq!(minority, 2);
+    }
+}
+
+#[test]
+fn folds_cover_examples() {
+    let mut rng = StdRng::seed_from_u64(3);
+    let folds = k_folds(10, 3, &mut rng).unwrap();
+    assert_eq!(
+        folds.iter().map(|f| f.len()).collect::<Vec<usize>>(),
+        vec![4, 3, 3]
+    );
+    let all: Vec<usize> = folds.concat();
+    assert_eq!(sorted(&all), (0..10).collect::<Vec<usize>>());
+
+    let dataset = classes();
+    for fold in stratified_k_folds(&dataset, 5, &mut rng).unwrap() {
+        assert_eq!(fold.len(), 8);
+        assert_eq!(fold.iter().filter(|&&i| i >= 30).count(), 2);
+    }
+}
+
+#[test]
+fn stratifies_binary_labels() {
+    let mut rng = StdRng::seed_from_u64(5);
+    let dataset = binary_classes();
+    assert_eq!(class_of(&dataset.get(35).1), 1);
+    assert_eq!(class_of(&dataset.get(3).1), 0);
+    let split = stratified_split(&dataset, 0.2, 0.2, &mut rng).unwrap();
+    assert_eq!(split.test.iter().filter(|&&i| i >= 30).count(), 2);
+    assert_eq!(split.validation.iter().filter(|&&i| i >= 30).count(), 2);
+    for fold in stratified_k_folds(&dataset, 5, &mut rng).unwrap() {
+        assert_eq!(fold.iter().filter(|&&i| i >= 30).count(), 2);
+    }
+}
+
+#[test]
+fn rejects_invalid_splits() {
+    let mut rng = StdRng::seed_from_u64(4);
+    assert!(matches!(k_folds(3, 4, &mut rng), Err(Error::Config(_))));
+    assert!(matches!(k_folds(3, 1, &mut rng), Err(Error::Config(_))));
+    assert!(stratified_k_folds(&classes(), 41, &mut rng).is_err());
+    assert!(matches!(
+        train_validation_test_split(10, 0.6, 0.5, &mut rng),
+        Err(Error::Config(_))
+    ));
+    assert!(stratified_split(&classes(), -0.1, 0.2, &mut rng).is_err());
+}
+
+#[test]
+fn cross_validates() {
+    let mut rng = StdRng::seed_from_u64(5);
+    let dataset = classes();
+    let folds = k_folds(dataset.len(), 4, &mut rng).unwrap();
+    let result = cross_validate(&dataset, &folds, |train, validation| {
+        // a "model" predicting the mean training input
+        assert_eq!(train.len() + validation.len(), 40);
+        let mean = (0..train.len()).map(|i| train.get(i).0[0]).sum::<Fxx>() / train.len() as Fxx;
+        let mut scores = Scores::new();
+        scores.insert("mean".to_string(), mean);
+        scores.insert("size".to_string(), validation.len() as Fxx);
+        scores
+    });
+    assert_eq!(result.folds.len(), 4);
+    assert_eq!(result.mean("size"), Some(10.0));
+    assert_eq!(result.std("size"), Some(0.0));
+    // each example is held out once, so the training means average to the
+    // overall mean
+    assert_approx_eq!(result.mean("mean").unwrap(), 19.5, 1e-4);
+    assert!(result.std("mean").unwrap() > 0.0);
+    assert_eq!(result.mean("missing"), None);
+    assert_eq!(result.summary().len(), 2);
+    assert!(format!("{}", result).contains("mean: mean="));
+}
@@ -0,0 +1,459 @@
+extern crate nalgebra as na;
+
+use na::allocator::Allocator;
+use na::{DMatrix, DVector, DefaultAllocator, DimName, VectorN};
+
+use std::io;
+use std::io::{Read, Write};
+
+use log::debug;
+
+use crate::data::{Dataset, InMemoryDataset};
+use crate::error::Error;
+use crate::model::Fxx;
+
+///
+/// A preprocessing transformation with statistics fit to data, e.g. to
+/// scale model inputs, or regression targets whose predictions are then
+/// mapped back by `invert`.
+///
+/// Fit parameters are flattened by `get_params` and `set_params` in the
+/// manner of `Model`, so that they can be written by `write_params` together
+/// with the parameters of the model they preprocess for.
+///
+pub trait Transform<M: DimName>
+where
+    DefaultAllocator: Allocator<Fxx, M>,
+{
+    /// Learn the statistics of the transformation from samples, failing if
+    /// there are none.
+    ///
+    /// # Arguments
+    /// * `xs` - the samples.
+    fn fit(&mut self, xs: &[VectorN<Fxx, M>]) -> Result<(), Error>;
+
+    /// Transform a sample.
+    fn apply(&self, x: &VectorN<Fxx, M>) -> VectorN<Fxx, M>;
+
+    /// Undo `apply`.
+    fn invert(&self, y: &VectorN<Fxx, M>) -> VectorN<Fxx, M>;
+
+    /// Append the fit statistics to params.
+    fn get_params(&self, params: &mut Vec<Fxx>);
+
+    /// Overwrite the fit statistics from the front of params in the order of
+    /// `get_params`, returning the unused remainder.
+    fn set_params<'p>(&mut self, params: &'p [Fxx]) -> &'p [Fxx];
+}
+
+fn split_vector<M>(params: &[Fxx]) -> (VectorN<Fxx, M>, &[Fxx])
+where
+    M: DimName,
+    DefaultAllocator: Allocator<Fxx, M>,
+{
+    let (head, rest) = params.split_at(M::dim());
+    (VectorN::<Fxx, M>::from_column_slice(head), rest)
+}
+
+fn check_samples<M>(xs: &[VectorN<Fxx, M>]) -> Result<(), Error>
+where
+    M: DimName,
+    DefaultAllocator: Allocator<Fxx, M>,
+{
+    if xs.is_empty() {
+        Err(Error::Config("cannot fit to no samples".to_string()))
+    } else {
+        Ok(())
+    }
+}
+
+fn mean<M>(xs: &[VectorN<Fxx, M>]) -> Result<VectorN<Fxx, M>, Error>
+where
+    M: DimName,
+    DefaultAllocator: Allocator<Fxx, M>,
+{
+    check_samples(xs)?;
+    Ok(xs
+        .iter()
+        .fold(VectorN::<Fxx, M>::zeros(), |acc, x| acc + x)
+        .unscale(xs.len() as Fxx))
+}
+
+///
+/// Z-score standardization, shifting each component to zero mean and
+/// scaling it to unit standard deviation.  Constant components are only
+/// shifted.  The identity until fit.
+///
+pub struct Standardize<M>
+where
+    M: DimName,
+    DefaultAllocator: Allocator<Fxx, M>,
+{
+    mean: VectorN<Fxx, M>,
+    std: VectorN<Fxx, M>,
+}
+
+impl<M> Standardize<M>
+where
+    M: DimName,
+    DefaultAllocator: Allocator<Fxx, M>,
+{
+    pub fn new() -> Self {
+        Standardize {
+            mean: VectorN::<Fxx, M>::zeros(),
+            std: VectorN::<Fxx, M>::from_element(1.0),
+        }
+    }
+
+    pub fn get_mean(&self) -> &VectorN<Fxx, M> {
+        &self.mean
+    }
+
+    pub fn get_std(&self) -> &VectorN<Fxx, M> {
+        &self.std
+    }
+}
+
+impl<M> Default for Standardize<M>
+where
+    M: DimName,
+    DefaultAllocator: Allocator<Fxx, M>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<M> Transform<M> for Standardize<M>
+where
+    M: DimName,
+    DefaultAllocator: Allocator<Fxx, M>,
+{
+    fn fit(&mut self, xs: &[VectorN<Fxx, M>]) -> Result<(), Error> {
+        self.mean = mean(xs)?;
+        let var = xs
+            .iter()
+            .fold(VectorN::<Fxx, M>::zeros(), |acc, x| {
+                let d = x - &self.mean;
+                acc + d.component_mul(&d)
+            })
+            .unscale(xs.len() as Fxx);
+        self.std = var.map(|v| if v > 0.0 { v.sqrt() } else { 1.0 });
+        debug!("standardizing by mean {:?} std {:?}", self.mean, self.std);
+        Ok(())
+    }
+
+    fn apply(&self, x: &VectorN<Fxx, M>) -> VectorN<Fxx, M> {
+        (x - &self.mean).component_div(&self.std)
+    }
+
+    fn invert(&self, y: &VectorN<Fxx, M>) -> VectorN<Fxx, M> {
+        y.component_mul(&self.std) + &self.mean
+    }
+
+    fn get_params(&self, params: &mut Vec<Fxx>) {
+        params.extend(self.mean.iter());
+        params.extend(self.std.iter());
+    }
+
+    fn set_params<'p>(&mut self, params: &'p [Fxx]) -> &'p [Fxx] {
+        let (mean, params) = split_vector(params);
+        let (std, params) = split_vector(params);
+        self.mean = mean;
+        self.std = std;
+        params
+    }
+}
+
+///
+/// Min-max scaling of each component from its observed range onto a target
+/// range, [0, 1] by default.  Constant components map to the low end of the
+/// range.  The identity until fit.
+///
+pub struct MinMaxScale<M>
+where
+    M: DimName,
+    DefaultAllocator: Allocator<Fxx, M>,
+{
+    min: VectorN<Fxx, M>,
+    span: VectorN<Fxx, M>,
+    low: Fxx,
+    high: Fxx,
+}
+
+impl<M> MinMaxScale<M>
+where
+    M: DimName,
+    DefaultAllocator: Allocator<Fxx, M>,
+{
+    pub fn new() -> Self {
+        MinMaxScale {
+            min: VectorN::<Fxx, M>::zeros(),
+            span: VectorN::<Fxx, M>::from_element(1.0),
+            low: 0.0,
+            high: 1.0,
+        }
+    }
+
+    ///
+    /// Set the range to scale onto, e.g. [-1, 1], failing if it's empty.
+    ///
+    pub fn set_range(&mut self, low: Fxx, high: Fxx) -> Result<(), Error> {
+        if low < high {
+            self.low = low;
+            self.high = high;
+            Ok(())
+        } else {
+            Err(Error::Config(format!("empty range {}..{}", low, high)))
+        }
+    }
+}
+
+impl<M> Default for MinMaxScale<M>
+where
+    M: DimName,
+    DefaultAllocator: Allocator<Fxx, M>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<M> Transform<M> for MinMaxScale<M>
+where
+    M: DimName,
+    DefaultAllocator: Allocator<Fxx, M>,
+{
+    fn fit(&mut self, xs: &[VectorN<Fxx, M>]) -> Result<(), Error> {
+        check_samples(xs)?;
+        let mut min = xs[0].clone();
+        let mut max = xs[0].clone();
+        for x in xs.iter().skip(1) {
+            min = min.inf(x);
+            max = max.sup(x);
+        }
+        self.span = (max - &min).map(|s| if s > 0.0 { s } else { 1.0 });
+        self.min = min;
+        Ok(())
+    }
+
+    fn apply(&self, x: &VectorN<Fxx, M>) -> VectorN<Fxx, M> {
+        (x - &self.min)
+            .component_div(&self.span)
+            .map(|u| self.low + (self.high - self.low) * u)
+    }
+
+    fn invert(&self, y: &VectorN<Fxx, M>) -> VectorN<Fxx, M> {
+        y.map(|v| (v - self.low) / (self.high - self.low))
+            .component_mul(&self.span)
+            + &self.min
+    }
+
+    fn get_params(&self, params: &mut Vec<Fxx>) {
+        params.extend(self.min.iter());
+        params.extend(self.span.iter());
+        params.push(self.low);
+        params.push(self.high);
+    }
+
+    fn set_params<'p>(&mut self, params: &'p [Fxx]) -> &'p [Fxx] {
+        let (min, params) = split_vector(params);
+        let (span, params) = split_vector(params);
+        self.min = min;
+        self.span = span;
+        self.low = params[0];
+        self.high = params[1];
+        &params[2..]
+    }
+}
+
+///
+/// PCA whitening, rotating centered samples onto the principal components of
+/// their covariance, ordered by decreasing variance, and scaling each to unit
+/// variance.  Components with variance below epsilon, 1e-5 by default, are
+/// regularized by adding epsilon to every variance.
+///
+pub struct PcaWhiten<M>
+where
+    M: DimName,
+    DefaultAllocator: Allocator<Fxx, M>,
+{
+    mean: VectorN<Fxx, M>,
+    /// principal components by column
+    components: DMatrix<Fxx>,
+    variances: VectorN<Fxx, M>,
+    epsilon: Fxx,
+}
+
+impl<M> PcaWhiten<M>
+where
+    M: DimName,
+    DefaultAllocator: Allocator<Fxx, M>,
+{
+    pub fn new() -> Self {
+        PcaWhiten {
+            mean: VectorN::<Fxx, M>::zeros(),
+            components: DMatrix::identity(M::dim(), M::dim()),
+            variances: VectorN::<Fxx, M>::from_element(1.0),
+            epsilon: 1e-5,
+        }
+    }
+
+    pub fn set_epsilon(&mut self, epsilon: Fxx) {
+        self.epsilon = epsilon;
+    }
+
+    /// The principal components, by column, in order of decreasing variance.
+    pub fn get_components(&self) -> &DMatrix<Fxx> {
+        &self.components
+    }
+
+    /// The variance along each principal component.
+    pub fn get_variances(&self) -> &VectorN<Fxx, M> {
+        &self.variances
+    }
+
+    fn scales(&self) -> Vec<Fxx> {
+        self.variances
+            .iter()
+            .map(|v| (v + self.epsilon).sqrt())
+            .collect()
+    }
+}
+
+///
+/// Multiply x by the matrix, or its transpose.
+///
+fn rotate(mat: &DMatrix<Fxx>, x: &[Fxx], transpose: bool) -> Vec<Fxx> {
+    let x = DVector::from_column_slice(x);
+    let y = if transpose { mat.tr_mul(&x) } else { mat * x };
+    y.as_slice().to_vec()
+}
+
+impl<M> Default for PcaWhiten<M>
+where
+    M: DimName,
+    DefaultAllocator: Allocator<Fxx, M>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<M> Transform<M> for PcaWhiten<M>
+where
+    M: DimName,
+    DefaultAllocator: Allocator<Fxx, M>,
+{
+    fn fit(&mut self, xs: &[VectorN<Fxx, M>]) -> Result<(), Error> {
+        self.mean = mean(xs)?;
+        let n = M::dim();
+        let mut cov = DMatrix::<Fxx>::zeros(n, n);
+        for x in xs {
+            let d = DVector::from_column_slice((x - &self.mean).as_slice());
+            cov.ger(1.0, &d, &d, 1.0);
+        }
+        cov.unscale_mut(xs.len() as Fxx);
+
+        let eigen = cov.symmetric_eigen();
+        let mut order: Vec<usize> = (0..n).collect();
+        order.sort_by(|&i, &j| eigen.eigenvalues[j].total_cmp(&eigen.eigenvalues[i]));
+        self.components = DMatrix::from_fn(n, n, |r, c| eigen.eigenvectors[(r, order[c])]);
+        self.variances = VectorN::<Fxx, M>::from_fn(|i, _| eigen.eigenvalues[order[i]].max(0.0));
+        debug!("principal variances {:?}", self.variances);
+        Ok(())
+    }
+
+    fn apply(&self, x: &VectorN<Fxx, M>) -> VectorN<Fxx, M> {
+        let y = rotate(&self.components, (x - &self.mean).as_slice(), true);
+        let scales = self.scales();
+        VectorN::<Fxx, M>::from_fn(|i, _| y[i] / scales[i])
+    }
+
+    fn invert(&self, y: &VectorN<Fxx, M>) -> VectorN<Fxx, M> {
+        let scales = self.scales();
+        let y: Vec<Fxx> = y.iter().zip(scales.iter()).map(|(v, s)| v * s).collect();
+        let d = rotate(&self.components, &y, false);
+        VectorN::<Fxx, M>::from_column_slice(&d) + &self.mean
+    }
+
+    fn get_params(&self, params: &mut Vec<Fxx>) {
+        params.extend(self.mean.iter());
+        params.extend(self.variances.iter());
+        params.extend(self.components.iter());
+    }
+
+    fn set_params<'p>(&mut self, params: &'p [Fxx]) -> &'p [Fxx] {
+        let (mean, params) = split_vector(params);
+        let (variances, params) = split_vector(params);
+        let n = M::dim();
+        self.mean = mean;
+        self.variances = variances;
+        self.components = DMatrix::from_column_slice(n, n, &params[..n * n]);
+        &params[n * n..]
+    }
+}
+
+///
+/// Fit transformations of the inputs and outputs of a dataset and return
+/// the transformed examples, failing if the dataset is empty.
+///
+/// # Arguments
+/// * `dataset` - the examples to fit to and transform.
+/// * `x_transform` - the transformation of inputs, if any.
+/// * `y_transform` - the transformation of outputs, if any.
+///
+pub fn fit_transform<M, N>(
+    dataset: &dyn Dataset<M, N>,
+    x_transform: Option<&mut dyn Transform<M>>,
+    y_transform: Option<&mut dyn Transform<N>>,
+) -> Result<InMemoryDataset<M, N>, Error>
+where
+    M: DimName,
+    N: DimName,
+    DefaultAllocator: Allocator<Fxx, M> + Allocator<Fxx, N>,
+{
+    let (mut xs, mut ys): (Vec<_>, Vec<_>) = (0..dataset.len()).map(|i| dataset.get(i)).unzip();
+    if let Some(t) = x_transform {
+        t.fit(&xs)?;
+        xs = xs.iter().map(|x| t.apply(x)).collect();
+    }
+    if let Some(t) = y_transform {
+        t.fit(&ys)?;
+        ys = ys.iter().map(|y| t.apply(y)).collect();
+    }
+    Ok(InMemoryDataset::new(xs.into_iter().zip(ys).collect()))
+}
+
+///
+/// Write parameters, e.g. from `Model::get_params` and
+/// `Transform::get_params`, as text, one per line.
+///
+pub fn write_params<W: Write>(writer: &mut W, params: &[Fxx]) -> Result<(), Error> {
+    for p in params {
+        writeln!(writer, "{}", p)?;
+    }
+    Ok(())
+}
+
+///
+/// Read parameters written by `write_params`.
+///
+pub fn read_params<R: Read>(reader: &mut R) -> Result<Vec<Fxx>, Error> {
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+    text.split_whitespace()
+        .map(|token| {
+            token.parse::<Fxx>().map_err(|err| {
+                Error::Io(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid parameter {}: {}", token, err),
+                ))
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[path = "./transform_test.rs"]
+mod transform_test;
@@ -0,0 +1,138 @@
+use super::*;
+
+use assert_approx_eq::assert_approx_eq;
+
+use na::{Vector2, Vector3, U1, U2};
+
+fn samples() -> Vec<Vector2<Fxx>> {
+    vec![
+        Vector2::new(1.0, 10.0),
+        Vector2::new(3.0, 10.0),
+        Vector2::new(5.0, 10.0),
+    ]
+}
+
+fn assert_vec_eq(a: &Vector2<Fxx>, b: &Vector2<Fxx>) {
+    for i in 0..2 {
+        assert_approx_eq!(a[i], b[i], 1e-3);
+    }
+}
+
+#[test]
+fn standardizes() {
+    let mut t = Standardize::new();
+    assert_eq!(t.apply(&Vector2::new(1.0, 2.0)), Vector2::new(1.0, 2.0));
+
+    t.fit(&samples()).unwrap();
+    assert_vec_eq(t.get_mean(), &Vector2::new(3.0, 10.0));
+    // the constant component is only centered
+    assert_vec_eq(t.get_std(), &Vector2::new((8.0 as Fxx / 3.0).sqrt(), 1.0));
+    let z = t.apply(&Vector2::new(3.0, 11.0));
+    assert_vec_eq(&z, &Vector2::new(0.0, 1.0));
+    assert_vec_eq(&t.invert(&z), &Vector2::new(3.0, 11.0));
+}
+
+#[test]
+fn scales_min_max() {
+    let mut t = MinMaxScale::new();
+    t.set_range(-1.0, 1.0).unwrap();
+    t.fit(&samples()).unwrap();
+    assert_vec_eq(
+        &t.apply(&Vector2::new(1.0, 10.0)),
+        &Vector2::new(-1.0, -1.0),
+    );
+    assert_vec_eq(&t.apply(&Vector2::new(4.0, 10.0)), &Vector2::new(0.5, -1.0));
+    let x = Vector2::new(7.0, 12.0);
+    assert_vec_eq(&t.invert(&t.apply(&x)), &x);
+}
+
+#[test]
+fn whitens_correlated_samples() {
+    // samples along the diagonal, with a little spread across it
+    let xs: Vec<Vector2<Fxx>> = (0..20)
+        .map(|i| {
+            let t = i as Fxx - 9.5;
+            let e = if i % 2 == 0 { 0.1 } else { -0.1 };
+            Vector2::new(t + e, t - e)
+        })
+        .collect();
+    let mut t = PcaWhiten::new();
+    t.set_epsilon(0.0);
+    t.fit(&xs).unwrap();
+    let v = t.get_variances();
+    assert!(v[0] > v[1]);
+    // the first component lies along the diagonal
+    let c = t.get_components();
+    assert_approx_eq!(c[(0, 0)].abs(), c[(1, 0)].abs(), 1e-2);
+
+    let ys: Vec<Vector2<Fxx>> = xs.iter().map(|x| t.apply(x)).collect();
+    let mut check = Standardize::new();
+    check.fit(&ys).unwrap();
+    assert_vec_eq(check.get_mean(), &Vector2::zeros());
+    assert_vec_eq(check.get_std(), &Vector2::new(1.0, 1.0));
+    assert_vec_eq(&t.invert(&ys[3]), &xs[3]);
+}
+
+#[test]
+fn restores_params() {
+    let mut params = Vec::new();
+    let mut t = Standardize::new();
+    t.fit(&samples()).unwrap();
+    t.get_params(&mut params);
+    let mut pca = PcaWhiten::<U2>::new();
+    pca.fit(&samples()).unwrap();
+    pca.get_params(&mut params);
+    params.push(42.0);
+
+    let mut buf = Vec::new();
+    write_params(&mut buf, &params).unwrap();
+    let read = read_params(&mut buf.as_slice()).unwrap();
+    assert_eq!(read, params);
+
+    let mut t2 = Standardize::<U2>::new();
+    let mut pca2 = PcaWhiten::<U2>::new();
+    let rest = pca2.set_params(t2.set_params(&read));
+    assert_eq!(rest, &[42.0]);
+    let x = Vector2::new(2.0, 9.0);
+    assert_eq!(t2.apply(&x), t.apply(&x));
+    assert_eq!(pca2.apply(&x), pca.apply(&x));
+
+    let mut m = MinMaxScale::<U1>::new();
+    assert_eq!(m.set_params(&[0.0, 2.0, -1.0, 1.0, 7.0]), &[7.0]);
+    assert_eq!(m.apply(&na::Vector1::new(1.0))[0], 0.0);
+
+    assert!(read_params(&mut "1.0 x".as_bytes()).is_err());
+}
+
+#[test]
+fn fits_datasets() {
+    let dataset = InMemoryDataset::from_fn(samples(), |x| Vector3::new(x[0], 2.0 * x[0], 0.0));
+    let mut tx = Standardize::new();
+    let mut ty = MinMaxScale::new();
+    let scaled = fit_transform(&dataset, Some(&mut tx), Some(&mut ty)).unwrap();
+    assert_eq!(scaled.len(), 3);
+    let (x, y) = scaled.get(2);
+    assert_approx_eq!(x[0], (1.5 as Fxx).sqrt(), 1e-4);
+    assert_eq!(y, Vector3::new(1.0, 1.0, 0.0));
+    assert_eq!(ty.invert(&y), Vector3::new(5.0, 10.0, 0.0));
+
+    let unscaled = fit_transform(&dataset, None, None).unwrap();
+    assert_eq!(unscaled.get(0), dataset.get(0));
+}
+
+#[test]
+fn rejects_invalid_fits() {
+    let mut t = MinMaxScale::<U2>::new();
+    assert!(matches!(t.set_range(1.0, 1.0), Err(Error::Config(_))));
+    assert!(matches!(t.fit(&[]), Err(Error::Config(_))));
+    assert!(matches!(
+        Standardize::<U2>::new().fit(&[]),
+        Err(Error::Config(_))
+    ));
+    assert!(matches!(
+        PcaWhiten::<U2>::new().fit(&[]),
+        Err(Error::Config(_))
+    ));
+    let empty = InMemoryDataset::<U2, U1>::new(Vec::new());
+    assert!(fit_transform(&empty, Some(&mut Standardize::new()), None).is_err());
+}
@@ -0,0 +1,221 @@
+extern crate nalgebra as na;
+
+use std::cell::RefCell;
+
+use log::debug;
+
+use na::allocator::Allocator;
+use na::{DefaultAllocator, DimName, Dynamic, VectorN};
+
+use rand::rngs::StdRng;
+use rand::Rng;
+
+use crate::error::Error;
+use crate::model::{Batch, Fxx, Model};
+
+///
+/// Dropout regularization applied to the output of the wrapped model.
+/// While training, each output is zeroed with probability `rate` and the
+/// surviving outputs are scaled by 1/(1 - rate) (inverted dropout) so that
+/// no rescaling is needed at evaluation time.
+///
+/// The mask used by `predict` is kept for the following `backpropagate`
+/// call, so that the error only flows through the outputs that contributed
+/// to the prediction, and is redrawn once backpropagation completes.
+/// Batches likewise use one mask per sample, drawn by the first prediction
+/// or backpropagation of a batch and redrawn once it's backpropagated.
+///
+pub struct Dropout<'a, M, N>
+where
+    M: DimName,
+    N: DimName,
+    DefaultAllocator: Allocator<Fxx, N> + Allocator<Fxx, N, Dynamic>,
+{
+    model: &'a mut dyn Model<M, N>,
+    rate: Fxx,
+    training: bool,
+    mask: VectorN<Fxx, N>,
+    batch_mask: RefCell<Batch<N>>,
+    rng: RefCell<StdRng>,
+}
+
+impl<'a, M, N> Dropout<'a, M, N>
+where
+    M: DimName,
+    N: DimName,
+    DefaultAllocator: Allocator<Fxx, N> + Allocator<Fxx, N, Dynamic>,
+{
+    ///
+    /// Wrap a model with dropout, starting in training mode, failing if the
+    /// rate is outside of [0, 1).
+    ///
+    /// # Arguments
+    /// * `model` - the model whose outputs are dropped.
+    /// * `rate` - the probability, in [0, 1), of dropping each output.
+    /// * `rng` - the source of the dropout masks.
+    ///
+    pub fn new(model: &'a mut dyn Model<M, N>, rate: Fxx, rng: StdRng) -> Result<Self, Error> {
+        if !(0.0..1.0).contains(&rate) {
+            return Err(Error::Config(format!(
+                "dropout rate {} outside of [0, 1)",
+                rate
+            )));
+        }
+        let mut dropout = Dropout {
+            model,
+            rate,
+            training: true,
+            mask: VectorN::<Fxx, N>::zeros(),
+            batch_mask: RefCell::new(Batch::<N>::zeros(0)),
+            rng: RefCell::new(rng),
+        };
+        dropout.sample_mask();
+        Ok(dropout)
+    }
+
+    pub fn get_mask(&self) -> &VectorN<Fxx, N> {
+        &self.mask
+    }
+
+    pub fn is_training(&self) -> bool {
+        self.training
+    }
+
+    fn sample_mask(&mut self) {
+        let rate = self.rate;
+        let rng = self.rng.get_mut();
+        self.mask = VectorN::<Fxx, N>::from_fn(|_, _| sample_keep(rate, rng));
+    }
+
+    fn sample_batch_mask(&self, ncols: usize) -> Batch<N> {
+        let rate = self.rate;
+        let rng = &mut *self.rng.borrow_mut();
+        Batch::<N>::from_fn(ncols, |_, _| sample_keep(rate, rng))
+    }
+
+    ///
+    /// The masks for a batch of the given size: those of the current batch,
+    /// or newly drawn when the size differs, e.g. for a short final batch.
+    ///
+    fn get_batch_mask(&self, ncols: usize) -> Batch<N> {
+        let mut mask = self.batch_mask.borrow_mut();
+        if mask.ncols() != ncols {
+            *mask = self.sample_batch_mask(ncols);
+        }
+        mask.clone()
+    }
+}
+
+///
+/// Draw the scale of an output: zero if dropped, otherwise 1/(1 - rate).
+///
+fn sample_keep(rate: Fxx, rng: &mut StdRng) -> Fxx {
+    if rng.gen::<Fxx>() < rate {
+        0.0
+    } else {
+        1.0 / (1.0 - rate)
+    }
+}
+
+impl<'a, M, N> Model<M, N> for Dropout<'a, M, N>
+where
+    M: DimName,
+    N: DimName,
+    DefaultAllocator: Allocator<Fxx, N> + Allocator<Fxx, N, Dynamic>,
+{
+    fn backpropagate(&mut self, x: &VectorN<Fxx, M>, de_dy: &VectorN<Fxx, N>) -> VectorN<Fxx, M>
+    where
+        DefaultAllocator: Allocator<Fxx, M> + Allocator<Fxx, N>,
+    {
+        debug!("dropout backprop {}->{}", M::dim(), N::dim());
+        if self.training {
+            let de_dp = de_dy.component_mul(&self.mask);
+            let de_dx = self.model.backpropagate(x, &de_dp);
+            self.sample_mask();
+            de_dx
+        } else {
+            self.model.backpropagate(x, de_dy)
+        }
+    }
+
+    fn backpropagate_batch(&mut self, x: &Batch<M>, de_dy: &Batch<N>) -> Batch<M>
+    where
+        DefaultAllocator: Allocator<Fxx, M>
+            + Allocator<Fxx, N>
+            + Allocator<Fxx, M, Dynamic>
+            + Allocator<Fxx, N, Dynamic>,
+    {
+        if self.training {
+            let de_dp = de_dy.component_mul(&self.get_batch_mask(x.ncols()));
+            let de_dx = self.model.backpropagate_batch(x, &de_dp);
+            let mask = self.sample_batch_mask(x.ncols());
+            *self.batch_mask.get_mut() = mask;
+            de_dx
+        } else {
+            self.model.backpropagate_batch(x, de_dy)
+        }
+    }
+
+    #[inline]
+    fn num_inputs(&self) -> usize {
+        M::dim()
+    }
+
+    #[inline]
+    fn num_outputs(&self) -> usize {
+        N::dim()
+    }
+
+    fn predict(&self, x: &VectorN<Fxx, M>) -> VectorN<Fxx, N>
+    where
+        DefaultAllocator: Allocator<Fxx, M> + Allocator<Fxx, N>,
+    {
+        let y = self.model.predict(x);
+        if self.training {
+            y.component_mul(&self.mask)
+        } else {
+            y
+        }
+    }
+
+    fn predict_batch(&self, x: &Batch<M>) -> Batch<N>
+    where
+        DefaultAllocator: Allocator<Fxx, M>
+            + Allocator<Fxx, N>
+            + Allocator<Fxx, M, Dynamic>
+            + Allocator<Fxx, N, Dynamic>,
+    {
+        let y = self.model.predict_batch(x);
+        if self.training {
+            y.component_mul(&self.get_batch_mask(x.ncols()))
+        } else {
+            y
+        }
+    }
+
+    fn update(&mut self, x: &VectorN<Fxx, M>, y: &VectorN<Fxx, N>) -> VectorN<Fxx, M>
+    where
+        DefaultAllocator: Allocator<Fxx, M> + Allocator<Fxx, N>,
+    {
+        let yh = self.predict(x);
+        let err = yh - y;
+        self.backpropagate(x, &err)
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.training = training;
+        self.model.set_training(training);
+    }
+
+    fn get_params(&self, params: &mut Vec<Fxx>) {
+        self.model.get_params(params);
+    }
+
+    fn set_params<'p>(&mut self, params: &'p [Fxx]) -> &'p [Fxx] {
+        self.model.set_params(params)
+    }
+}
+
+#[cfg(test)]
+#[path = "./dropout_test.rs"]
+mod dropout_test;
@@ -0,0 +1,169 @@
+use super::*;
+
+use na::{Vector2, Vector4};
+use na::{U1, U2, U4};
+use rand::SeedableRng;
+
+use crate::{Batch, LayeredModel, LinearModel, Model, SGDTrainer, UpdateParams};
+
+const LEARNING_PARAMS: UpdateParams = UpdateParams {
+    step_size: 0.1,
+    l2_reg: 0.0,
+};
+
+#[test]
+fn create_dropout() {
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut underlying_model = LinearModel::<U2, U4>::new_random(&mut trainer);
+    let model = Dropout::new(&mut underlying_model, 0.5, StdRng::seed_from_u64(1)).unwrap();
+    assert_eq!(model.num_inputs(), 2);
+    assert_eq!(model.num_outputs(), 4);
+    assert!(model.is_training());
+}
+
+#[test]
+fn rejects_invalid_rate() {
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut underlying_model = LinearModel::<U2, U4>::new_random(&mut trainer);
+    let dropout = Dropout::new(&mut underlying_model, 1.0, StdRng::seed_from_u64(1));
+    assert!(matches!(dropout, Err(Error::Config(_))));
+}
+
+#[test]
+fn scales_kept_outputs() {
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut rng = StdRng::seed_from_u64(2);
+    let mut underlying_model = LinearModel::<U2, U4>::new_random_with_rng(&mut trainer, &mut rng);
+    let x = Vector2::<Fxx>::new(1.0, 2.0);
+    let expected = underlying_model.predict(&x);
+
+    let model = Dropout::new(&mut underlying_model, 0.25, rng).unwrap();
+    let y = model.predict(&x);
+    for i in 0..4 {
+        let mask = model.get_mask()[i];
+        assert!(mask == 0.0 || mask == 1.0 / 0.75);
+        assert_eq!(y[i], expected[i] * mask);
+    }
+}
+
+#[test]
+fn evaluation_is_deterministic() {
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut rng = StdRng::seed_from_u64(3);
+    let mut underlying_model = LinearModel::<U2, U4>::new_random_with_rng(&mut trainer, &mut rng);
+    let x = Vector2::<Fxx>::new(1.0, 2.0);
+    let expected = underlying_model.predict(&x);
+
+    let mut model = Dropout::new(&mut underlying_model, 0.5, rng).unwrap();
+    model.set_training(false);
+    assert!(!model.is_training());
+    assert_eq!(model.predict(&x), expected);
+    assert_eq!(model.predict(&x), expected);
+}
+
+#[test]
+fn backpropagates_through_kept_outputs() {
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut rng = StdRng::seed_from_u64(4);
+    let mut underlying_model = LinearModel::<U2, U4>::new_random_with_rng(&mut trainer, &mut rng);
+    let ws0 = *underlying_model.get_ws();
+
+    let mask = {
+        let mut model = Dropout::new(&mut underlying_model, 0.5, rng).unwrap();
+        let mask = *model.get_mask();
+        let x = Vector2::<Fxx>::new(1.0, 2.0);
+        model.update(&x, &Vector4::<Fxx>::new(-1.0, -1.0, -1.0, -1.0));
+        mask
+    };
+
+    let ws1 = underlying_model.get_ws();
+    for i in 0..4 {
+        if mask[i] == 0.0 {
+            assert_eq!(ws1.row(i), ws0.row(i), "dropped output {} was trained", i);
+        } else {
+            assert_ne!(ws1.row(i), ws0.row(i), "kept output {} was not trained", i);
+        }
+    }
+}
+
+#[test]
+fn layered_model_propagates_mode() {
+    let mut train0 = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut train1 = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut rng = StdRng::seed_from_u64(5);
+    let mut model0 = LinearModel::<U2, U4>::new_random_with_rng(&mut train0, &mut rng);
+    let mut model1 = LinearModel::<U4, U1>::new_random_with_rng(&mut train1, &mut rng);
+    let x = Vector2::<Fxx>::new(1.0, 2.0);
+    let expected = model1.predict(&model0.predict(&x));
+
+    let mut dropout = Dropout::new(&mut model0, 0.9, rng).unwrap();
+    let mut model = LayeredModel::new(&mut dropout, &mut model1);
+    model.set_training(false);
+    assert_eq!(model.predict(&x), expected);
+    model.set_training(true);
+    assert_ne!(model.predict(&x), expected);
+    model.set_training(false);
+    assert_eq!(model.predict(&x), expected);
+}
+
+#[test]
+fn masks_batch_samples() {
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut rng = StdRng::seed_from_u64(6);
+    let mut underlying_model = LinearModel::<U2, U4>::new_random_with_rng(&mut trainer, &mut rng);
+    let x = Batch::<U2>::from_row_slice(&[1.0, 2.0, 3.0, 2.0, 1.0, 0.5]);
+    let expected = underlying_model.predict_batch(&x);
+
+    let mut model = Dropout::new(&mut underlying_model, 0.5, rng).unwrap();
+    model.set_training(false);
+    assert_eq!(model.predict_batch(&x), expected);
+
+    model.set_training(true);
+    let y0 = model.predict_batch(&x);
+    assert_eq!(y0, model.predict_batch(&x));
+    model.backpropagate_batch(&x, &Batch::<U4>::zeros(3));
+
+    // a mask is drawn for each sample in the next batch of the same size
+    let y1 = model.predict_batch(&x);
+    assert_ne!(y1, y0);
+    for j in 0..3 {
+        for i in 0..4 {
+            let scale = y1[(i, j)] / expected[(i, j)];
+            assert!(scale.abs() < 1e-6 || (scale - 2.0).abs() < 1e-4);
+        }
+    }
+    assert_ne!(
+        y1.column(0) - expected.column(0),
+        y1.column(1) - expected.column(1)
+    );
+}
+
+#[test]
+fn masks_first_and_short_batches_per_sample() {
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut rng = StdRng::seed_from_u64(7);
+    let mut underlying_model = LinearModel::<U2, U4>::new_random_with_rng(&mut trainer, &mut rng);
+    let x = Batch::<U2>::from_element(8, 1.0);
+    let expected = underlying_model.predict_batch(&x);
+    let mut model = Dropout::new(&mut underlying_model, 0.5, rng).unwrap();
+
+    let kept =
+        |y: &Batch<U4>, j: usize| -> Vec<bool> { (0..4).map(|i| y[(i, j)] != 0.0).collect() };
+    let distinct = |y: &Batch<U4>| (1..y.ncols()).any(|j| kept(y, j) != kept(y, 0));
+
+    // the first batch, and a short batch after it, each draw per-sample masks
+    let y0 = model.predict_batch(&x);
+    assert!(distinct(&y0));
+    assert!(y0
+        .iter()
+        .zip(expected.iter())
+        .all(|(&y, &e)| y == 0.0 || (y - 2.0 * e).abs() < 1e-4));
+    assert_eq!(y0, model.predict_batch(&x));
+    let x_short = x.columns(0, 5).into_owned();
+    let y1 = model.predict_batch(&x_short);
+    assert!(distinct(&y1));
+
+    // and are redrawn once the batch is backpropagated
+    model.backpropagate_batch(&x_short, &Batch::<U4>::zeros(5));
+    assert_ne!(model.predict_batch(&x_short), y1);
+}
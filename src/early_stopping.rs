@@ -0,0 +1,182 @@
+extern crate nalgebra as na;
+
+use na::DimName;
+
+use log::debug;
+
+use crate::model::{Fxx, Model};
+
+///
+/// Why training should stop.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StopReason {
+    /// The metric failed to improve for `patience` epochs.
+    Patience,
+    /// The metric reached its target.
+    Target,
+    /// The metric became NaN or infinite.
+    Diverged,
+}
+
+///
+/// Monitor a validation metric after each epoch, snapshotting the model
+/// parameters whenever the metric improves, and signal when training should
+/// stop: when the metric has not improved by more than min_delta for
+/// `patience` epochs, reaches a target, or diverges.  `restore` loads the
+/// best parameters back into the model.
+///
+/// Lower metrics, e.g. losses, are better unless `set_maximize` is set.
+///
+pub struct EarlyStopping {
+    patience: usize,
+    min_delta: Fxx,
+    maximize: bool,
+    target: Option<Fxx>,
+    epoch: usize,
+    wait: usize,
+    best: Option<Fxx>,
+    best_epoch: usize,
+    best_params: Vec<Fxx>,
+    stop_reason: Option<StopReason>,
+}
+
+impl EarlyStopping {
+    ///
+    /// # Arguments
+    /// * `patience` - the number of epochs without improvement to allow, 0 to
+    ///   stop on the first.
+    ///
+    pub fn new(patience: usize) -> Self {
+        EarlyStopping {
+            patience,
+            min_delta: 0.0,
+            maximize: false,
+            target: None,
+            epoch: 0,
+            wait: 0,
+            best: None,
+            best_epoch: 0,
+            best_params: Vec::new(),
+            stop_reason: None,
+        }
+    }
+
+    ///
+    /// Count only improvements greater than min_delta.
+    ///
+    pub fn set_min_delta(&mut self, min_delta: Fxx) {
+        self.min_delta = min_delta;
+    }
+
+    ///
+    /// Prefer greater metrics, e.g. accuracy.
+    ///
+    pub fn set_maximize(&mut self, maximize: bool) {
+        self.maximize = maximize;
+    }
+
+    ///
+    /// Stop once the metric is at least as good as the target, e.g. a
+    /// tolerated mean error.
+    ///
+    pub fn set_target(&mut self, target: Fxx) {
+        self.target = Some(target);
+    }
+
+    /// The best metric observed.
+    pub fn get_best(&self) -> Option<Fxx> {
+        self.best
+    }
+
+    /// The epoch, counting from 1, of the best metric.
+    pub fn get_best_epoch(&self) -> usize {
+        self.best_epoch
+    }
+
+    /// The number of epochs observed.
+    pub fn get_epoch(&self) -> usize {
+        self.epoch
+    }
+
+    pub fn get_stop_reason(&self) -> Option<StopReason> {
+        self.stop_reason
+    }
+
+    fn is_better(&self, metric: Fxx, than: Fxx) -> bool {
+        if self.maximize {
+            metric > than + self.min_delta
+        } else {
+            metric < than - self.min_delta
+        }
+    }
+
+    fn reaches(&self, metric: Fxx, target: Fxx) -> bool {
+        if self.maximize {
+            metric >= target
+        } else {
+            metric <= target
+        }
+    }
+
+    ///
+    /// Record the metric of an epoch, returning whether to continue
+    /// training.
+    ///
+    /// # Arguments
+    /// * `model` - the model to snapshot if the metric improved.
+    /// * `metric` - the validation metric after the epoch.
+    ///
+    pub fn step<M: DimName, N: DimName>(&mut self, model: &dyn Model<M, N>, metric: Fxx) -> bool {
+        if self.stop_reason.is_some() {
+            return false;
+        }
+        self.epoch += 1;
+        if !metric.is_finite() {
+            debug!("epoch {} diverged: {}", self.epoch, metric);
+            self.stop_reason = Some(StopReason::Diverged);
+            return false;
+        }
+
+        if self.best.is_none_or(|best| self.is_better(metric, best)) {
+            self.best = Some(metric);
+            self.best_epoch = self.epoch;
+            self.best_params.clear();
+            model.get_params(&mut self.best_params);
+            self.wait = 0;
+        } else {
+            self.wait += 1;
+        }
+        debug!(
+            "epoch {} metric {} best {:?} at {}",
+            self.epoch, metric, self.best, self.best_epoch
+        );
+
+        if self
+            .target
+            .is_some_and(|target| self.reaches(metric, target))
+        {
+            self.stop_reason = Some(StopReason::Target);
+        } else if self.wait > 0 && self.wait >= self.patience {
+            self.stop_reason = Some(StopReason::Patience);
+        }
+        self.stop_reason.is_none()
+    }
+
+    ///
+    /// Load the parameters of the best epoch into the model, returning
+    /// whether there were any to load.
+    ///
+    pub fn restore<M: DimName, N: DimName>(&self, model: &mut dyn Model<M, N>) -> bool {
+        if self.best.is_none() {
+            return false;
+        }
+        debug!("restoring parameters of epoch {}", self.best_epoch);
+        model.set_params(&self.best_params);
+        true
+    }
+}
+
+#[cfg(test)]
+#[path = "./early_stopping_test.rs"]
+mod early_stopping_test;
@@ -0,0 +1,84 @@
+use super::*;
+
+use na::{Vector2, U1, U2};
+
+use crate::{LinearModel, SGDTrainer, UpdateParams};
+
+const LEARNING_PARAMS: UpdateParams = UpdateParams {
+    step_size: 0.1,
+    l2_reg: 0.0,
+};
+
+#[test]
+fn stops_without_improvement() {
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let model = LinearModel::<U2, U1>::new_random(&mut trainer);
+    let mut stopping = EarlyStopping::new(2);
+    stopping.set_min_delta(0.1);
+    assert!(stopping.step(&model, 3.0));
+    assert!(stopping.step(&model, 2.0));
+    // improvements within min_delta don't count
+    assert!(stopping.step(&model, 1.95));
+    assert!(!stopping.step(&model, 2.5));
+    assert_eq!(stopping.get_stop_reason(), Some(StopReason::Patience));
+    assert_eq!(stopping.get_best(), Some(2.0));
+    assert_eq!(stopping.get_best_epoch(), 2);
+    assert_eq!(stopping.get_epoch(), 4);
+    assert!(!stopping.step(&model, 0.0));
+    assert_eq!(stopping.get_epoch(), 4);
+}
+
+#[test]
+fn zero_patience_stops_on_first_regression() {
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let model = LinearModel::<U2, U1>::new_random(&mut trainer);
+    let mut stopping = EarlyStopping::new(0);
+    assert!(stopping.step(&model, 3.0));
+    assert!(stopping.step(&model, 2.0));
+    assert!(!stopping.step(&model, 2.5));
+    assert_eq!(stopping.get_stop_reason(), Some(StopReason::Patience));
+    assert_eq!(stopping.get_best_epoch(), 2);
+}
+
+#[test]
+fn stops_at_target() {
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let model = LinearModel::<U2, U1>::new_random(&mut trainer);
+    let mut stopping = EarlyStopping::new(10);
+    stopping.set_maximize(true);
+    stopping.set_target(0.9);
+    assert!(stopping.step(&model, 0.5));
+    assert!(stopping.step(&model, 0.4));
+    assert!(!stopping.step(&model, 0.95));
+    assert_eq!(stopping.get_stop_reason(), Some(StopReason::Target));
+    assert_eq!(stopping.get_best(), Some(0.95));
+}
+
+#[test]
+fn stops_on_divergence() {
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let model = LinearModel::<U2, U1>::new_random(&mut trainer);
+    let mut stopping = EarlyStopping::new(10);
+    assert!(stopping.step(&model, 1.0));
+    assert!(!stopping.step(&model, Fxx::NAN));
+    assert_eq!(stopping.get_stop_reason(), Some(StopReason::Diverged));
+    assert_eq!(stopping.get_best(), Some(1.0));
+}
+
+#[test]
+fn restores_best_params() {
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut model = LinearModel::<U2, U1>::new_random(&mut trainer);
+    let mut stopping = EarlyStopping::new(1);
+    assert!(!stopping.restore(&mut model));
+
+    let x = Vector2::new(1.0, 2.0);
+    let best = model.predict(&x);
+    stopping.step(&model, 1.0);
+    model.update(&x, &na::Vector1::new(100.0));
+    assert_ne!(model.predict(&x), best);
+    stopping.step(&model, 2.0);
+
+    assert!(stopping.restore(&mut model));
+    assert_eq!(model.predict(&x), best);
+}
@@ -0,0 +1,94 @@
+use std::error;
+use std::fmt;
+use std::io;
+
+use image::ImageError;
+
+use crate::data::CsvError;
+
+///
+/// The errors of the fallible operations of the library.  Malformed files,
+/// e.g. a history or parameters that don't parse, are reported as `Io` errors
+/// of kind `InvalidData`.
+///
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// An image couldn't be decoded or encoded.
+    Image(ImageError),
+    /// CSV data couldn't be read or encoded.
+    Csv(CsvError),
+    /// The dimension of some data doesn't match that required.
+    Shape {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    /// A matrix that must be inverted is singular.
+    Singular(String),
+    /// A value that must be finite is NaN or infinite.
+    NonFinite(String),
+    /// Settings are invalid or inconsistent with the data.
+    Config(String),
+    /// A worker thread panicked.
+    Worker(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "{}", err),
+            Error::Image(err) => write!(f, "{}", err),
+            Error::Csv(err) => write!(f, "{}", err),
+            Error::Shape {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{} has {} components, expected {}",
+                name, found, expected
+            ),
+            Error::Singular(msg) => write!(f, "singular matrix: {}", msg),
+            Error::NonFinite(msg) => write!(f, "not finite: {}", msg),
+            Error::Config(msg) => write!(f, "invalid configuration: {}", msg),
+            Error::Worker(msg) => write!(f, "worker failed: {}", msg),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::Image(err) => Some(err),
+            Error::Csv(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<ImageError> for Error {
+    fn from(err: ImageError) -> Self {
+        Error::Image(err)
+    }
+}
+
+impl From<CsvError> for Error {
+    fn from(err: CsvError) -> Self {
+        match err {
+            CsvError::Io(err) => Error::Io(err),
+            err => Error::Csv(err),
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./error_test.rs"]
+mod error_test;
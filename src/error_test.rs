@@ -0,0 +1,73 @@
+use super::*;
+
+use std::error::Error as _;
+use std::io::Cursor;
+
+use nalgebra::{Matrix1, Matrix2x1, U1, U2};
+
+use crate::data::{ColumnRef, CsvColumn, CsvLoader};
+use crate::img::read_luma;
+use crate::{Fxx, LinearModel, SGDTrainer, UpdateParams};
+
+#[test]
+fn converts_errors() {
+    let err = Error::from(io::Error::new(io::ErrorKind::NotFound, "missing"));
+    assert!(matches!(err, Error::Io(_)));
+    assert_eq!(err.to_string(), "missing");
+    assert!(err.source().is_some());
+
+    let err = Error::from(CsvError::UnknownColumn("x".to_string()));
+    assert!(matches!(err, Error::Csv(CsvError::UnknownColumn(_))));
+    assert_eq!(err.to_string(), "unknown CSV column x");
+
+    let err = Error::from(CsvError::Io(io::Error::other("closed")));
+    assert!(matches!(err, Error::Io(_)));
+}
+
+#[test]
+fn describes_errors() {
+    let err = Error::Shape {
+        name: "features".to_string(),
+        expected: 2,
+        found: 3,
+    };
+    assert_eq!(err.to_string(), "features has 3 components, expected 2");
+    assert!(err.source().is_none());
+    assert_eq!(
+        Error::NonFinite("loss".to_string()).to_string(),
+        "not finite: loss"
+    );
+    assert_eq!(
+        Error::Config("no classes".to_string()).to_string(),
+        "invalid configuration: no classes"
+    );
+    assert_eq!(
+        Error::Worker("diverged".to_string()).to_string(),
+        "worker failed: diverged"
+    );
+}
+
+#[test]
+fn reports_library_errors() {
+    let loader = CsvLoader::new(vec![CsvColumn::numeric(ColumnRef::Index(0))], vec![]);
+    assert!(matches!(
+        loader.load_path::<U1, U1, _>("no/such/file.csv"),
+        Err(Error::Io(_))
+    ));
+
+    let params = UpdateParams {
+        step_size: 0.1,
+        l2_reg: 0.0,
+    };
+    let mut trainer = SGDTrainer::new(&params);
+    let mut model = LinearModel::<U2, U1>::new_random(&mut trainer);
+    let x = Matrix2x1::<Fxx>::new(1.0, 1.0);
+    let y = Matrix1::<Fxx>::new(2.0);
+    assert!(matches!(model.update_bulk(&x, &y), Err(Error::Singular(_))));
+
+    let mut cursor = Cursor::new(b"not an image".to_vec());
+    assert!(matches!(
+        read_luma::<_, U2, U2>(&mut cursor),
+        Err(Error::Image(_))
+    ));
+}
@@ -0,0 +1,401 @@
+extern crate nalgebra as na;
+
+use std::fs::File;
+use std::io::BufWriter;
+
+use log::{debug, info, warn};
+
+use na::allocator::Allocator;
+use na::{DefaultAllocator, DimName, Dynamic};
+
+use rand::rngs::StdRng;
+
+use crate::data::{write_params, DataLoader, Dataset};
+use crate::early_stopping::EarlyStopping;
+use crate::error::Error;
+use crate::history::History;
+use crate::metrics::{evaluate, Metric, Regression, RegressionMetric};
+use crate::model::{Fxx, Model};
+
+///
+/// Whether a callback lets training continue.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Control {
+    Continue,
+    Stop,
+}
+
+///
+/// The progress of a fit, passed to callbacks.  Losses are mean squared
+/// errors over every output component.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct FitState {
+    /// The current epoch, counting from 1.
+    pub epoch: usize,
+    /// The number of updates applied.
+    pub step: usize,
+    /// The loss of the latest mini-batch, before its update.
+    pub loss: Fxx,
+    /// The norm of the error gradient backpropagated by the latest update,
+    /// root mean square over the samples of a mini-batch.
+    pub gradient_norm: Fxx,
+    /// The mean loss of the mini-batches of the epoch so far.
+    pub epoch_loss: Fxx,
+    /// The loss on the validation dataset after the latest epoch, if any.
+    pub validation_loss: Option<Fxx>,
+    /// The fraction of each update applied, 1 unless adjusted by a
+    /// callback, e.g. a learning rate schedule.
+    pub learning_rate_scale: Fxx,
+    /// Whether a callback stopped training.
+    pub stopped: bool,
+}
+
+///
+/// Hooks into `Fit::run`, e.g. to log progress, checkpoint parameters, stop
+/// early or adjust the learning rate.  Every hook defaults to continuing,
+/// except `on_nan`.
+///
+pub trait Callback<M: DimName, N: DimName> {
+    /// Called after each update.
+    fn on_step(&mut self, _model: &mut dyn Model<M, N>, _state: &mut FitState) -> Control {
+        Control::Continue
+    }
+
+    ///
+    /// Called, instead of updating the model, when the loss of a mini-batch
+    /// isn't finite.  Stops training unless overridden, e.g. to roll back
+    /// the model.
+    ///
+    fn on_nan(&mut self, _model: &mut dyn Model<M, N>, _state: &mut FitState) -> Control {
+        Control::Stop
+    }
+
+    /// Called after the validation loss of each epoch is computed.
+    fn on_validation(&mut self, _model: &mut dyn Model<M, N>, _state: &mut FitState) -> Control {
+        Control::Continue
+    }
+
+    /// Called at the end of each epoch, after any validation.
+    fn on_epoch_end(&mut self, _model: &mut dyn Model<M, N>, _state: &mut FitState) -> Control {
+        Control::Continue
+    }
+}
+
+///
+/// A training loop over the epochs of a dataset, one example at a time or
+/// in mini-batches, with an optional validation dataset evaluated after each
+/// epoch and callbacks invoked throughout.
+///
+/// Each update backpropagates the error of the same prediction that gives
+/// its loss, so a model drawing random masks, like `Dropout`, is trained
+/// with the masks the loss was computed with.
+///
+pub struct Fit<'a, M: DimName, N: DimName> {
+    epochs: usize,
+    batch_size: usize,
+    validation: Option<&'a dyn Dataset<M, N>>,
+    callbacks: Vec<&'a mut dyn Callback<M, N>>,
+}
+
+impl<'a, M, N> Fit<'a, M, N>
+where
+    M: DimName,
+    N: DimName,
+    DefaultAllocator: Allocator<Fxx, M>
+        + Allocator<Fxx, N>
+        + Allocator<Fxx, M, Dynamic>
+        + Allocator<Fxx, N, Dynamic>,
+{
+    ///
+    /// # Arguments
+    /// * `epochs` - the most epochs to train.
+    ///
+    pub fn new(epochs: usize) -> Self {
+        Fit {
+            epochs,
+            batch_size: 0,
+            validation: None,
+            callbacks: Vec::new(),
+        }
+    }
+
+    ///
+    /// Train on mini-batches of this size with `Model::backpropagate_batch`,
+    /// or on single examples with `Model::backpropagate` if zero, the default.
+    ///
+    pub fn set_batch_size(&mut self, batch_size: usize) {
+        self.batch_size = batch_size;
+    }
+
+    pub fn set_validation(&mut self, validation: &'a dyn Dataset<M, N>) {
+        self.validation = Some(validation);
+    }
+
+    ///
+    /// Add a callback, invoked after those added before it.
+    ///
+    pub fn add_callback(&mut self, callback: &'a mut dyn Callback<M, N>) {
+        self.callbacks.push(callback);
+    }
+
+    fn notify<F>(&mut self, model: &mut dyn Model<M, N>, state: &mut FitState, hook: F) -> bool
+    where
+        F: Fn(&mut dyn Callback<M, N>, &mut dyn Model<M, N>, &mut FitState) -> Control,
+    {
+        let mut proceed = true;
+        for callback in self.callbacks.iter_mut() {
+            if hook(&mut **callback, model, state) == Control::Stop {
+                proceed = false;
+            }
+        }
+        if !proceed {
+            state.stopped = true;
+        }
+        proceed
+    }
+
+    ///
+    /// Train the model until every epoch has run or a callback stops
+    /// training, returning the final state.
+    ///
+    /// A learning rate scale other than 1 scales each update by moving the
+    /// parameters only that fraction of the way from their values before the
+    /// update to those after.
+    ///
+    /// # Arguments
+    /// * `model` - the model to train.
+    /// * `dataset` - the training examples.
+    /// * `rng` - the source of the order of the examples in each epoch.
+    ///
+    pub fn run(
+        &mut self,
+        model: &mut dyn Model<M, N>,
+        dataset: &dyn Dataset<M, N>,
+        rng: StdRng,
+    ) -> Result<FitState, Error> {
+        let mut state = FitState {
+            epoch: 0,
+            step: 0,
+            loss: Fxx::NAN,
+            gradient_norm: Fxx::NAN,
+            epoch_loss: Fxx::NAN,
+            validation_loss: None,
+            learning_rate_scale: 1.0,
+            stopped: false,
+        };
+        let mut loader = DataLoader::new(dataset, self.batch_size.max(1), rng)?;
+        let mut before = Vec::new();
+        let mut after = Vec::new();
+
+        'epochs: while state.epoch < self.epochs {
+            state.epoch += 1;
+            model.set_training(true);
+            let mut loss_sum = 0.0;
+            let mut batches = 0;
+            for (x, y) in loader.epoch() {
+                let scale = state.learning_rate_scale;
+                if scale != 1.0 {
+                    before.clear();
+                    model.get_params(&mut before);
+                }
+                let (loss, gradient_norm) = if self.batch_size > 0 {
+                    let err = model.predict_batch(&x) - &y;
+                    let loss = err.norm_squared() / err.len() as Fxx;
+                    if !loss.is_finite() {
+                        (loss, Fxx::NAN)
+                    } else {
+                        let de_dx = model.backpropagate_batch(&x, &err);
+                        (loss, (de_dx.norm_squared() / de_dx.ncols() as Fxx).sqrt())
+                    }
+                } else {
+                    let x = x.column(0).into_owned();
+                    let err = model.predict(&x) - y.column(0);
+                    let loss = err.norm_squared() / err.len() as Fxx;
+                    if !loss.is_finite() {
+                        (loss, Fxx::NAN)
+                    } else {
+                        (loss, model.backpropagate(&x, &err).norm())
+                    }
+                };
+                state.loss = loss;
+                if !loss.is_finite() {
+                    debug!(
+                        "epoch {} step {} loss {}",
+                        state.epoch, state.step, state.loss
+                    );
+                    if !self.notify(model, &mut state, |c, m, s| c.on_nan(m, s)) {
+                        break 'epochs;
+                    }
+                    continue;
+                }
+                state.gradient_norm = gradient_norm;
+                if scale != 1.0 {
+                    after.clear();
+                    model.get_params(&mut after);
+                    for (a, b) in after.iter_mut().zip(before.iter()) {
+                        *a = b + scale * (*a - b);
+                    }
+                    model.set_params(&after);
+                }
+
+                state.step += 1;
+                loss_sum += state.loss;
+                batches += 1;
+                state.epoch_loss = loss_sum / batches as Fxx;
+                if !self.notify(model, &mut state, |c, m, s| c.on_step(m, s)) {
+                    break 'epochs;
+                }
+            }
+
+            if let Some(validation) = self.validation {
+                model.set_training(false);
+                let mut mse = RegressionMetric::new(Regression::Mse);
+                evaluate(&*model, validation, &mut [&mut mse]);
+                state.validation_loss = Some(Metric::<N>::value(&mse));
+                if !self.notify(model, &mut state, |c, m, s| c.on_validation(m, s)) {
+                    break;
+                }
+            }
+            if !self.notify(model, &mut state, |c, m, s| c.on_epoch_end(m, s)) {
+                break;
+            }
+        }
+        model.set_training(false);
+        Ok(state)
+    }
+}
+
+///
+/// Stop when the validation loss, or the training loss without validation,
+/// stops improving.
+///
+impl<M: DimName, N: DimName> Callback<M, N> for EarlyStopping {
+    fn on_epoch_end(&mut self, model: &mut dyn Model<M, N>, state: &mut FitState) -> Control {
+        let loss = state.validation_loss.unwrap_or(state.epoch_loss);
+        if self.step(&*model, loss) {
+            Control::Continue
+        } else {
+            Control::Stop
+        }
+    }
+}
+
+///
+/// Record the loss of each update and the training and validation losses of
+/// each epoch.
+///
+impl<M: DimName, N: DimName> Callback<M, N> for History {
+    fn on_step(&mut self, model: &mut dyn Model<M, N>, state: &mut FitState) -> Control {
+        self.record_step(&*model, state.loss, state.gradient_norm);
+        Control::Continue
+    }
+
+    fn on_epoch_end(&mut self, model: &mut dyn Model<M, N>, state: &mut FitState) -> Control {
+        let mut values = vec![("loss", state.epoch_loss)];
+        if let Some(validation_loss) = state.validation_loss {
+            values.push(("validation_loss", validation_loss));
+        }
+        self.record_epoch(&*model, &values);
+        Control::Continue
+    }
+}
+
+///
+/// Write the model parameters with `write_params` every few epochs,
+/// overwriting the previous checkpoint.  Stops training if the file can't be
+/// written.
+///
+pub struct Checkpoint {
+    path: String,
+    every: usize,
+}
+
+impl Checkpoint {
+    ///
+    /// # Arguments
+    /// * `path` - the file to write.
+    /// * `every` - the number of epochs between checkpoints.
+    ///
+    pub fn new(path: &str, every: usize) -> Self {
+        Checkpoint {
+            path: path.to_string(),
+            every: every.max(1),
+        }
+    }
+}
+
+impl<M: DimName, N: DimName> Callback<M, N> for Checkpoint {
+    fn on_epoch_end(&mut self, model: &mut dyn Model<M, N>, state: &mut FitState) -> Control {
+        if !state.epoch.is_multiple_of(self.every) {
+            return Control::Continue;
+        }
+        let mut params = Vec::new();
+        model.get_params(&mut params);
+        let written = File::create(&self.path)
+            .map_err(Error::from)
+            .and_then(|file| write_params(&mut BufWriter::new(file), &params));
+        match written {
+            Ok(()) => {
+                debug!("epoch {} checkpoint {}", state.epoch, self.path);
+                Control::Continue
+            }
+            Err(err) => {
+                warn!("failed to write checkpoint {}: {}", self.path, err);
+                Control::Stop
+            }
+        }
+    }
+}
+
+///
+/// Multiply the learning rate scale by a factor every few epochs.
+///
+pub struct StepDecay {
+    factor: Fxx,
+    every: usize,
+}
+
+impl StepDecay {
+    ///
+    /// # Arguments
+    /// * `factor` - the decay, e.g. 0.5 to halve the learning rate.
+    /// * `every` - the number of epochs between decays.
+    ///
+    pub fn new(factor: Fxx, every: usize) -> Self {
+        StepDecay {
+            factor,
+            every: every.max(1),
+        }
+    }
+}
+
+impl<M: DimName, N: DimName> Callback<M, N> for StepDecay {
+    fn on_epoch_end(&mut self, _model: &mut dyn Model<M, N>, state: &mut FitState) -> Control {
+        if state.epoch.is_multiple_of(self.every) {
+            state.learning_rate_scale *= self.factor;
+            debug!("learning rate scale {}", state.learning_rate_scale);
+        }
+        Control::Continue
+    }
+}
+
+///
+/// Log the losses of each epoch at info level.
+///
+pub struct Logger;
+
+impl<M: DimName, N: DimName> Callback<M, N> for Logger {
+    fn on_epoch_end(&mut self, _model: &mut dyn Model<M, N>, state: &mut FitState) -> Control {
+        info!(
+            "epoch {} step {} loss {} validation {:?}",
+            state.epoch, state.step, state.epoch_loss, state.validation_loss
+        );
+        Control::Continue
+    }
+}
+
+#[cfg(test)]
+#[path = "./fit_test.rs"]
+mod fit_test;
@@ -0,0 +1,251 @@
+use super::*;
+
+use na::{Vector1, Vector2, U1, U2};
+use rand::SeedableRng;
+
+use crate::data::{read_params, InMemoryDataset};
+use crate::{Dropout, LinearModel, SGDTrainer, StopReason, UpdateParams};
+
+const LEARNING_PARAMS: UpdateParams = UpdateParams {
+    step_size: 0.1,
+    l2_reg: 0.0,
+};
+
+fn dataset(n: usize) -> InMemoryDataset<U2, U1> {
+    InMemoryDataset::from_fn(
+        (0..n)
+            .map(|i| Vector2::new(i as Fxx / n as Fxx, 1.0 - 2.0 * (i % 3) as Fxx / 3.0))
+            .collect(),
+        |x| Vector1::new(2.0 * x[0] - x[1] + 0.5),
+    )
+}
+
+/// Count the hooks invoked, stopping at a given step.
+#[derive(Default)]
+struct Counter {
+    steps: usize,
+    nans: usize,
+    validations: usize,
+    epochs: usize,
+    stop_at: Option<usize>,
+}
+
+impl Callback<U2, U1> for Counter {
+    fn on_step(&mut self, _model: &mut dyn Model<U2, U1>, state: &mut FitState) -> Control {
+        self.steps += 1;
+        assert_eq!(state.step, self.steps);
+        if Some(state.step) == self.stop_at {
+            Control::Stop
+        } else {
+            Control::Continue
+        }
+    }
+
+    fn on_nan(&mut self, _model: &mut dyn Model<U2, U1>, _state: &mut FitState) -> Control {
+        self.nans += 1;
+        Control::Continue
+    }
+
+    fn on_validation(&mut self, _model: &mut dyn Model<U2, U1>, state: &mut FitState) -> Control {
+        assert!(state.validation_loss.is_some());
+        self.validations += 1;
+        Control::Continue
+    }
+
+    fn on_epoch_end(&mut self, _model: &mut dyn Model<U2, U1>, _state: &mut FitState) -> Control {
+        self.epochs += 1;
+        Control::Continue
+    }
+}
+
+#[test]
+fn fits_examples() {
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut model =
+        LinearModel::<U2, U1>::new_random_with_rng(&mut trainer, &mut StdRng::seed_from_u64(1));
+    let train = dataset(12);
+    let validation = dataset(5);
+    let mut counter = Counter::default();
+    let mut history = History::new();
+    let state = {
+        let mut fit = Fit::new(100);
+        fit.set_validation(&validation);
+        fit.add_callback(&mut counter);
+        fit.add_callback(&mut history);
+        fit.run(&mut model, &train, StdRng::seed_from_u64(2))
+            .unwrap()
+    };
+    assert_eq!(state.epoch, 100);
+    assert_eq!(state.step, 1200);
+    assert!(!state.stopped);
+    assert!(state.validation_loss.unwrap() < 1e-3);
+    assert_eq!(
+        (counter.steps, counter.validations, counter.epochs),
+        (1200, 100, 100)
+    );
+    assert_eq!(history.get_step(), 1200);
+    assert!(history.series(crate::Phase::Step, "gradient_norm")[0].1 > 0.0);
+    assert_eq!(
+        history.series(crate::Phase::Epoch, "validation_loss").len(),
+        100
+    );
+}
+
+/// Check that dropped outputs, predicting 0 for targets of 1, have loss 1
+/// and backpropagate no gradient.
+struct DropoutCheck {
+    dropped: usize,
+}
+
+impl Callback<U2, U1> for DropoutCheck {
+    fn on_step(&mut self, _model: &mut dyn Model<U2, U1>, state: &mut FitState) -> Control {
+        assert_eq!(state.loss == 1.0, state.gradient_norm == 0.0);
+        if state.loss == 1.0 {
+            self.dropped += 1;
+        }
+        Control::Continue
+    }
+}
+
+#[test]
+fn updates_with_the_masks_of_the_loss() {
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut underlying_model = LinearModel::<U2, U1>::new_random(&mut trainer);
+    let mut model = Dropout::new(&mut underlying_model, 0.5, StdRng::seed_from_u64(4)).unwrap();
+    let train = InMemoryDataset::from_fn(
+        (0..8).map(|i| Vector2::new(i as Fxx / 8.0, 1.0)).collect(),
+        |_| Vector1::new(1.0),
+    );
+    let mut check = DropoutCheck { dropped: 0 };
+    {
+        let mut fit = Fit::new(5);
+        fit.add_callback(&mut check);
+        fit.run(&mut model, &train, StdRng::seed_from_u64(5))
+            .unwrap();
+    }
+    assert!(check.dropped > 0 && check.dropped < 40);
+}
+
+#[test]
+fn trains_mini_batches() {
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut model = LinearModel::<U2, U1>::new_random(&mut trainer);
+    let train = dataset(10);
+    let mut counter = Counter {
+        stop_at: Some(7),
+        ..Default::default()
+    };
+    let state = {
+        let mut fit = Fit::new(5);
+        fit.set_batch_size(4);
+        fit.add_callback(&mut counter);
+        fit.run(&mut model, &train, StdRng::seed_from_u64(3))
+            .unwrap()
+    };
+    // 3 mini-batches per epoch, stopping during the third epoch
+    assert!(state.stopped);
+    assert_eq!(state.epoch, 3);
+    assert_eq!(state.step, 7);
+    assert_eq!(counter.epochs, 2);
+    assert_eq!(counter.validations, 0);
+}
+
+#[test]
+fn stops_early() {
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut model = LinearModel::<U2, U1>::new_random(&mut trainer);
+    let train = dataset(10);
+    let mut stopping = EarlyStopping::new(3);
+    stopping.set_target(Fxx::INFINITY);
+    let state = {
+        let mut fit = Fit::new(10);
+        fit.add_callback(&mut stopping);
+        fit.run(&mut model, &train, StdRng::seed_from_u64(4))
+            .unwrap()
+    };
+    assert!(state.stopped);
+    assert_eq!(state.epoch, 1);
+    assert_eq!(stopping.get_stop_reason(), Some(StopReason::Target));
+    assert_eq!(stopping.get_best(), Some(state.epoch_loss));
+}
+
+#[test]
+fn skips_nan_losses() {
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut model = LinearModel::<U2, U1>::new_random(&mut trainer);
+    let mut train = dataset(4);
+    train.push(Vector2::new(0.5, 0.5), Vector1::new(Fxx::NAN));
+    let mut counter = Counter::default();
+    let state = {
+        let mut fit = Fit::new(2);
+        fit.add_callback(&mut counter);
+        fit.run(&mut model, &train, StdRng::seed_from_u64(5))
+            .unwrap()
+    };
+    assert_eq!(counter.nans, 2);
+    assert_eq!(state.step, 8);
+    assert!(state.epoch_loss.is_finite());
+
+    // callbacks stop at NaN by default
+    let mut logger = Logger;
+    let state = {
+        let mut fit = Fit::new(2);
+        fit.add_callback(&mut logger);
+        fit.run(&mut model, &train, StdRng::seed_from_u64(5))
+            .unwrap()
+    };
+    assert!(state.stopped);
+    assert_eq!(state.epoch, 1);
+    assert!(state.step <= 4);
+}
+
+#[test]
+fn scales_learning_rate() {
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut model = LinearModel::<U2, U1>::new_random(&mut trainer);
+    let train = dataset(6);
+    let mut decay = StepDecay::new(0.0, 2);
+    let mut snapshots = Vec::new();
+    for epochs in 1..=3 {
+        model.set_params(&[0.0, 0.0, 0.0]);
+        let state = {
+            let mut fit = Fit::new(epochs);
+            fit.add_callback(&mut decay);
+            fit.run(&mut model, &train, StdRng::seed_from_u64(6))
+                .unwrap()
+        };
+        assert_eq!(
+            state.learning_rate_scale,
+            if epochs < 2 { 1.0 } else { 0.0 }
+        );
+        let mut params = Vec::new();
+        model.get_params(&mut params);
+        snapshots.push(params);
+    }
+    // the third epoch, after decay to 0, leaves the parameters unchanged
+    assert_ne!(snapshots[0], snapshots[1]);
+    assert_eq!(snapshots[1], snapshots[2]);
+}
+
+#[test]
+fn writes_checkpoints() {
+    let path = std::env::temp_dir().join(format!("lair_checkpoint_{}", std::process::id()));
+    let path = path.to_str().unwrap();
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut model = LinearModel::<U2, U1>::new_random(&mut trainer);
+    let train = dataset(6);
+    let mut checkpoint = Checkpoint::new(path, 2);
+    {
+        let mut fit = Fit::new(3);
+        fit.add_callback(&mut checkpoint);
+        fit.run(&mut model, &train, StdRng::seed_from_u64(7))
+            .unwrap();
+    }
+    let written = read_params(&mut File::open(path).unwrap()).unwrap();
+    std::fs::remove_file(path).unwrap();
+    let mut params = Vec::new();
+    model.get_params(&mut params);
+    assert_eq!(written.len(), 3);
+    // written after the second epoch, not the third
+    assert_ne!(written, params);
+}
@@ -0,0 +1,545 @@
+extern crate nalgebra as na;
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use log::{debug, warn};
+
+use na::allocator::Allocator;
+use na::storage::Storage;
+use na::{DefaultAllocator, Dim, DimName, Dynamic, Matrix, VectorN};
+
+use crate::error::Error;
+use crate::model::{has_nan, Batch, Fxx, Model};
+
+///
+/// What a `Monitor` does when an update leaves its model's parameters or
+/// gradients NaN or infinite.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    /// Log a warning and keep the update.
+    Warn,
+    /// Log a warning and restore the parameters from before the update.
+    Skip,
+    /// Log a warning and restore the parameters of the last healthy snapshot.
+    Rollback,
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Action::Warn => "warn",
+            Action::Skip => "skip",
+            Action::Rollback => "rollback",
+        })
+    }
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "warn" => Ok(Action::Warn),
+            "skip" => Ok(Action::Skip),
+            "rollback" => Ok(Action::Rollback),
+            _ => Err(format!("unknown action {}", s)),
+        }
+    }
+}
+
+///
+/// The activation applied to a monitored model's outputs, determining how
+/// its saturation is measured.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Activation {
+    /// Outputs don't saturate.
+    Linear,
+    /// Units whose outputs are never positive are dead.
+    Relu,
+    /// Outputs within epsilon of 0 or 1 are saturated.
+    Logit,
+}
+
+///
+/// The statistics of one monitored layer.  Norms are of the latest update;
+/// output statistics are over the predictions since the last
+/// `Health::reset_outputs`.
+///
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LayerHealth {
+    /// The number of updates and backpropagations.
+    pub updates: usize,
+    /// The Euclidean norm of the parameters.
+    pub weight_norm: Fxx,
+    /// The Euclidean norm of the change in the parameters.
+    pub update_norm: Fxx,
+    /// The norm of the error gradient with respect to the outputs, root mean
+    /// square over the samples of a mini-batch.
+    pub gradient_norm: Fxx,
+    /// The number of output values observed.
+    pub outputs: usize,
+    /// The number of saturated output values observed.
+    pub saturated: usize,
+    /// The number of NaN or infinite output values observed.
+    pub non_finite_outputs: usize,
+    /// The number of updates leaving NaN or infinite parameters or gradients.
+    pub non_finite: usize,
+    /// The number of updates undone by `Action::Skip`.
+    pub skipped: usize,
+    /// The number of rollbacks by `Action::Rollback`.
+    pub rollbacks: usize,
+    /// Whether each ReLU unit has had a positive output.
+    active: Vec<bool>,
+}
+
+impl LayerHealth {
+    ///
+    /// The fraction of observed output values that were saturated.
+    ///
+    pub fn saturation(&self) -> Fxx {
+        if self.outputs == 0 {
+            0.0
+        } else {
+            self.saturated as Fxx / self.outputs as Fxx
+        }
+    }
+
+    ///
+    /// The number of ReLU units that haven't had a positive output, zero for
+    /// other activations.
+    ///
+    pub fn dead_units(&self) -> usize {
+        self.active.iter().filter(|&&active| !active).count()
+    }
+
+    fn observe<R, C, S>(&mut self, activation: Activation, epsilon: Fxx, y: &Matrix<Fxx, R, C, S>)
+    where
+        R: Dim,
+        C: Dim,
+        S: Storage<Fxx, R, C>,
+    {
+        if activation == Activation::Relu && self.active.len() != y.nrows() {
+            self.active = vec![false; y.nrows()];
+        }
+        for j in 0..y.ncols() {
+            for i in 0..y.nrows() {
+                let yi = y[(i, j)];
+                if !yi.is_finite() {
+                    self.non_finite_outputs += 1;
+                }
+                let saturated = match activation {
+                    Activation::Linear => false,
+                    Activation::Relu => {
+                        if yi > 0.0 {
+                            self.active[i] = true;
+                        }
+                        false
+                    }
+                    Activation::Logit => yi < epsilon || yi > 1.0 - epsilon,
+                };
+                if saturated {
+                    self.saturated += 1;
+                }
+            }
+        }
+        self.outputs += y.len();
+    }
+}
+
+///
+/// The health of the layers of a network, shared by the `Monitor`s wrapping
+/// them through a `RefCell`, and the action they take on updates leaving NaN
+/// or infinite parameters or gradients.  Unlike debug assertions, monitoring
+/// is enabled in release builds.
+///
+pub struct Health {
+    action: Action,
+    epsilon: Fxx,
+    snapshot_every: usize,
+    layers: BTreeMap<String, LayerHealth>,
+}
+
+impl Health {
+    ///
+    /// # Arguments
+    /// * `action` - the action on NaN or infinite parameters or gradients.
+    ///
+    pub fn new(action: Action) -> Self {
+        Health {
+            action,
+            epsilon: 1e-3,
+            snapshot_every: 10,
+            layers: BTreeMap::new(),
+        }
+    }
+
+    pub fn get_action(&self) -> Action {
+        self.action
+    }
+
+    ///
+    /// Count logit outputs within epsilon of 0 or 1 as saturated, 1e-3 by
+    /// default.
+    ///
+    pub fn set_saturation_epsilon(&mut self, epsilon: Fxx) {
+        self.epsilon = epsilon;
+    }
+
+    ///
+    /// Snapshot the parameters for `Action::Rollback` after this many healthy
+    /// updates of each layer, 10 by default.
+    ///
+    pub fn set_snapshot_every(&mut self, snapshot_every: usize) {
+        self.snapshot_every = snapshot_every.max(1);
+    }
+
+    pub fn get_layer(&self, name: &str) -> Option<&LayerHealth> {
+        self.layers.get(name)
+    }
+
+    pub fn get_layers(&self) -> &BTreeMap<String, LayerHealth> {
+        &self.layers
+    }
+
+    ///
+    /// Whether no update has left NaN or infinite parameters or gradients.
+    ///
+    pub fn is_healthy(&self) -> bool {
+        self.layers.values().all(|layer| layer.non_finite == 0)
+    }
+
+    ///
+    /// Fail with `Error::NonFinite` naming the first layer left with NaN or
+    /// infinite parameters or gradients by an update, e.g. to stop training.
+    ///
+    pub fn check(&self) -> Result<(), Error> {
+        match self.layers.iter().find(|(_, layer)| layer.non_finite > 0) {
+            Some((name, layer)) => Err(Error::NonFinite(format!(
+                "{} in {} of {} updates",
+                name, layer.non_finite, layer.updates
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    ///
+    /// Start counting saturated outputs and dead units afresh, e.g. each
+    /// epoch.
+    ///
+    pub fn reset_outputs(&mut self) {
+        for layer in self.layers.values_mut() {
+            layer.outputs = 0;
+            layer.saturated = 0;
+            layer.non_finite_outputs = 0;
+            layer.active.clear();
+        }
+    }
+
+    fn observe<R, C, S>(&mut self, name: &str, activation: Activation, y: &Matrix<Fxx, R, C, S>)
+    where
+        R: Dim,
+        C: Dim,
+        S: Storage<Fxx, R, C>,
+    {
+        let epsilon = self.epsilon;
+        if let Some(layer) = self.layers.get_mut(name) {
+            layer.observe(activation, epsilon, y);
+        }
+    }
+}
+
+///
+/// One line per layer: its update count, weight, update and gradient norms,
+/// saturation, dead units and NaN or infinite counts.
+///
+impl fmt::Display for Health {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, layer) in &self.layers {
+            writeln!(
+                f,
+                "{} updates={} |w|={} |dw|={} |de_dy|={} saturation={} dead={} \
+                 non_finite={} non_finite_outputs={} skipped={} rollbacks={}",
+                name,
+                layer.updates,
+                layer.weight_norm,
+                layer.update_norm,
+                layer.gradient_norm,
+                layer.saturation(),
+                layer.dead_units(),
+                layer.non_finite,
+                layer.non_finite_outputs,
+                layer.skipped,
+                layer.rollbacks
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The Euclidean norm, summed in double precision to avoid overflow.
+fn norm(v: &[Fxx]) -> Fxx {
+    v.iter().map(|&vi| (vi as f64).powi(2)).sum::<f64>().sqrt() as Fxx
+}
+
+fn distance(a: &[Fxx], b: &[Fxx]) -> Fxx {
+    a.iter()
+        .zip(b.iter())
+        .map(|(&ai, &bi)| (ai as f64 - bi as f64).powi(2))
+        .sum::<f64>()
+        .sqrt() as Fxx
+}
+
+/// The root mean square over the samples of a mini-batch of their norms.
+fn batch_norm<M: DimName>(m: &Batch<M>) -> Fxx
+where
+    DefaultAllocator: Allocator<Fxx, M, Dynamic>,
+{
+    (m.norm_squared() / m.ncols().max(1) as Fxx).sqrt()
+}
+
+///
+/// A model, typically one layer of a network, whose outputs, gradients and
+/// parameters are recorded in a shared `Health`.  After each update or
+/// backpropagation it checks the parameters and gradients, warning, skipping
+/// the update or rolling back to the last healthy snapshot if any are NaN or
+/// infinite.  Unless warning, a bad update also returns a zero input gradient
+/// so the NaNs don't spread to the layers before it.
+///
+pub struct Monitor<'a, M: DimName, N: DimName> {
+    name: String,
+    model: &'a mut dyn Model<M, N>,
+    health: &'a RefCell<Health>,
+    activation: Activation,
+    /// The parameters after the latest kept update.
+    params: Vec<Fxx>,
+    /// The parameters restored by `Action::Rollback`.
+    snapshot: Vec<Fxx>,
+}
+
+impl<'a, M, N> Monitor<'a, M, N>
+where
+    M: DimName,
+    N: DimName,
+{
+    ///
+    /// # Arguments
+    /// * `name` - the layer's name in the health statistics.
+    /// * `model` - the model monitored.
+    /// * `health` - the statistics of the network.
+    /// * `activation` - the activation applied to the model's outputs.
+    ///
+    pub fn new(
+        name: &str,
+        model: &'a mut dyn Model<M, N>,
+        health: &'a RefCell<Health>,
+        activation: Activation,
+    ) -> Self {
+        let mut params = Vec::new();
+        model.get_params(&mut params);
+        let layer = LayerHealth {
+            weight_norm: norm(&params),
+            ..Default::default()
+        };
+        health.borrow_mut().layers.insert(name.to_string(), layer);
+        Monitor {
+            name: name.to_string(),
+            model,
+            health,
+            activation,
+            snapshot: params.clone(),
+            params,
+        }
+    }
+
+    ///
+    /// Record an update or backpropagation, returning whether it was healthy.
+    ///
+    fn check(&mut self, gradient_norm: Fxx, finite_gradients: bool) -> bool {
+        let mut params = Vec::new();
+        self.model.get_params(&mut params);
+        let finite =
+            finite_gradients && gradient_norm.is_finite() && params.iter().all(|p| p.is_finite());
+
+        let mut health = self.health.borrow_mut();
+        let action = health.action;
+        let snapshot_every = health.snapshot_every;
+        let layer = health.layers.entry(self.name.clone()).or_default();
+        layer.updates += 1;
+        layer.gradient_norm = gradient_norm;
+        if finite || action == Action::Warn {
+            layer.update_norm = distance(&params, &self.params);
+            layer.weight_norm = norm(&params);
+            self.params = params;
+        }
+        if finite {
+            if layer.updates.is_multiple_of(snapshot_every) {
+                self.snapshot.clone_from(&self.params);
+            }
+            return true;
+        }
+
+        layer.non_finite += 1;
+        match action {
+            Action::Warn => {
+                warn!("{} update {} not finite", self.name, layer.updates);
+            }
+            Action::Skip => {
+                layer.skipped += 1;
+                warn!("{} update {} not finite, skipped", self.name, layer.updates);
+                self.model.set_params(&self.params);
+            }
+            Action::Rollback => {
+                layer.rollbacks += 1;
+                warn!(
+                    "{} update {} not finite, rolled back",
+                    self.name, layer.updates
+                );
+                self.params.clone_from(&self.snapshot);
+                self.model.set_params(&self.params);
+            }
+        }
+        false
+    }
+
+    fn observe<R, C, S>(&self, y: &Matrix<Fxx, R, C, S>)
+    where
+        R: Dim,
+        C: Dim,
+        S: Storage<Fxx, R, C>,
+    {
+        self.health
+            .borrow_mut()
+            .observe(&self.name, self.activation, y);
+    }
+
+    fn is_kept(&self, healthy: bool) -> bool {
+        healthy || self.health.borrow().action == Action::Warn
+    }
+}
+
+impl<'a, M, N> Model<M, N> for Monitor<'a, M, N>
+where
+    M: DimName,
+    N: DimName,
+{
+    fn backpropagate(&mut self, x: &VectorN<Fxx, M>, de_dy: &VectorN<Fxx, N>) -> VectorN<Fxx, M>
+    where
+        DefaultAllocator: Allocator<Fxx, M> + Allocator<Fxx, N>,
+    {
+        let de_dx = self.model.backpropagate(x, de_dy);
+        let healthy = self.check(de_dy.norm(), !has_nan(&de_dx));
+        if self.is_kept(healthy) {
+            de_dx
+        } else {
+            debug!("{} backprop zeroed", self.name);
+            VectorN::<Fxx, M>::zeros()
+        }
+    }
+
+    fn backpropagate_batch(&mut self, x: &Batch<M>, de_dy: &Batch<N>) -> Batch<M>
+    where
+        DefaultAllocator: Allocator<Fxx, M>
+            + Allocator<Fxx, N>
+            + Allocator<Fxx, M, Dynamic>
+            + Allocator<Fxx, N, Dynamic>,
+    {
+        let de_dx = self.model.backpropagate_batch(x, de_dy);
+        let healthy = self.check(batch_norm(de_dy), !has_nan(&de_dx));
+        if self.is_kept(healthy) {
+            de_dx
+        } else {
+            debug!("{} batch backprop zeroed", self.name);
+            Batch::<M>::zeros(x.ncols())
+        }
+    }
+
+    #[inline]
+    fn num_inputs(&self) -> usize {
+        M::dim()
+    }
+
+    #[inline]
+    fn num_outputs(&self) -> usize {
+        N::dim()
+    }
+
+    fn predict(&self, x: &VectorN<Fxx, M>) -> VectorN<Fxx, N>
+    where
+        DefaultAllocator: Allocator<Fxx, M> + Allocator<Fxx, N>,
+    {
+        let y = self.model.predict(x);
+        self.observe(&y);
+        y
+    }
+
+    fn predict_batch(&self, x: &Batch<M>) -> Batch<N>
+    where
+        DefaultAllocator: Allocator<Fxx, M>
+            + Allocator<Fxx, N>
+            + Allocator<Fxx, M, Dynamic>
+            + Allocator<Fxx, N, Dynamic>,
+    {
+        let y = self.model.predict_batch(x);
+        self.observe(&y);
+        y
+    }
+
+    ///
+    /// Update the model, taking the gradient norm as that of the error of its
+    /// prediction before the update.
+    ///
+    fn update(&mut self, x: &VectorN<Fxx, M>, y: &VectorN<Fxx, N>) -> VectorN<Fxx, M>
+    where
+        DefaultAllocator: Allocator<Fxx, M> + Allocator<Fxx, N>,
+    {
+        let error = (self.model.predict(x) - y).norm();
+        let de_dx = self.model.update(x, y);
+        let healthy = self.check(error, !has_nan(&de_dx));
+        if self.is_kept(healthy) {
+            de_dx
+        } else {
+            VectorN::<Fxx, M>::zeros()
+        }
+    }
+
+    fn update_batch(&mut self, x: &Batch<M>, y: &Batch<N>) -> Batch<M>
+    where
+        DefaultAllocator: Allocator<Fxx, M>
+            + Allocator<Fxx, N>
+            + Allocator<Fxx, M, Dynamic>
+            + Allocator<Fxx, N, Dynamic>,
+    {
+        let error = batch_norm(&(self.model.predict_batch(x) - y));
+        let de_dx = self.model.update_batch(x, y);
+        let healthy = self.check(error, !has_nan(&de_dx));
+        if self.is_kept(healthy) {
+            de_dx
+        } else {
+            Batch::<M>::zeros(x.ncols())
+        }
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.model.set_training(training);
+    }
+
+    fn get_params(&self, params: &mut Vec<Fxx>) {
+        self.model.get_params(params);
+    }
+
+    fn set_params<'p>(&mut self, params: &'p [Fxx]) -> &'p [Fxx] {
+        let rest = self.model.set_params(params);
+        self.params.clear();
+        self.model.get_params(&mut self.params);
+        rest
+    }
+}
+
+#[cfg(test)]
+#[path = "./health_test.rs"]
+mod health_test;
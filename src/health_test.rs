@@ -0,0 +1,170 @@
+use super::*;
+
+use assert_approx_eq::assert_approx_eq;
+
+use na::{Vector1, Vector2, U1, U2};
+
+use crate::{LayeredModel, LinearModel, Logit, Relu, SGDTrainer, UpdateParams};
+
+const LEARNING_PARAMS: UpdateParams = UpdateParams {
+    step_size: 0.1,
+    l2_reg: 0.0,
+};
+
+fn params(model: &dyn Model<U2, U1>) -> Vec<Fxx> {
+    let mut params = Vec::new();
+    model.get_params(&mut params);
+    params
+}
+
+#[test]
+fn parses_actions() {
+    for action in &[Action::Warn, Action::Skip, Action::Rollback] {
+        assert_eq!(action.to_string().parse::<Action>(), Ok(*action));
+    }
+    assert!("ignore".parse::<Action>().is_err());
+}
+
+#[test]
+fn records_norms() {
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut linear = LinearModel::<U2, U1>::new_random(&mut trainer);
+    linear.set_params(&[3.0, 0.0, 4.0]);
+    let health = RefCell::new(Health::new(Action::Warn));
+    let mut model = Monitor::new("linear", &mut linear, &health, Activation::Linear);
+    assert_eq!(
+        health.borrow().get_layer("linear").unwrap().weight_norm,
+        5.0
+    );
+
+    let before = params(&model);
+    model.update(&Vector2::new(1.0, 0.0), &Vector1::new(1.0));
+    let after = params(&model);
+    let layer = health.borrow().get_layer("linear").unwrap().clone();
+    assert_eq!(layer.updates, 1);
+    assert_eq!(layer.gradient_norm, 6.0);
+    assert_approx_eq!(
+        layer.update_norm,
+        before
+            .iter()
+            .zip(after.iter())
+            .map(|(b, a)| (a - b) * (a - b))
+            .sum::<Fxx>()
+            .sqrt()
+    );
+    assert!(health.borrow().is_healthy());
+    assert!(health.borrow().check().is_ok());
+}
+
+#[test]
+fn measures_saturation() {
+    let health = RefCell::new(Health::new(Action::Warn));
+    let mut train0 = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut linear0 = LinearModel::<U2, U2>::new_random(&mut train0);
+    // the second unit is never positive for positive inputs
+    linear0.set_params(&[1.0, -1.0, 1.0, -1.0, 0.0, 0.0]);
+    let mut relu = Relu::new(&mut linear0);
+    let relu = Monitor::new("relu", &mut relu, &health, Activation::Relu);
+    let mut train1 = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut linear1 = LinearModel::<U2, U1>::new_random(&mut train1);
+    linear1.set_params(&[100.0, 0.0, 0.0]);
+    let mut logit = Logit::new(&mut linear1);
+    let logit = Monitor::new("logit", &mut logit, &health, Activation::Logit);
+
+    relu.predict(&Vector2::new(1.0, 2.0));
+    logit.predict(&Vector2::new(1.0, 0.0));
+    logit.predict(&Vector2::new(0.0, 0.0));
+    {
+        let health = health.borrow();
+        let relu = health.get_layer("relu").unwrap();
+        assert_eq!((relu.outputs, relu.dead_units()), (2, 1));
+        let logit = health.get_layer("logit").unwrap();
+        assert_eq!((logit.outputs, logit.saturated), (2, 1));
+        assert_eq!(logit.saturation(), 0.5);
+    }
+    health.borrow_mut().reset_outputs();
+    assert_eq!(health.borrow().get_layer("relu").unwrap().dead_units(), 0);
+    assert_eq!(health.borrow().get_layer("logit").unwrap().outputs, 0);
+}
+
+#[test]
+fn warns() {
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut linear = LinearModel::<U2, U1>::new_random(&mut trainer);
+    let health = RefCell::new(Health::new(Action::Warn));
+    let mut model = Monitor::new("linear", &mut linear, &health, Activation::Linear);
+    let de_dx = model.update(&Vector2::new(1.0, 0.0), &Vector1::new(Fxx::NAN));
+    assert!(has_nan(&de_dx));
+    assert!(params(&model).iter().any(|p| p.is_nan()));
+    let layer = health.borrow().get_layer("linear").unwrap().clone();
+    assert_eq!(
+        (layer.non_finite, layer.skipped, layer.rollbacks),
+        (1, 0, 0)
+    );
+    assert!(!health.borrow().is_healthy());
+    match health.borrow().check() {
+        Err(Error::NonFinite(msg)) => assert_eq!(msg, "linear in 1 of 1 updates"),
+        result => panic!("expected non-finite error, got {:?}", result),
+    };
+}
+
+#[test]
+fn skips_updates() {
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut linear = LinearModel::<U2, U1>::new_random(&mut trainer);
+    let health = RefCell::new(Health::new(Action::Skip));
+    let mut model = Monitor::new("linear", &mut linear, &health, Activation::Linear);
+    model.update(&Vector2::new(1.0, 0.0), &Vector1::new(1.0));
+    let before = params(&model);
+    let de_dx = model.update(&Vector2::new(1.0, 0.0), &Vector1::new(Fxx::NAN));
+    assert_eq!(de_dx, Vector2::zeros());
+    assert_eq!(params(&model), before);
+    let layer = health.borrow().get_layer("linear").unwrap().clone();
+    assert_eq!((layer.updates, layer.non_finite, layer.skipped), (2, 1, 1));
+}
+
+#[test]
+fn rolls_back() {
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut linear = LinearModel::<U2, U1>::new_random(&mut trainer);
+    let health = RefCell::new(Health::new(Action::Rollback));
+    health.borrow_mut().set_snapshot_every(2);
+    let mut model = Monitor::new("linear", &mut linear, &health, Activation::Linear);
+    let x = Vector2::new(1.0, 0.5);
+    let y = Vector1::new(2.0);
+    model.update(&x, &y);
+    model.update(&x, &y);
+    let snapshot = params(&model);
+    model.update(&x, &y);
+    assert_ne!(params(&model), snapshot);
+    model.update(&x, &Vector1::new(Fxx::INFINITY));
+    assert_eq!(params(&model), snapshot);
+    assert_eq!(health.borrow().get_layer("linear").unwrap().rollbacks, 1);
+}
+
+#[test]
+fn monitors_layers() {
+    let health = RefCell::new(Health::new(Action::Skip));
+    let mut train0 = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut linear0 = LinearModel::<U2, U2>::new_random(&mut train0);
+    let mut relu = Relu::new(&mut linear0);
+    let mut layer0 = Monitor::new("relu", &mut relu, &health, Activation::Relu);
+    let mut train1 = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut linear1 = LinearModel::<U2, U1>::new_random(&mut train1);
+    let mut layer1 = Monitor::new("linear", &mut linear1, &health, Activation::Linear);
+    let mut model = LayeredModel::<U2, U2, U1>::new(&mut layer0, &mut layer1);
+
+    let x = Batch::<U2>::from_column_slice(&[1.0, 0.5, -0.5, 1.0]);
+    let y = Batch::<U1>::from_column_slice(&[1.0, 0.0]);
+    model.update_batch(&x, &y);
+    let before = params(&model);
+    model.update_batch(&x, &Batch::<U1>::from_column_slice(&[Fxx::NAN, 0.0]));
+    assert_eq!(params(&model), before);
+
+    let health = health.borrow();
+    assert_eq!(health.get_layer("relu").unwrap().updates, 2);
+    assert_eq!(health.get_layer("linear").unwrap().skipped, 1);
+    let report = health.to_string();
+    assert_eq!(report.lines().count(), 2);
+    assert!(report.starts_with("linear updates=2 "));
+}
@@ -0,0 +1,505 @@
+extern crate nalgebra as na;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, BufRead, Read, Write};
+use std::iter::Peekable;
+use std::str::FromStr;
+
+use na::allocator::Allocator;
+use na::{DefaultAllocator, DimName};
+
+use crate::error::Error;
+use crate::metrics::Metric;
+use crate::model::{Fxx, Model};
+
+///
+/// Whether a record summarizes a single update or a whole epoch.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Phase {
+    Step,
+    Epoch,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Phase::Step => write!(f, "step"),
+            Phase::Epoch => write!(f, "epoch"),
+        }
+    }
+}
+
+impl FromStr for Phase {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s {
+            "step" => Ok(Phase::Step),
+            "epoch" => Ok(Phase::Epoch),
+            _ => Err(invalid(format!("unknown phase {}", s))),
+        }
+    }
+}
+
+///
+/// Named values recorded after an update or epoch, numbered by the steps
+/// and epochs completed at the time of recording.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    pub phase: Phase,
+    pub step: usize,
+    pub epoch: usize,
+    pub values: BTreeMap<String, Fxx>,
+}
+
+impl Record {
+    pub fn get(&self, name: &str) -> Option<Fxx> {
+        self.values.get(name).copied()
+    }
+}
+
+///
+/// The history of a training run: the loss and error gradient norm of each
+/// update, metrics of each epoch, the learning rate and the norms of the
+/// model's parameters and of their changes.  Histories are written to and
+/// read from CSV or JSON Lines files so that runs can be compared.
+///
+/// Step records include:
+/// * `loss` - the training loss of the update.
+/// * `gradient_norm` - the norm of the error gradient backpropagated by the
+///   update.
+///
+/// Epoch records, and step records every `set_step_norms` steps, include:
+/// * `weight_norm` - the L2 norm of the model parameters.
+/// * `update_norm` - the L2 norm of the parameter change since the previous
+///   record of the same phase with parameter norms.
+///
+/// Every record includes the `learning_rate`, when one is set.
+///
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct History {
+    records: Vec<Record>,
+    step: usize,
+    epoch: usize,
+    learning_rate: Option<Fxx>,
+    step_norms: usize,
+    step_params: Vec<Fxx>,
+    epoch_params: Vec<Fxx>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        History::default()
+    }
+
+    ///
+    /// Record the learning rate, e.g. `UpdateParams::step_size`, with
+    /// subsequent records.
+    ///
+    pub fn set_learning_rate(&mut self, learning_rate: Fxx) {
+        self.learning_rate = Some(learning_rate);
+    }
+
+    pub fn get_learning_rate(&self) -> Option<Fxx> {
+        self.learning_rate
+    }
+
+    ///
+    /// Record parameter norms with every this many steps, or never if zero,
+    /// the default.  Each costs a copy of the model parameters.
+    ///
+    pub fn set_step_norms(&mut self, every: usize) {
+        self.step_norms = every;
+    }
+
+    pub fn get_records(&self) -> &[Record] {
+        &self.records
+    }
+
+    /// The number of steps recorded.
+    pub fn get_step(&self) -> usize {
+        self.step
+    }
+
+    /// The number of epochs recorded.
+    pub fn get_epoch(&self) -> usize {
+        self.epoch
+    }
+
+    ///
+    /// Record the training loss of an update to the model.
+    ///
+    /// # Arguments
+    /// * `model` - the updated model.
+    /// * `loss` - the loss of the update.
+    /// * `gradient_norm` - the norm of the error gradient with respect to the
+    ///   model inputs returned by the update, e.g. the root mean square over
+    ///   a mini-batch of the norms of its samples.
+    ///
+    pub fn record_step<M: DimName, N: DimName>(
+        &mut self,
+        model: &dyn Model<M, N>,
+        loss: Fxx,
+        gradient_norm: Fxx,
+    ) {
+        self.step += 1;
+        let mut values = self.values();
+        if self.step_norms > 0 && self.step.is_multiple_of(self.step_norms) {
+            let mut params = Vec::new();
+            model.get_params(&mut params);
+            insert_norms(&mut values, &params, &self.step_params);
+            self.step_params = params;
+        }
+        values.insert("loss".to_string(), loss);
+        values.insert("gradient_norm".to_string(), gradient_norm);
+        self.push(Phase::Step, values);
+    }
+
+    ///
+    /// Record named values, e.g. validation losses, at the end of an epoch.
+    ///
+    pub fn record_epoch<M: DimName, N: DimName>(
+        &mut self,
+        model: &dyn Model<M, N>,
+        values: &[(&str, Fxx)],
+    ) {
+        self.epoch += 1;
+        let mut params = Vec::new();
+        model.get_params(&mut params);
+        let mut record = self.values();
+        insert_norms(&mut record, &params, &self.epoch_params);
+        for &(name, value) in values {
+            record.insert(name.to_string(), value);
+        }
+        self.epoch_params = params;
+        self.push(Phase::Epoch, record);
+    }
+
+    ///
+    /// Add the values of metrics, by name, to the latest record.
+    ///
+    pub fn record_metrics<N>(&mut self, metrics: &[&dyn Metric<N>])
+    where
+        N: DimName,
+        DefaultAllocator: Allocator<Fxx, N>,
+    {
+        if let Some(record) = self.records.last_mut() {
+            for metric in metrics {
+                record.values.insert(metric.name(), metric.value());
+            }
+        }
+    }
+
+    /// The values of every record.
+    fn values(&self) -> BTreeMap<String, Fxx> {
+        let mut values = BTreeMap::new();
+        if let Some(rate) = self.learning_rate {
+            values.insert("learning_rate".to_string(), rate);
+        }
+        values
+    }
+
+    fn push(&mut self, phase: Phase, values: BTreeMap<String, Fxx>) {
+        self.records.push(Record {
+            phase,
+            step: self.step,
+            epoch: self.epoch,
+            values,
+        });
+    }
+
+    ///
+    /// The names of all values recorded, in order.
+    ///
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .records
+            .iter()
+            .flat_map(|r| r.values.keys().cloned())
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    ///
+    /// The values of a name recorded in a phase, numbered by step for
+    /// `Phase::Step` and by epoch for `Phase::Epoch`, e.g. to plot a
+    /// learning curve.
+    ///
+    pub fn series(&self, phase: Phase, name: &str) -> Vec<(usize, Fxx)> {
+        self.records
+            .iter()
+            .filter(|r| r.phase == phase)
+            .filter_map(|r| {
+                let x = match phase {
+                    Phase::Step => r.step,
+                    Phase::Epoch => r.epoch,
+                };
+                r.get(name).map(|value| (x, value))
+            })
+            .collect()
+    }
+
+    ///
+    /// The least finite value of a name recorded in a phase, with its step
+    /// or epoch number, to compare runs.
+    ///
+    pub fn min(&self, phase: Phase, name: &str) -> Option<(usize, Fxx)> {
+        self.series(phase, name)
+            .into_iter()
+            .filter(|(_, value)| value.is_finite())
+            .fold(None, |min, (x, value)| match min {
+                Some((_, least)) if least <= value => min,
+                _ => Some((x, value)),
+            })
+    }
+
+    ///
+    /// Write one row per record with columns phase, step, epoch and then
+    /// every recorded name, leaving values missing from a record empty.
+    ///
+    pub fn write_csv<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        let names = self.names();
+        let mut csv_writer = csv::Writer::from_writer(writer);
+        let mut header = vec!["phase".to_string(), "step".into(), "epoch".into()];
+        header.extend(names.iter().cloned());
+        csv_writer.write_record(&header)?;
+        for record in &self.records {
+            let mut row = vec![
+                record.phase.to_string(),
+                record.step.to_string(),
+                record.epoch.to_string(),
+            ];
+            row.extend(
+                names
+                    .iter()
+                    .map(|name| record.get(name).map_or(String::new(), |v| v.to_string())),
+            );
+            csv_writer.write_record(&row)?;
+        }
+        csv_writer.flush()?;
+        Ok(())
+    }
+
+    ///
+    /// Read a history written by `write_csv`.
+    ///
+    pub fn read_csv<R: Read>(reader: R) -> Result<Self, Error> {
+        let mut csv_reader = csv::Reader::from_reader(reader);
+        let header: Vec<String> = csv_reader.headers()?.iter().map(String::from).collect();
+        if header.len() < 3 || header[..3] != ["phase", "step", "epoch"] {
+            return Err(invalid("expected columns phase, step, epoch".to_string()));
+        }
+        let mut history = History::new();
+        for row in csv_reader.records() {
+            let row = row?;
+            let mut values = BTreeMap::new();
+            for (name, field) in header[3..].iter().zip(row.iter().skip(3)) {
+                if !field.is_empty() {
+                    values.insert(name.clone(), parse(field)?);
+                }
+            }
+            history.restore(Record {
+                phase: row[0].parse()?,
+                step: parse(&row[1])?,
+                epoch: parse(&row[2])?,
+                values,
+            });
+        }
+        Ok(history)
+    }
+
+    ///
+    /// Write one JSON object per record, writing non-finite values as null.
+    ///
+    pub fn write_jsonl<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        for record in &self.records {
+            write!(
+                writer,
+                "{{\"phase\":\"{}\",\"step\":{},\"epoch\":{}",
+                record.phase, record.step, record.epoch
+            )?;
+            for (name, value) in &record.values {
+                write!(writer, ",{}:", quote(name))?;
+                if value.is_finite() {
+                    write!(writer, "{}", value)?;
+                } else {
+                    write!(writer, "null")?;
+                }
+            }
+            writeln!(writer, "}}")?;
+        }
+        Ok(())
+    }
+
+    ///
+    /// Read a history written by `write_jsonl`, reading nulls as NaN.
+    ///
+    pub fn read_jsonl<R: BufRead>(reader: R) -> Result<Self, Error> {
+        let mut history = History::new();
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let mut phase = None;
+            let mut step = None;
+            let mut epoch = None;
+            let mut values = BTreeMap::new();
+            for (name, value) in parse_object(&line)? {
+                match (name.as_str(), value) {
+                    ("phase", Json::String(s)) => phase = Some(s.parse()?),
+                    ("step", Json::Number(s)) => step = Some(parse(&s)?),
+                    ("epoch", Json::Number(s)) => epoch = Some(parse(&s)?),
+                    (_, Json::Number(s)) => {
+                        values.insert(name, parse(&s)?);
+                    }
+                    (_, Json::Null) => {
+                        values.insert(name, Fxx::NAN);
+                    }
+                    _ => return Err(invalid(format!("unexpected value of {}", name))),
+                }
+            }
+            match (phase, step, epoch) {
+                (Some(phase), Some(step), Some(epoch)) => history.restore(Record {
+                    phase,
+                    step,
+                    epoch,
+                    values,
+                }),
+                _ => return Err(invalid(format!("missing phase, step or epoch: {}", line))),
+            }
+        }
+        Ok(history)
+    }
+
+    fn restore(&mut self, record: Record) {
+        self.step = self.step.max(record.step);
+        self.epoch = self.epoch.max(record.epoch);
+        if let Some(rate) = record.get("learning_rate") {
+            self.learning_rate = Some(rate);
+        }
+        self.records.push(record);
+    }
+}
+
+fn norm<I: Iterator<Item = Fxx>>(xs: I) -> Fxx {
+    xs.map(|x| x * x).sum::<Fxx>().sqrt()
+}
+
+fn insert_norms(values: &mut BTreeMap<String, Fxx>, params: &[Fxx], previous: &[Fxx]) {
+    values.insert("weight_norm".to_string(), norm(params.iter().copied()));
+    if previous.len() == params.len() {
+        let update = norm(params.iter().zip(previous).map(|(p, q)| p - q));
+        values.insert("update_norm".to_string(), update);
+    }
+}
+
+fn invalid(msg: String) -> Error {
+    Error::Io(io::Error::new(io::ErrorKind::InvalidData, msg))
+}
+
+fn parse<T: FromStr>(s: &str) -> Result<T, Error> {
+    s.parse::<T>()
+        .map_err(|_| invalid(format!("cannot parse '{}'", s)))
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+///
+/// The values of the flat JSON objects written by `write_jsonl`.
+///
+enum Json {
+    String(String),
+    Number(String),
+    Null,
+}
+
+fn parse_object(line: &str) -> Result<Vec<(String, Json)>, Error> {
+    let malformed = || invalid(format!("malformed JSON object: {}", line));
+    let mut chars = line.trim().chars().peekable();
+    if chars.next() != Some('{') {
+        return Err(malformed());
+    }
+    let mut fields = Vec::new();
+    loop {
+        skip_whitespace(&mut chars);
+        match chars.next() {
+            Some('}') if fields.is_empty() => break,
+            Some('"') => (),
+            _ => return Err(malformed()),
+        }
+        let name = parse_string(&mut chars).ok_or_else(malformed)?;
+        skip_whitespace(&mut chars);
+        if chars.next() != Some(':') {
+            return Err(malformed());
+        }
+        skip_whitespace(&mut chars);
+        let value = match chars.peek() {
+            Some('"') => {
+                chars.next();
+                Json::String(parse_string(&mut chars).ok_or_else(malformed)?)
+            }
+            Some('n') => {
+                let null: String = chars.by_ref().take(4).collect();
+                if null != "null" {
+                    return Err(malformed());
+                }
+                Json::Null
+            }
+            _ => {
+                let mut number = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c == ',' || c == '}' || c.is_whitespace() {
+                        break;
+                    }
+                    number.push(c);
+                    chars.next();
+                }
+                Json::Number(number)
+            }
+        };
+        fields.push((name, value));
+        skip_whitespace(&mut chars);
+        match chars.next() {
+            Some(',') => (),
+            Some('}') => break,
+            _ => return Err(malformed()),
+        }
+    }
+    if chars.next().is_some() {
+        return Err(malformed());
+    }
+    Ok(fields)
+}
+
+fn skip_whitespace<I: Iterator<Item = char>>(chars: &mut Peekable<I>) {
+    while chars.peek().is_some_and(|c| c.is_whitespace()) {
+        chars.next();
+    }
+}
+
+/// Parse the rest of a string whose opening quote has been consumed.
+fn parse_string<I: Iterator<Item = char>>(chars: &mut I) -> Option<String> {
+    let mut s = String::new();
+    loop {
+        match chars.next()? {
+            '"' => return Some(s),
+            '\\' => match chars.next()? {
+                c @ '"' | c @ '\\' => s.push(c),
+                _ => return None,
+            },
+            c => s.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./history_test.rs"]
+mod history_test;
@@ -0,0 +1,151 @@
+use super::*;
+
+use na::{Vector1, Vector2, U1, U2};
+
+use crate::metrics::{Regression, RegressionMetric};
+use crate::{LinearModel, SGDTrainer, UpdateParams};
+
+const LEARNING_PARAMS: UpdateParams = UpdateParams {
+    step_size: 0.1,
+    l2_reg: 0.0,
+};
+
+fn run() -> History {
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut model = LinearModel::<U2, U1>::new_random(&mut trainer);
+    model.set_params(&[0.0, 0.0, 0.0]);
+    let mut history = History::new();
+    history.set_learning_rate(LEARNING_PARAMS.step_size);
+    history.set_step_norms(2);
+    let x = Vector2::new(1.0, -1.0);
+    let y = Vector1::new(1.0);
+    for _ in 0..2 {
+        for _ in 0..3 {
+            let loss = (model.predict(&x) - y).norm_squared();
+            let e = model.update(&x, &y);
+            history.record_step(&model, loss, e.norm());
+        }
+        let mut mse = RegressionMetric::new(Regression::Mse);
+        mse.update(&model.predict(&x), &y);
+        history.record_epoch(&model, &[("validation_loss", 0.5)]);
+        history.record_metrics::<U1>(&[&mse]);
+    }
+    history
+}
+
+#[test]
+fn records_steps_and_epochs() {
+    let history = run();
+    assert_eq!(history.get_records().len(), 8);
+    assert_eq!(history.get_step(), 6);
+    assert_eq!(history.get_epoch(), 2);
+
+    let losses = history.series(Phase::Step, "loss");
+    assert_eq!(losses.len(), 6);
+    assert_eq!(losses[0], (1, 1.0));
+    assert!(losses[5].1 < losses[0].1);
+
+    let epochs = history.series(Phase::Epoch, "mse");
+    assert_eq!(epochs.iter().map(|e| e.0).collect::<Vec<_>>(), vec![1, 2]);
+    assert_eq!(history.get_records()[3].step, 3);
+    assert_eq!(history.get_records()[3].epoch, 1);
+
+    // the zero weights backpropagate no error
+    let first = &history.get_records()[0];
+    assert_eq!(first.get("gradient_norm"), Some(0.0));
+    assert_eq!(first.get("learning_rate"), Some(0.1));
+    assert_eq!(first.get("weight_norm"), None);
+    // parameter norms every other step, differenced from the previous
+    let second = &history.get_records()[1];
+    assert!(second.get("gradient_norm").unwrap() > 0.0);
+    assert!(second.get("weight_norm").unwrap() > 0.0);
+    assert_eq!(second.get("update_norm"), None);
+    assert_eq!(history.get_records()[2].get("weight_norm"), None);
+    let fourth = &history.get_records()[4];
+    assert_eq!(fourth.step, 4);
+    assert!(fourth.get("update_norm").unwrap() > 0.0);
+    assert!(history.get_records()[3].get("weight_norm").unwrap() > 0.0);
+
+    assert_eq!(history.min(Phase::Step, "loss").unwrap().0, 6);
+    assert_eq!(
+        history.names(),
+        vec![
+            "gradient_norm",
+            "learning_rate",
+            "loss",
+            "mse",
+            "update_norm",
+            "validation_loss",
+            "weight_norm"
+        ]
+    );
+}
+
+#[test]
+fn round_trips_csv() {
+    let history = run();
+    let mut buffer = Vec::new();
+    history.write_csv(&mut buffer).unwrap();
+    let text = String::from_utf8(buffer.clone()).unwrap();
+    assert!(text.starts_with("phase,step,epoch,gradient_norm,learning_rate,loss,mse,"));
+    // missing values are empty
+    assert!(text
+        .lines()
+        .nth(1)
+        .unwrap()
+        .starts_with("step,1,0,0,0.1,1,,,,"));
+
+    let read = History::read_csv(buffer.as_slice()).unwrap();
+    assert_eq!(read.get_records(), history.get_records());
+    assert_eq!(read.get_step(), 6);
+    assert_eq!(read.get_learning_rate(), Some(0.1));
+}
+
+#[test]
+fn round_trips_jsonl() {
+    let mut history = run();
+    history
+        .records
+        .last_mut()
+        .unwrap()
+        .values
+        .insert("odd \"name\"".to_string(), Fxx::INFINITY);
+    let mut buffer = Vec::new();
+    history.write_jsonl(&mut buffer).unwrap();
+    let text = String::from_utf8(buffer.clone()).unwrap();
+    assert_eq!(text.lines().count(), 8);
+    assert!(text.starts_with("{\"phase\":\"step\",\"step\":1,\"epoch\":0,"));
+    assert!(text.contains("\"odd \\\"name\\\"\":null"));
+
+    let read = History::read_jsonl(buffer.as_slice()).unwrap();
+    assert_eq!(read.get_records()[..7], history.get_records()[..7]);
+    let last = read.get_records().last().unwrap();
+    assert!(last.get("odd \"name\"").unwrap().is_nan());
+    assert_eq!(last.get("mse"), history.get_records()[7].get("mse"));
+}
+
+#[test]
+fn reads_spaced_jsonl() {
+    let text =
+        "{ \"phase\": \"step\", \"step\": 1 , \"epoch\": 0, \"loss\": 0.5, \"mse\" :null }\n";
+    let read = History::read_jsonl(text.as_bytes()).unwrap();
+    let record = &read.get_records()[0];
+    assert_eq!(
+        (record.phase, record.step, record.epoch),
+        (Phase::Step, 1, 0)
+    );
+    assert_eq!(record.get("loss"), Some(0.5));
+    assert!(record.get("mse").unwrap().is_nan());
+}
+
+#[test]
+fn rejects_malformed_files() {
+    assert!(History::read_jsonl("{\"phase\":\"step\"}\n".as_bytes()).is_err());
+    assert!(History::read_jsonl("{\"phase\":\"step\",\"step\":1".as_bytes()).is_err());
+    assert!(History::read_jsonl(
+        "{\"phase\":\"step\",\"step\":1,\"epoch\":0} trailing\n".as_bytes()
+    )
+    .is_err());
+    assert!(History::read_csv("a,b\n1,2\n".as_bytes()).is_err());
+    assert!(History::read_csv("phase,step,epoch\nnope,1,1\n".as_bytes()).is_err());
+}
@@ -0,0 +1,506 @@
+extern crate nalgebra as na;
+
+use na::allocator::Allocator;
+use na::{DMatrix, DefaultAllocator, DimName, MatrixMN};
+
+use rand::distributions::{Distribution, Normal, Uniform};
+use rand::{Rng, RngCore};
+
+use log::debug;
+
+use crate::error::Error;
+use crate::model::Fxx;
+
+///
+/// A random image transformation for data augmentation.
+///
+/// Geometric transformations are also applied to an optional label position
+/// map, a matrix covering the same field of view as the image, possibly at a
+/// different resolution, e.g. the target positions of the conv2d benchmark,
+/// so that labels follow the transformed image.  Photometric transformations
+/// leave the label unchanged.
+///
+pub trait Augmentation {
+    /// Draw the random parameters of the transformation and apply it in
+    /// place.
+    ///
+    /// # Arguments
+    /// * `image` - the image to transform.
+    /// * `label` - a position map to transform geometrically with the image.
+    /// * `rng` - the source of the random parameters.
+    fn augment(
+        &self,
+        image: &mut DMatrix<Fxx>,
+        label: Option<&mut DMatrix<Fxx>>,
+        rng: &mut dyn RngCore,
+    );
+}
+
+///
+/// Resample the image, and label, from source coordinates computed by f from
+/// the destination coordinates, both normalized to [0, 1) by the matrix
+/// dimensions.  Sources outside of the matrix become zero.
+///
+fn warp<F>(image: &mut DMatrix<Fxx>, label: Option<&mut DMatrix<Fxx>>, f: F)
+where
+    F: Fn(Fxx, Fxx) -> (Fxx, Fxx),
+{
+    fn resample<F: Fn(Fxx, Fxx) -> (Fxx, Fxx)>(mat: &mut DMatrix<Fxx>, f: &F) {
+        let (h, w) = (mat.nrows(), mat.ncols());
+        let src = mat.clone();
+        for c in 0..w {
+            for r in 0..h {
+                let (u, v) = f((r as Fxx + 0.5) / h as Fxx, (c as Fxx + 0.5) / w as Fxx);
+                let (sr, sc) = ((u * h as Fxx).floor(), (v * w as Fxx).floor());
+                mat[(r, c)] = if sr >= 0.0 && sc >= 0.0 && sr < h as Fxx && sc < w as Fxx {
+                    src[(sr as usize, sc as usize)]
+                } else {
+                    0.0
+                };
+            }
+        }
+    }
+
+    resample(image, &f);
+    if let Some(label) = label {
+        resample(label, &f);
+    }
+}
+
+///
+/// Crop a random window, covering a random fraction of each side, and scale
+/// it back to the full image size.
+///
+pub struct RandomCrop {
+    min_scale: Fxx,
+    max_scale: Fxx,
+}
+
+impl RandomCrop {
+    ///
+    /// Fails unless 0 < min_scale <= max_scale <= 1.
+    ///
+    /// # Arguments
+    /// * `min_scale` - the least fraction, in (0, 1], of each side to keep.
+    /// * `max_scale` - the greatest fraction, in [min_scale, 1], to keep.
+    ///
+    pub fn new(min_scale: Fxx, max_scale: Fxx) -> Result<Self, Error> {
+        if !(0.0 < min_scale && min_scale <= max_scale && max_scale <= 1.0) {
+            return Err(Error::Config(format!(
+                "invalid crop scales {}..{}",
+                min_scale, max_scale
+            )));
+        }
+        Ok(RandomCrop {
+            min_scale,
+            max_scale,
+        })
+    }
+}
+
+impl Augmentation for RandomCrop {
+    fn augment(
+        &self,
+        image: &mut DMatrix<Fxx>,
+        label: Option<&mut DMatrix<Fxx>>,
+        rng: &mut dyn RngCore,
+    ) {
+        let s = if self.min_scale < self.max_scale {
+            rng.gen_range(self.min_scale, self.max_scale)
+        } else {
+            self.min_scale
+        };
+        let u0 = rng.gen::<Fxx>() * (1.0 - s);
+        let v0 = rng.gen::<Fxx>() * (1.0 - s);
+        debug!("crop {} at ({}, {})", s, u0, v0);
+        warp(image, label, |u, v| (u0 + s * u, v0 + s * v));
+    }
+}
+
+///
+/// Mirror the image left-to-right, or top-to-bottom, with a given
+/// probability.
+///
+pub struct Flip {
+    horizontal: bool,
+    p: Fxx,
+}
+
+impl Flip {
+    /// Mirror columns, left-to-right, with probability p.
+    pub fn horizontal(p: Fxx) -> Self {
+        Flip {
+            horizontal: true,
+            p,
+        }
+    }
+
+    /// Mirror rows, top-to-bottom, with probability p.
+    pub fn vertical(p: Fxx) -> Self {
+        Flip {
+            horizontal: false,
+            p,
+        }
+    }
+}
+
+impl Augmentation for Flip {
+    fn augment(
+        &self,
+        image: &mut DMatrix<Fxx>,
+        label: Option<&mut DMatrix<Fxx>>,
+        rng: &mut dyn RngCore,
+    ) {
+        if rng.gen::<Fxx>() >= self.p {
+            return;
+        }
+        if self.horizontal {
+            warp(image, label, |u, v| (u, 1.0 - v));
+        } else {
+            warp(image, label, |u, v| (1.0 - u, v));
+        }
+    }
+}
+
+///
+/// Rotate counter-clockwise by a uniformly chosen multiple of 90 degrees.
+/// Images or labels that aren't square are only turned by 0 or 180 degrees,
+/// since a quarter turn would change their shape.
+///
+pub struct Rotate90 {}
+
+impl Rotate90 {
+    pub fn new() -> Self {
+        Rotate90 {}
+    }
+}
+
+impl Default for Rotate90 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Augmentation for Rotate90 {
+    fn augment(
+        &self,
+        image: &mut DMatrix<Fxx>,
+        label: Option<&mut DMatrix<Fxx>>,
+        rng: &mut dyn RngCore,
+    ) {
+        let square = image.is_square() && label.as_ref().is_none_or(|l| l.is_square());
+        let quarters = if square {
+            rng.gen_range(0, 4)
+        } else {
+            2 * rng.gen_range(0, 2)
+        };
+        match quarters {
+            1 => warp(image, label, |u, v| (v, 1.0 - u)),
+            2 => warp(image, label, |u, v| (1.0 - u, 1.0 - v)),
+            3 => warp(image, label, |u, v| (1.0 - v, u)),
+            _ => (),
+        }
+    }
+}
+
+///
+/// Rotate about the image center by a uniformly chosen small angle, filling
+/// uncovered corners with zero.
+///
+pub struct RandomRotation {
+    max_degrees: Fxx,
+}
+
+impl RandomRotation {
+    ///
+    /// # Arguments
+    /// * `max_degrees` - the greatest rotation, in either direction.
+    ///
+    pub fn new(max_degrees: Fxx) -> Self {
+        RandomRotation { max_degrees }
+    }
+}
+
+impl Augmentation for RandomRotation {
+    fn augment(
+        &self,
+        image: &mut DMatrix<Fxx>,
+        label: Option<&mut DMatrix<Fxx>>,
+        rng: &mut dyn RngCore,
+    ) {
+        if self.max_degrees <= 0.0 {
+            return;
+        }
+        let theta = rng
+            .gen_range(-self.max_degrees, self.max_degrees)
+            .to_radians();
+        let (sin, cos) = theta.sin_cos();
+        // rotate in the image's pixel aspect, shared by the label
+        let (h, w) = (image.nrows() as Fxx, image.ncols() as Fxx);
+        warp(image, label, |u, v| {
+            let (y, x) = ((u - 0.5) * h, (v - 0.5) * w);
+            let (sy, sx) = (cos * y - sin * x, sin * y + cos * x);
+            (sy / h + 0.5, sx / w + 0.5)
+        });
+    }
+}
+
+///
+/// Add a uniformly chosen offset to every pixel.
+///
+pub struct Brightness {
+    max_delta: Fxx,
+}
+
+impl Brightness {
+    ///
+    /// # Arguments
+    /// * `max_delta` - the greatest offset, in either direction.
+    ///
+    pub fn new(max_delta: Fxx) -> Self {
+        Brightness { max_delta }
+    }
+}
+
+impl Augmentation for Brightness {
+    fn augment(
+        &self,
+        image: &mut DMatrix<Fxx>,
+        _label: Option<&mut DMatrix<Fxx>>,
+        rng: &mut dyn RngCore,
+    ) {
+        if self.max_delta > 0.0 {
+            let delta = rng.gen_range(-self.max_delta, self.max_delta);
+            image.add_scalar_mut(delta);
+        }
+    }
+}
+
+///
+/// Scale the difference of every pixel from the image mean by a uniformly
+/// chosen factor.
+///
+pub struct Contrast {
+    min_factor: Fxx,
+    max_factor: Fxx,
+}
+
+impl Contrast {
+    ///
+    /// Fails unless min_factor <= max_factor.
+    ///
+    pub fn new(min_factor: Fxx, max_factor: Fxx) -> Result<Self, Error> {
+        if min_factor <= max_factor {
+            Ok(Contrast {
+                min_factor,
+                max_factor,
+            })
+        } else {
+            Err(Error::Config(format!(
+                "invalid contrast factors {}..{}",
+                min_factor, max_factor
+            )))
+        }
+    }
+}
+
+impl Augmentation for Contrast {
+    fn augment(
+        &self,
+        image: &mut DMatrix<Fxx>,
+        _label: Option<&mut DMatrix<Fxx>>,
+        rng: &mut dyn RngCore,
+    ) {
+        let factor = if self.min_factor < self.max_factor {
+            rng.gen_range(self.min_factor, self.max_factor)
+        } else {
+            self.min_factor
+        };
+        let mean = image.mean();
+        image.apply(|x| mean + factor * (x - mean));
+    }
+}
+
+///
+/// Add independent zero-mean Gaussian noise to every pixel.
+///
+pub struct GaussianNoise {
+    std: Fxx,
+}
+
+impl GaussianNoise {
+    pub fn new(std: Fxx) -> Self {
+        GaussianNoise { std }
+    }
+}
+
+impl Augmentation for GaussianNoise {
+    fn augment(
+        &self,
+        image: &mut DMatrix<Fxx>,
+        _label: Option<&mut DMatrix<Fxx>>,
+        rng: &mut dyn RngCore,
+    ) {
+        if self.std > 0.0 {
+            let normal = Normal::new(0.0, self.std as f64);
+            image.apply(|x| x + normal.sample(rng) as Fxx);
+        }
+    }
+}
+
+///
+/// Random erasing, Zhong et al. 2017: with a given probability, overwrite a
+/// random rectangle, covering a random fraction of the image, with a value.
+///
+pub struct RandomErasing {
+    p: Fxx,
+    min_area: Fxx,
+    max_area: Fxx,
+    value: Fxx,
+}
+
+impl RandomErasing {
+    ///
+    /// Fails unless 0 < min_area <= max_area <= 1.
+    ///
+    /// # Arguments
+    /// * `p` - the probability of erasing.
+    /// * `min_area` - the least fraction of the image area to erase.
+    /// * `max_area` - the greatest fraction of the image area to erase.
+    /// * `value` - the value written to erased pixels.
+    ///
+    pub fn new(p: Fxx, min_area: Fxx, max_area: Fxx, value: Fxx) -> Result<Self, Error> {
+        if !(0.0 < min_area && min_area <= max_area && max_area <= 1.0) {
+            return Err(Error::Config(format!(
+                "invalid erasing areas {}..{}",
+                min_area, max_area
+            )));
+        }
+        Ok(RandomErasing {
+            p,
+            min_area,
+            max_area,
+            value,
+        })
+    }
+}
+
+impl Augmentation for RandomErasing {
+    fn augment(
+        &self,
+        image: &mut DMatrix<Fxx>,
+        _label: Option<&mut DMatrix<Fxx>>,
+        rng: &mut dyn RngCore,
+    ) {
+        if rng.gen::<Fxx>() >= self.p {
+            return;
+        }
+        let area = if self.min_area < self.max_area {
+            rng.gen_range(self.min_area, self.max_area)
+        } else {
+            self.min_area
+        };
+        // split the area between the sides with a random aspect ratio, the
+        // whole image having only one
+        let aspect = if area < 1.0 {
+            rng.gen_range(area, 1.0 / area).max(area)
+        } else {
+            1.0
+        };
+        let (h, w) = (image.nrows(), image.ncols());
+        let eh = ((area * aspect).sqrt().min(1.0) * h as Fxx).round() as usize;
+        let ew = ((area / aspect).sqrt().min(1.0) * w as Fxx).round() as usize;
+        if eh == 0 || ew == 0 {
+            return;
+        }
+        let r0 = Uniform::from(0..=h - eh).sample(rng);
+        let c0 = Uniform::from(0..=w - ew).sample(rng);
+        image.slice_mut((r0, c0), (eh, ew)).fill(self.value);
+    }
+}
+
+///
+/// Augmentations applied in sequence.
+///
+#[derive(Default)]
+pub struct Pipeline {
+    steps: Vec<Box<dyn Augmentation>>,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Pipeline { steps: Vec::new() }
+    }
+
+    pub fn push(&mut self, step: Box<dyn Augmentation>) {
+        self.steps.push(step);
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    ///
+    /// Return an augmented copy of the image.
+    ///
+    pub fn apply<R, C>(
+        &self,
+        image: &MatrixMN<Fxx, R, C>,
+        rng: &mut dyn RngCore,
+    ) -> MatrixMN<Fxx, R, C>
+    where
+        R: DimName,
+        C: DimName,
+        DefaultAllocator: Allocator<Fxx, R, C>,
+    {
+        let mut dimage = DMatrix::from_column_slice(R::dim(), C::dim(), image.as_slice());
+        self.augment(&mut dimage, None, rng);
+        MatrixMN::<Fxx, R, C>::from_column_slice(dimage.as_slice())
+    }
+
+    ///
+    /// Return augmented copies of the image and its label position map.
+    ///
+    pub fn apply_labeled<R, C, LR, LC>(
+        &self,
+        image: &MatrixMN<Fxx, R, C>,
+        label: &MatrixMN<Fxx, LR, LC>,
+        rng: &mut dyn RngCore,
+    ) -> (MatrixMN<Fxx, R, C>, MatrixMN<Fxx, LR, LC>)
+    where
+        R: DimName,
+        C: DimName,
+        LR: DimName,
+        LC: DimName,
+        DefaultAllocator: Allocator<Fxx, R, C> + Allocator<Fxx, LR, LC>,
+    {
+        let mut dimage = DMatrix::from_column_slice(R::dim(), C::dim(), image.as_slice());
+        let mut dlabel = DMatrix::from_column_slice(LR::dim(), LC::dim(), label.as_slice());
+        self.augment(&mut dimage, Some(&mut dlabel), rng);
+        (
+            MatrixMN::<Fxx, R, C>::from_column_slice(dimage.as_slice()),
+            MatrixMN::<Fxx, LR, LC>::from_column_slice(dlabel.as_slice()),
+        )
+    }
+}
+
+impl Augmentation for Pipeline {
+    fn augment(
+        &self,
+        image: &mut DMatrix<Fxx>,
+        mut label: Option<&mut DMatrix<Fxx>>,
+        rng: &mut dyn RngCore,
+    ) {
+        for step in self.steps.iter() {
+            step.augment(image, label.as_deref_mut(), rng);
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./augment_test.rs"]
+mod augment_test;
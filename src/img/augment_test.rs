@@ -0,0 +1,196 @@
+use super::*;
+
+use assert_approx_eq::assert_approx_eq;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use na::{Matrix2, Matrix2x3, Matrix4, U1, U2};
+
+/// A 4x4 image counting up column-major, each pixel distinct.
+fn counting() -> DMatrix<Fxx> {
+    DMatrix::from_fn(4, 4, |r, c| (4 * c + r) as Fxx)
+}
+
+#[test]
+fn flips_image_and_label() {
+    let mut rng = StdRng::seed_from_u64(1);
+    let mut image = counting();
+    let mut label = DMatrix::from_column_slice(2, 2, &[1.0, 0.0, 0.0, 0.0]);
+    Flip::horizontal(1.0).augment(&mut image, Some(&mut label), &mut rng);
+    assert_eq!(image.column(0), counting().column(3));
+    assert_eq!(image.column(3), counting().column(0));
+    assert_eq!(label.as_slice(), &[0.0, 0.0, 1.0, 0.0]);
+
+    let mut image = counting();
+    Flip::vertical(1.0).augment(&mut image, None, &mut rng);
+    assert_eq!(image.row(0), counting().row(3));
+
+    let mut image = counting();
+    Flip::vertical(0.0).augment(&mut image, None, &mut rng);
+    assert_eq!(image, counting());
+}
+
+#[test]
+fn rotates_quarter_turns() {
+    let mut rng = StdRng::seed_from_u64(2);
+    let image = counting();
+    let mut seen = Vec::new();
+    for _ in 0..32 {
+        let mut rotated = image.clone();
+        Rotate90::new().augment(&mut rotated, None, &mut rng);
+        // every quarter turn permutes the pixels
+        let mut sorted = rotated.as_slice().to_vec();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(sorted, image.as_slice());
+        if !seen.contains(&rotated) {
+            seen.push(rotated);
+        }
+    }
+    assert_eq!(seen.len(), 4);
+    // a counter-clockwise turn moves the top-right corner to the top-left
+    assert!(seen
+        .iter()
+        .any(|m| m[(0, 0)] == image[(0, 3)] && m[(3, 0)] == image[(0, 0)]));
+}
+
+#[test]
+fn half_turns_rectangles() {
+    let mut rng = StdRng::seed_from_u64(3);
+    let image = DMatrix::from_fn(2, 3, |r, c| (3 * r + c) as Fxx);
+    let turned = DMatrix::from_fn(2, 3, |r, c| image[(1 - r, 2 - c)]);
+    let mut seen = (false, false);
+    for _ in 0..16 {
+        let mut rotated = image.clone();
+        let mut label = DMatrix::<Fxx>::zeros(4, 4);
+        Rotate90::new().augment(&mut rotated, Some(&mut label), &mut rng);
+        if rotated == image {
+            seen.0 = true;
+        } else {
+            assert_eq!(rotated, turned);
+            seen.1 = true;
+        }
+    }
+    assert_eq!(seen, (true, true));
+}
+
+#[test]
+fn crops_consistently_with_label() {
+    let mut rng = StdRng::seed_from_u64(4);
+    // the label marks the bright top-left quadrant of the image at half
+    // resolution
+    let mut image = DMatrix::from_fn(8, 8, |r, c| if r < 4 && c < 4 { 1.0 } else { 0.0 });
+    let mut label = DMatrix::from_fn(4, 4, |r, c| if r < 2 && c < 2 { 1.0 } else { 0.0 });
+    RandomCrop::new(0.5, 0.5)
+        .unwrap()
+        .augment(&mut image, Some(&mut label), &mut rng);
+    for r in 0..4 {
+        for c in 0..4 {
+            assert_eq!(label[(r, c)], image[(2 * r, 2 * c)], "({}, {})", r, c);
+        }
+    }
+
+    let mut image = counting();
+    RandomCrop::new(1.0, 1.0)
+        .unwrap()
+        .augment(&mut image, None, &mut rng);
+    assert_eq!(image, counting());
+}
+
+#[test]
+fn rotates_small_angles() {
+    let mut rng = StdRng::seed_from_u64(5);
+    let mut image = DMatrix::from_element(9, 9, 1.0);
+    RandomRotation::new(0.0).augment(&mut image, None, &mut rng);
+    assert_eq!(image.sum(), 81.0);
+
+    RandomRotation::new(30.0).augment(&mut image, None, &mut rng);
+    // the center stays, corners may be uncovered
+    assert_eq!(image[(4, 4)], 1.0);
+    assert!(image.sum() <= 81.0);
+}
+
+#[test]
+fn jitters_brightness_and_contrast() {
+    let mut rng = StdRng::seed_from_u64(6);
+    let mut image = counting();
+    Brightness::new(0.5).augment(&mut image, None, &mut rng);
+    let delta = image[(0, 0)];
+    assert!(delta.abs() <= 0.5);
+    assert_eq!(image, counting().add_scalar(delta));
+
+    let mut image = counting();
+    Contrast::new(2.0, 2.0)
+        .unwrap()
+        .augment(&mut image, None, &mut rng);
+    assert_approx_eq!(image.mean(), counting().mean());
+    assert_approx_eq!(image[(1, 0)] - image[(0, 0)], 2.0);
+}
+
+#[test]
+fn adds_gaussian_noise() {
+    let mut rng = StdRng::seed_from_u64(7);
+    let mut image = DMatrix::zeros(100, 100);
+    GaussianNoise::new(0.1).augment(&mut image, None, &mut rng);
+    let var = image.norm_squared() / 10000.0;
+    assert_approx_eq!(var.sqrt(), 0.1, 0.01);
+    assert_approx_eq!(image.mean(), 0.0, 0.01);
+}
+
+#[test]
+fn erases_rectangles() {
+    let mut rng = StdRng::seed_from_u64(8);
+    let mut image = DMatrix::from_element(10, 10, 1.0);
+    RandomErasing::new(1.0, 0.25, 0.25, 0.0)
+        .unwrap()
+        .augment(&mut image, None, &mut rng);
+    let erased = image.iter().filter(|&&x| x == 0.0).count();
+    assert!(erased > 10 && erased <= 50, "erased {}", erased);
+
+    let mut image = DMatrix::from_element(10, 10, 1.0);
+    RandomErasing::new(0.0, 0.25, 0.25, 0.0)
+        .unwrap()
+        .augment(&mut image, None, &mut rng);
+    assert_eq!(image.sum(), 100.0);
+
+    RandomErasing::new(1.0, 1.0, 1.0, 0.0)
+        .unwrap()
+        .augment(&mut image, None, &mut rng);
+    assert_eq!(image.sum(), 0.0);
+}
+
+#[test]
+fn rejects_invalid_settings() {
+    assert!(matches!(RandomCrop::new(0.0, 0.5), Err(Error::Config(_))));
+    assert!(matches!(RandomCrop::new(0.5, 1.5), Err(Error::Config(_))));
+    assert!(matches!(Contrast::new(2.0, 1.0), Err(Error::Config(_))));
+    assert!(matches!(
+        RandomErasing::new(1.0, 0.5, 0.25, 0.0),
+        Err(Error::Config(_))
+    ));
+}
+
+#[test]
+fn pipelines_fixed_size_matrices() {
+    let mut rng = StdRng::seed_from_u64(9);
+    let mut pipeline = Pipeline::new();
+    assert!(pipeline.is_empty());
+    pipeline.push(Box::new(Flip::horizontal(1.0)));
+    pipeline.push(Box::new(Flip::vertical(1.0)));
+    pipeline.push(Box::new(Brightness::new(0.0)));
+    assert_eq!(pipeline.len(), 3);
+
+    let image = Matrix2x3::new(1.0, 2.0, 3.0, 4.0, 5.0, 6.0);
+    assert_eq!(
+        pipeline.apply(&image, &mut rng),
+        Matrix2x3::new(6.0, 5.0, 4.0, 3.0, 2.0, 1.0)
+    );
+
+    let image = Matrix4::from_column_slice(counting().as_slice());
+    let label = Matrix2::new(1.0, 0.0, 0.0, 0.0);
+    let (flipped, label) = pipeline.apply_labeled(&image, &label, &mut rng);
+    assert_eq!(flipped[(0, 0)], 15.0);
+    assert_eq!(label, Matrix2::new(0.0, 0.0, 0.0, 1.0));
+
+    let column = MatrixMN::<Fxx, U2, U1>::new(1.0, 2.0);
+    assert_eq!(pipeline.apply(&column, &mut rng).as_slice(), &[2.0, 1.0]);
+}
@@ -278,6 +278,18 @@ where
         let err = yh - y;
         self.backpropagate(x, &err)
     }
+
+    fn set_training(&mut self, training: bool) {
+        self.pooler.set_training(training);
+    }
+
+    fn get_params(&self, params: &mut Vec<Fxx>) {
+        self.pooler.get_params(params);
+    }
+
+    fn set_params<'p>(&mut self, params: &'p [Fxx]) -> &'p [Fxx] {
+        self.pooler.set_params(params)
+    }
 }
 
 #[cfg(test)]
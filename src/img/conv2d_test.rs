@@ -2,7 +2,7 @@ use super::*;
 
 use na::{U1, U12, U2, U3, U4, U6};
 
-use crate::{LinearModel, Model, SGDTrainer, UpdateParams};
+use crate::{Batch, LinearModel, Model, SGDTrainer, UpdateParams};
 
 const LEARNING_PARAMS: UpdateParams = UpdateParams {
     step_size: 1e-6,
@@ -20,6 +20,17 @@ fn create_conv_model() {
     cnn.update(&x, &y);
 }
 
+#[test]
+fn predicts_batch() {
+    let mut train0 = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut pooler = LinearModel::<U6, U1>::new_random(&mut train0);
+    let cnn = Conv2d::<U2, U3, U1, U1, U3, U4, U12, U4>::new(&mut pooler);
+    let x = Batch::<U12>::new_random(2);
+    let y = cnn.predict_batch(&x);
+    assert_eq!(y.column(0), cnn.predict(&x.column(0).into_owned()));
+    assert_eq!(y.column(1), cnn.predict(&x.column(1).into_owned()));
+}
+
 #[test]
 fn extracts_input_patch() {
     let mut train0 = SGDTrainer::new(&LEARNING_PARAMS);
@@ -0,0 +1,49 @@
+use super::*;
+
+const INK: Rgb<u8> = Rgb([255, 0, 0]);
+
+fn inked(image: &RgbImage) -> Vec<(u32, u32)> {
+    image
+        .enumerate_pixels()
+        .filter(|(_, _, p)| **p == INK)
+        .map(|(x, y, _)| (x, y))
+        .collect()
+}
+
+#[test]
+fn measures_text() {
+    assert_eq!(text_width("", 2), 0);
+    assert_eq!(text_width("a", 1), 5);
+    assert_eq!(text_width("ab", 2), 22);
+}
+
+#[test]
+fn draws_glyphs() {
+    let mut image = RgbImage::new(12, 8);
+    draw_text(&mut image, 0, 0, "-_", 1, INK);
+    // a dash across the middle row, an underscore across the bottom
+    let expected: Vec<(u32, u32)> = (0..5)
+        .map(|x| (x, 3))
+        .chain((6..11).map(|x| (x, 6)))
+        .collect();
+    let mut pixels = inked(&image);
+    pixels.sort_by_key(|&(x, y)| (y, x));
+    assert_eq!(pixels, expected);
+    // lower case is drawn upper case
+    let mut upper = RgbImage::new(6, 7);
+    let mut lower = RgbImage::new(6, 7);
+    draw_text(&mut upper, 0, 0, "E", 1, INK);
+    draw_text(&mut lower, 0, 0, "e", 1, INK);
+    assert_eq!(upper, lower);
+}
+
+#[test]
+fn scales_and_clips() {
+    let mut image = RgbImage::new(10, 10);
+    draw_text(&mut image, -4, 6, ".", 2, INK);
+    // the dot, in the bottom rows of the glyph, falls below the image
+    assert!(inked(&image).is_empty());
+    // and is scaled from 2x2 to 4x4 pixels
+    draw_text(&mut image, 0, -8, ".", 2, INK);
+    assert_eq!(inked(&image).len(), 16);
+}
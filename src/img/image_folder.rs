@@ -0,0 +1,278 @@
+extern crate nalgebra as na;
+
+use image::imageops::FilterType;
+use image::io::Reader;
+use image::{ColorType, DynamicImage, GenericImageView};
+
+use na::allocator::Allocator;
+use na::{DefaultAllocator, DimName, VectorN};
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use log::debug;
+
+use crate::data::{Dataset, Example};
+use crate::error::Error;
+use crate::model::Fxx;
+
+const IMG_EXTENSIONS: [&str; 7] = ["bmp", "gif", "jpeg", "jpg", "png", "tif", "tiff"];
+const BYTE_MAX: Fxx = 255.0;
+const DEEP_MAX: Fxx = 65535.0;
+
+///
+/// How images are fit to the dataset's dimensions.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Resize {
+    /// Stretch to the dimensions, changing the aspect ratio as necessary, as
+    /// `read_luma` does.
+    Exact,
+    /// Scale to fit within the dimensions, preserving the aspect ratio, and
+    /// center on a black background.
+    Fit,
+    /// Scale to cover the dimensions, preserving the aspect ratio, and crop
+    /// the center.
+    Fill,
+}
+
+///
+/// The channels decoded from each image.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorMode {
+    /// A single greyscale channel, flattened in the column-major order of
+    /// `read_luma` matrices.
+    Luma,
+    /// Red, green and blue channels interleaved by pixel in row-major order,
+    /// index `3 * (r * cols + c) + k`, the input layout of `Conv2d` with
+    /// three input channels.
+    Rgb,
+}
+
+impl ColorMode {
+    pub fn channels(&self) -> usize {
+        match self {
+            ColorMode::Luma => 1,
+            ColorMode::Rgb => 3,
+        }
+    }
+}
+
+///
+/// Images labeled by the name of their subdirectory, e.g. root/cat/1.png and
+/// root/dog/2.png, as examples of pixel values scaled to [0, 1] and one-hot
+/// class vectors.  Classes are ordered by name.
+///
+/// Every image is decoded and cached when the folder is opened, so
+/// unreadable files are reported by `open` rather than by `get`.
+///
+pub struct ImageFolderDataset<M, N>
+where
+    M: DimName,
+    N: DimName,
+    DefaultAllocator: Allocator<Fxx, M> + Allocator<Fxx, N>,
+{
+    classes: Vec<String>,
+    entries: Vec<(PathBuf, usize)>,
+    rows: usize,
+    cols: usize,
+    color: ColorMode,
+    resize: Resize,
+    images: Vec<VectorN<Fxx, M>>,
+    _labels: std::marker::PhantomData<VectorN<Fxx, N>>,
+}
+
+pub(crate) fn sorted_entries(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut paths = dir
+        .read_dir()?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<io::Result<Vec<PathBuf>>>()?;
+    paths.retain(|p| {
+        p.file_name()
+            .is_some_and(|name| !name.to_string_lossy().starts_with('.'))
+    });
+    paths.sort();
+    Ok(paths)
+}
+
+pub(crate) fn is_img(path: &Path) -> bool {
+    path.is_file()
+        && path.extension().is_some_and(|ext| {
+            IMG_EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str())
+        })
+}
+
+impl<M, N> ImageFolderDataset<M, N>
+where
+    M: DimName,
+    N: DimName,
+    DefaultAllocator: Allocator<Fxx, M> + Allocator<Fxx, N>,
+{
+    ///
+    /// Scan the class subdirectories of root for images and decode them,
+    /// returning the first failure.
+    ///
+    /// # Arguments
+    /// * `root` - the directory holding one subdirectory per class.
+    /// * `rows` - the height of the decoded images.
+    /// * `cols` - the width of the decoded images.
+    /// * `color` - the channels to decode, M = channels * rows * cols.
+    /// * `resize` - how to fit images to rows x cols.
+    ///
+    pub fn open<P: AsRef<Path>>(
+        root: P,
+        rows: usize,
+        cols: usize,
+        color: ColorMode,
+        resize: Resize,
+    ) -> Result<Self, Error> {
+        if color.channels() * rows * cols != M::dim() {
+            return Err(Error::Shape {
+                name: format!("{} channel {}x{} image", color.channels(), rows, cols),
+                expected: M::dim(),
+                found: color.channels() * rows * cols,
+            });
+        }
+
+        let mut classes = Vec::new();
+        let mut entries = Vec::new();
+        for dir in sorted_entries(root.as_ref())?
+            .into_iter()
+            .filter(|p| p.is_dir())
+        {
+            let name = match dir.file_name() {
+                Some(name) => name.to_string_lossy().to_string(),
+                None => continue,
+            };
+            let label = classes.len();
+            let images: Vec<PathBuf> = sorted_entries(&dir)?
+                .into_iter()
+                .filter(|p| is_img(p))
+                .collect();
+            debug!("found {} images in {:#?}", images.len(), dir);
+            entries.extend(images.into_iter().map(|p| (p, label)));
+            classes.push(name);
+        }
+        if classes.len() != N::dim() {
+            return Err(Error::Config(format!(
+                "found {} classes in {}, expected {}",
+                classes.len(),
+                root.as_ref().to_string_lossy(),
+                N::dim()
+            )));
+        }
+
+        let mut dataset = ImageFolderDataset {
+            classes,
+            entries,
+            rows,
+            cols,
+            color,
+            resize,
+            images: Vec::new(),
+            _labels: std::marker::PhantomData,
+        };
+        dataset.images = dataset
+            .entries
+            .iter()
+            .map(|(path, _)| dataset.decode(path))
+            .collect::<Result<_, Error>>()?;
+        Ok(dataset)
+    }
+
+    /// The class names, by label index.
+    pub fn get_classes(&self) -> &[String] {
+        &self.classes
+    }
+
+    /// The image files and their label indices.
+    pub fn get_entries(&self) -> &[(PathBuf, usize)] {
+        &self.entries
+    }
+
+    fn decode(&self, path: &Path) -> Result<VectorN<Fxx, M>, Error> {
+        debug!("decoding {}", path.to_string_lossy());
+        let image = Reader::open(path)?.with_guessed_format()?.decode()?;
+        let (w, h) = (self.cols as u32, self.rows as u32);
+        let image = match self.resize {
+            Resize::Exact => image.resize_exact(w, h, FilterType::Nearest),
+            Resize::Fit => image.resize(w, h, FilterType::Nearest),
+            Resize::Fill => image.resize_to_fill(w, h, FilterType::Nearest),
+        };
+        Ok(self.to_vector(&image))
+    }
+
+    ///
+    /// Flatten the image into the layout of the color mode, centering images
+    /// smaller than rows x cols.
+    ///
+    fn to_vector(&self, image: &DynamicImage) -> VectorN<Fxx, M> {
+        let (w, h) = image.dimensions();
+        let (w, h) = (w as usize, h as usize);
+        let row_off = (self.rows - h) / 2;
+        let col_off = (self.cols - w) / 2;
+        // channel values interleaved by pixel in row-major order
+        let values = channel_values(image, self.color);
+        let channels = self.color.channels();
+        let value = |r: usize, c: usize, k: usize| {
+            if r >= row_off && r < row_off + h && c >= col_off && c < col_off + w {
+                values[channels * ((r - row_off) * w + c - col_off) + k]
+            } else {
+                0.0
+            }
+        };
+        match self.color {
+            ColorMode::Luma => {
+                VectorN::<Fxx, M>::from_fn(|i, _| value(i % self.rows, i / self.rows, 0))
+            }
+            ColorMode::Rgb => VectorN::<Fxx, M>::from_fn(|i, _| {
+                let p = i / 3;
+                value(p / self.cols, p % self.cols, i % 3)
+            }),
+        }
+    }
+}
+
+///
+/// The pixel channel values of the image scaled to [0, 1] by the maximum of
+/// its bit depth.
+///
+pub(crate) fn channel_values(image: &DynamicImage, color: ColorMode) -> Vec<Fxx> {
+    let deep = matches!(
+        image.color(),
+        ColorType::L16 | ColorType::La16 | ColorType::Rgb16 | ColorType::Rgba16
+    );
+    match (color, deep) {
+        (ColorMode::Luma, true) => scale(&image.to_luma16().into_raw(), DEEP_MAX),
+        (ColorMode::Luma, false) => scale(&image.to_luma8().into_raw(), BYTE_MAX),
+        (ColorMode::Rgb, true) => scale(&image.to_rgb16().into_raw(), DEEP_MAX),
+        (ColorMode::Rgb, false) => scale(&image.to_rgb8().into_raw(), BYTE_MAX),
+    }
+}
+
+fn scale<T: Copy + Into<Fxx>>(values: &[T], max: Fxx) -> Vec<Fxx> {
+    values.iter().map(|&v| v.into() / max).collect()
+}
+
+impl<M, N> Dataset<M, N> for ImageFolderDataset<M, N>
+where
+    M: DimName,
+    N: DimName,
+    DefaultAllocator: Allocator<Fxx, M> + Allocator<Fxx, N>,
+{
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn get(&self, i: usize) -> Example<M, N> {
+        let x = self.images[i].clone();
+        let mut y = VectorN::<Fxx, N>::zeros();
+        y[self.entries[i].1] = 1.0;
+        (x, y)
+    }
+}
+
+#[cfg(test)]
+#[path = "./image_folder_test.rs"]
+mod image_folder_test;
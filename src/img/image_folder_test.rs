@@ -0,0 +1,131 @@
+use super::*;
+
+use std::fs;
+
+use assert_approx_eq::assert_approx_eq;
+use image::{ImageBuffer, Luma, Rgb};
+use na::{U12, U18, U2, U3, U4, U6};
+
+///
+/// A scratch directory holding 2x3 images of two classes, removed on drop.
+///
+struct Fixture {
+    root: PathBuf,
+}
+
+impl Fixture {
+    fn new(name: &str) -> Self {
+        let root = std::env::temp_dir().join(format!("lair-{}-{}", name, std::process::id()));
+        fs::create_dir_all(root.join("bright")).unwrap();
+        fs::create_dir_all(root.join("dark")).unwrap();
+        // pixel values increase along rows
+        ImageBuffer::from_fn(3, 2, |x, y| Luma([(y * 3 + x) as u8 * 51]))
+            .save(root.join("bright").join("a.png"))
+            .unwrap();
+        ImageBuffer::from_fn(3, 2, |x, _| Rgb([255u8, 0, if x == 0 { 255 } else { 0 }]))
+            .save(root.join("bright").join("b.png"))
+            .unwrap();
+        ImageBuffer::from_fn(1, 2, |_, _| Luma([255u8]))
+            .save(root.join("dark").join("tall.png"))
+            .unwrap();
+        fs::write(root.join("dark").join("notes.txt"), "not an image").unwrap();
+        Fixture { root }
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        fs::remove_dir_all(&self.root).ok();
+    }
+}
+
+#[test]
+fn labels_by_directory() {
+    let fixture = Fixture::new("labels");
+    let dataset =
+        ImageFolderDataset::<U6, U2>::open(&fixture.root, 2, 3, ColorMode::Luma, Resize::Exact)
+            .unwrap();
+    assert_eq!(
+        dataset.get_classes(),
+        &["bright".to_string(), "dark".to_string()]
+    );
+    assert_eq!(dataset.len(), 3);
+    assert_eq!(dataset.get(0).1.as_slice(), &[1.0, 0.0]);
+    assert_eq!(dataset.get(2).1.as_slice(), &[0.0, 1.0]);
+}
+
+#[test]
+fn reads_luma_column_major() {
+    let fixture = Fixture::new("luma");
+    let dataset =
+        ImageFolderDataset::<U6, U2>::open(&fixture.root, 2, 3, ColorMode::Luma, Resize::Exact)
+            .unwrap();
+    let (x, _) = dataset.get(0);
+    let expected = [0.0, 0.6, 0.2, 0.8, 0.4, 1.0];
+    for i in 0..6 {
+        assert_approx_eq!(x[i], expected[i], 1e-3);
+    }
+    assert_eq!(dataset.get(0).0, x);
+}
+
+#[test]
+fn reads_interleaved_rgb() {
+    let fixture = Fixture::new("rgb");
+    let dataset =
+        ImageFolderDataset::<U18, U2>::open(&fixture.root, 2, 3, ColorMode::Rgb, Resize::Exact)
+            .unwrap();
+    let (x, _) = dataset.get(1);
+    // the first pixel of each row is magenta, the rest red
+    assert_eq!(&x.as_slice()[..6], &[1.0, 0.0, 1.0, 1.0, 0.0, 0.0]);
+    assert_eq!(&x.as_slice()[9..12], &[1.0, 0.0, 1.0]);
+}
+
+#[test]
+fn fits_preserving_aspect() {
+    let fixture = Fixture::new("fit");
+    let dataset =
+        ImageFolderDataset::<U12, U2>::open(&fixture.root, 2, 6, ColorMode::Luma, Resize::Fit)
+            .unwrap();
+    // the 1x2 image is centered, columns 2 and 3 of 6
+    let (x, _) = dataset.get(2);
+    for c in 0..6 {
+        let expected = if c == 2 { 1.0 } else { 0.0 };
+        assert_eq!(x[2 * c], expected, "column {}", c);
+    }
+
+    let dataset =
+        ImageFolderDataset::<U4, U2>::open(&fixture.root, 2, 2, ColorMode::Luma, Resize::Fill)
+            .unwrap();
+    assert_eq!(dataset.get(2).0.as_slice(), &[1.0; 4]);
+}
+
+#[test]
+fn rejects_mismatched_dimensions() {
+    let fixture = Fixture::new("dims");
+    assert!(ImageFolderDataset::<U6, U3>::open(
+        &fixture.root,
+        2,
+        3,
+        ColorMode::Luma,
+        Resize::Exact
+    )
+    .is_err());
+    assert!(
+        ImageFolderDataset::<U6, U2>::open(&fixture.root, 2, 3, ColorMode::Rgb, Resize::Exact)
+            .is_err()
+    );
+}
+
+#[test]
+fn rejects_undecodable_images() {
+    let fixture = Fixture::new("corrupt");
+    fs::write(fixture.root.join("dark").join("broken.png"), "not a png").unwrap();
+    assert!(ImageFolderDataset::<U6, U2>::open(
+        &fixture.root,
+        2,
+        3,
+        ColorMode::Luma,
+        Resize::Exact
+    )
+    .is_err());
+}
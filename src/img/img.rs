@@ -2,7 +2,7 @@ extern crate nalgebra as na;
 
 use image::imageops::FilterType;
 use image::io::Reader;
-use image::{ImageBuffer, ImageError, Luma};
+use image::{DynamicImage, ImageBuffer, Rgb};
 
 use na::allocator::Allocator;
 use nalgebra::storage::{Storage, StorageMut};
@@ -13,6 +13,8 @@ use std::convert::TryInto;
 use std::io::{BufRead, Seek};
 use log::debug;
 
+use crate::error::Error;
+use crate::img::render::{save, BitDepth, Mapping, Renderer};
 use crate::model::Fxx;
 
 const IMG_MAX: Fxx = 65535.0;
@@ -103,25 +105,31 @@ where
     range_matrix::<M, U1, _>(v)
 }
 
+///
+/// An image dimension as the u32 the image crate expects.
+///
+pub(crate) fn image_dim(n: usize) -> Result<u32, Error> {
+    n.try_into()
+        .map_err(|_| Error::Config(format!("image dimension {} too large", n)))
+}
+
 ///
 /// Represent an image as a matrix, scaling it and converting it to greyscale.
 ///
-pub fn read_luma<F, R, C>(reader: &mut F) -> Result<MatrixMN<Fxx, R, C>, ImageError>
+pub fn read_luma<F, R, C>(reader: &mut F) -> Result<MatrixMN<Fxx, R, C>, Error>
 where
     F: BufRead + Seek,
     R: DimName,
     C: DimName,
     DefaultAllocator: Allocator<Fxx, R, C>,
 {
-    let ir = Reader::new(reader)
-        .with_guessed_format()
-        .expect("Failed to read image");
+    let ir = Reader::new(reader).with_guessed_format()?;
     let image = ir.decode()?;
     let scaled = image
         .grayscale()
         .resize_exact( // exact to change aspect ratio as necessary
-            C::dim().try_into().unwrap(),
-            R::dim().try_into().unwrap(),
+            image_dim(C::dim())?,
+            image_dim(R::dim())?,
             FilterType::Nearest,
         )
         .to_luma16();
@@ -132,21 +140,19 @@ where
     }))
 }
 
-pub fn read_lumad<F>(reader: &mut F, dim: (usize, usize)) -> Result<DMatrix<Fxx>, ImageError>
+pub fn read_lumad<F>(reader: &mut F, dim: (usize, usize)) -> Result<DMatrix<Fxx>, Error>
 where
     F: BufRead + Seek,
 {
     let num_rows = dim.0;
     let num_cols = dim.1;
-    let ir = Reader::new(reader)
-        .with_guessed_format()
-        .expect("Failed to read image");
+    let ir = Reader::new(reader).with_guessed_format()?;
     let image = ir.decode()?;
     let scaled = image
         .grayscale()
         .resize(
-            num_cols.try_into().unwrap(),
-            num_rows.try_into().unwrap(),
+            image_dim(num_cols)?,
+            image_dim(num_rows)?,
             FilterType::Nearest,
         )
         .to_luma16();
@@ -156,6 +162,64 @@ where
     }))
 }
 
+///
+/// Decode an image resized exactly to rows x cols, checking that its channels
+/// fill a vector of the given dimension.
+///
+fn read_resized<F>(
+    reader: &mut F,
+    channels: usize,
+    rows: usize,
+    cols: usize,
+    dim: usize,
+) -> Result<DynamicImage, Error>
+where
+    F: BufRead + Seek,
+{
+    if channels * rows * cols != dim {
+        return Err(Error::Shape {
+            name: format!("{} channel {}x{} image", channels, rows, cols),
+            expected: dim,
+            found: channels * rows * cols,
+        });
+    }
+    let image = Reader::new(reader).with_guessed_format()?.decode()?;
+    Ok(image.resize_exact(image_dim(cols)?, image_dim(rows)?, FilterType::Nearest))
+}
+
+///
+/// Represent an image as red, green and blue channels interleaved by pixel
+/// in row-major order, index `3 * (r * cols + c) + k`, the input layout of a
+/// `Conv2d` with three input channels.  As with `read_luma`, the image is
+/// stretched to rows x cols and values span [0..65535].
+///
+pub fn read_rgb<F, M>(reader: &mut F, rows: usize, cols: usize) -> Result<VectorN<Fxx, M>, Error>
+where
+    F: BufRead + Seek,
+    M: DimName,
+    DefaultAllocator: Allocator<Fxx, M>,
+{
+    let image = read_resized(reader, 3, rows, cols, M::dim())?;
+    Ok(VectorN::<Fxx, M>::from_iterator(
+        image.to_rgb16().into_raw().into_iter().map(|v| v as Fxx),
+    ))
+}
+
+///
+/// As `read_rgb`, with a fourth alpha channel, index `4 * (r * cols + c) + k`.
+///
+pub fn read_rgba<F, M>(reader: &mut F, rows: usize, cols: usize) -> Result<VectorN<Fxx, M>, Error>
+where
+    F: BufRead + Seek,
+    M: DimName,
+    DefaultAllocator: Allocator<Fxx, M>,
+{
+    let image = read_resized(reader, 4, rows, cols, M::dim())?;
+    Ok(VectorN::<Fxx, M>::from_iterator(
+        image.to_rgba16().into_raw().into_iter().map(|v| v as Fxx),
+    ))
+}
+
 ///
 /// Scale the image values of the input to span [0..1].
 ///
@@ -170,33 +234,74 @@ where
     m * mat
 }
 
-pub fn write_luma_matrix<M, N, S>(data: &Matrix<Fxx, M, N, S>, path: &str) -> Result<(), ImageError>
+///
+/// Write a matrix as a 16-bit greyscale image, clamping its values to the
+/// [0..65535] of `read_luma`.  See `Renderer` for other mappings and colors.
+///
+pub fn write_luma_matrix<M, N, S>(data: &Matrix<Fxx, M, N, S>, path: &str) -> Result<(), Error>
 where
     M: DimName,
     N: DimName,
     S: Storage<Fxx, M, N>,
 {
-    ImageBuffer::from_fn(N::dim() as u32, M::dim() as u32, |x, y| {
-        Luma([data[(y as usize, x as usize)] as u16]) // XXX u16
-    })
-    .save(path)
+    luma_renderer().write_matrix(data, path)
 }
 
+///
+/// Write a vector in row-major order as a 16-bit greyscale image, clamping
+/// its values as `write_luma_matrix` does.
+///
 pub fn write_luma_vector<M, S>(
     data: &Vector<Fxx, M, S>,
     height: usize,
     width: usize,
     path: &str,
-) -> Result<(), ImageError>
+) -> Result<(), Error>
+where
+    M: DimName,
+    S: Storage<Fxx, M>,
+{
+    luma_renderer().write_vector(data, height, width, path)
+}
+
+fn luma_renderer() -> Renderer {
+    let mut renderer = Renderer::new(Mapping::Clamp);
+    renderer.set_depth(BitDepth::Sixteen);
+    renderer
+}
+
+///
+/// Write interleaved red, green and blue channels in the layout of
+/// `read_rgb`, e.g. the output of a `Conv2d` with three output channels, as
+/// a 16-bit image.  As with `Mapping::Clamp`, values are clamped to the
+/// [0..65535] of `read_rgb` and rounded, and NaN is written as 0, so values
+/// in range read back to the nearest integer.
+///
+pub fn write_rgb<M, S>(
+    data: &Vector<Fxx, M, S>,
+    height: usize,
+    width: usize,
+    path: &str,
+) -> Result<(), Error>
 where
     M: DimName,
     S: Storage<Fxx, M>,
 {
-    debug_assert!(M::dim() == height * width);
-    ImageBuffer::from_fn(width as u32, height as u32, |x, y| {
-        Luma([data[(x + width as u32 * y) as usize] as u16]) // XXX u16....
-    })
-    .save(path)
+    if M::dim() != 3 * height * width {
+        return Err(Error::Shape {
+            name: format!("3 channel {}x{} image", height, width),
+            expected: 3 * height * width,
+            found: M::dim(),
+        });
+    }
+    let values: Vec<Fxx> = data.iter().cloned().collect();
+    let levels = Renderer::new(Mapping::Clamp).normalize(&values);
+    let image = ImageBuffer::from_fn(image_dim(width)?, image_dim(height)?, |x, y| {
+        let i = 3 * (y as usize * width + x as usize);
+        let deep = |t: Fxx| (t * IMG_MAX).round() as u16;
+        Rgb([deep(levels[i]), deep(levels[i + 1]), deep(levels[i + 2])])
+    });
+    save(&DynamicImage::ImageRgb16(image), path)
 }
 
 #[cfg(test)]
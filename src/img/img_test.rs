@@ -1,7 +1,7 @@
 use super::*;
 
 use assert_approx_eq::assert_approx_eq;
-use na::{DimProd, U1, U2, U3, U4};
+use na::{DimProd, U1, U12, U18, U2, U24, U3, U4};
 use std::fs::File;
 use std::io::BufReader;
 
@@ -52,3 +52,70 @@ fn read_luma_image() {
         }
     }
 }
+
+/// A 2x3 image whose pixel at (x, y) is (x, y, x + y) * 16, opaque.
+fn rgba_png() -> Vec<u8> {
+    let image = ImageBuffer::from_fn(3, 2, |x, y| {
+        image::Rgba([(x * 16) as u8, (y * 16) as u8, ((x + y) * 16) as u8, 255])
+    });
+    let mut png = Vec::new();
+    DynamicImage::ImageRgba8(image)
+        .write_to(&mut png, image::ImageOutputFormat::Png)
+        .unwrap();
+    png
+}
+
+#[test]
+fn read_rgb_interleaves_channels() {
+    let x = read_rgb::<_, U18>(&mut std::io::Cursor::new(rgba_png()), 2, 3).unwrap();
+    let (pi, ic) = (3, 3);
+    for i in 0..2 {
+        for j in 0..3 {
+            // 8-bit values are read as their 16-bit equivalents
+            let pixel = [
+                j as Fxx * 4096.0,
+                i as Fxx * 4096.0,
+                (i + j) as Fxx * 4096.0,
+            ];
+            for k in 0..pi {
+                assert_eq!(x[pi * (i * ic + j) + k], pixel[k]);
+            }
+        }
+    }
+
+    let x = read_rgba::<_, U24>(&mut std::io::Cursor::new(rgba_png()), 2, 3).unwrap();
+    assert_eq!(x[4 * (3 + 2) + 2], 3.0 * 4096.0);
+    assert_eq!(x[4 * (3 + 2) + 3], 65280.0);
+}
+
+#[test]
+fn read_rgb_checks_dimensions() {
+    match read_rgb::<_, U12>(&mut std::io::Cursor::new(rgba_png()), 2, 3) {
+        Err(Error::Shape {
+            expected, found, ..
+        }) => assert_eq!((expected, found), (12, 18)),
+        result => panic!("expected shape mismatch, got {:?}", result.map(|_| ())),
+    }
+}
+
+#[test]
+fn write_rgb_round_trips() {
+    let path = std::env::temp_dir().join(format!("lair_rgb_{}.png", std::process::id()));
+    let path = path.to_str().unwrap();
+    let x = VectorN::<Fxx, U18>::from_fn(|i, _| i as Fxx * 3000.0 + 7.0);
+    write_rgb(&x, 2, 3, path).unwrap();
+    let written = read_rgb::<_, U18>(&mut BufReader::new(File::open(path).unwrap()), 2, 3);
+    assert_eq!(written.unwrap(), x);
+
+    // out of range values are clamped and fractions rounded
+    let mut y = x;
+    y[0] = -5.0;
+    y[1] = 70000.0;
+    y[2] = Fxx::NAN;
+    y[3] = 1.4;
+    write_rgb(&y, 2, 3, path).unwrap();
+    let written = read_rgb::<_, U18>(&mut BufReader::new(File::open(path).unwrap()), 2, 3);
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(&written.unwrap().as_slice()[..4], &[0.0, 65535.0, 0.0, 1.0]);
+    assert!(write_rgb(&x, 3, 3, path).is_err());
+}
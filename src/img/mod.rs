@@ -1,5 +1,19 @@
+pub mod augment;
 pub mod conv2d;
+pub mod font;
+pub mod image_folder;
 pub mod img;
+pub mod montage;
+pub mod render;
+pub mod synth;
+pub use augment::{
+    Augmentation, Brightness, Contrast, Flip, GaussianNoise, Pipeline, RandomCrop, RandomErasing,
+    RandomRotation, Rotate90,
+};
+pub use image_folder::{ColorMode, ImageFolderDataset, Resize};
+pub use montage::Montage;
+pub use render::{BitDepth, Colormap, Mapping, Renderer};
+pub use synth::{BoundingBox, Labeling, Overlap, Placement, PlacementGenerator};
 pub use img::{
     overlay_matrix, 
     overlay_matrix_to_vector, 
@@ -7,7 +21,10 @@ pub use img::{
     range_vector, 
     read_luma,
     read_lumad,
+    read_rgb,
+    read_rgba,
     unit_range_from_img,
     write_luma_matrix,
     write_luma_vector,
+    write_rgb,
 };
@@ -0,0 +1,236 @@
+extern crate nalgebra as na;
+
+use image::{Rgb, RgbImage};
+
+use na::storage::Storage;
+use na::{Dim, Matrix, Vector};
+
+use crate::error::Error;
+use crate::img::font::{draw_text, text_width, GLYPH_HEIGHT};
+use crate::img::img::image_dim;
+use crate::img::render::Renderer;
+use crate::model::Fxx;
+
+const BACKGROUND: Rgb<u8> = Rgb([255, 255, 255]);
+const LABEL: Rgb<u8> = Rgb([0, 0, 0]);
+/// Pixels around and between tiles.
+const PADDING: u32 = 4;
+/// Pixels between a label and its tile.
+const LABEL_GAP: u32 = 2;
+
+struct Tile {
+    label: String,
+    values: Vec<Fxx>,
+    height: usize,
+    width: usize,
+}
+
+///
+/// A grid of labeled tiles, e.g. the filters of a `Conv2d` pooler or the
+/// channels of its output, written as one image.  Each tile is normalized
+/// on its own by the renderer's mapping, so `Mapping::AutoRange` or
+/// `Mapping::Symmetric` stretch every tile to the full range of the
+/// colormap, while `Mapping::Fixed` puts all tiles on one scale.
+///
+pub struct Montage {
+    renderer: Renderer,
+    scale: u32,
+    columns: Option<usize>,
+    tiles: Vec<Tile>,
+}
+
+impl Montage {
+    pub fn new(renderer: Renderer) -> Self {
+        Montage {
+            renderer,
+            scale: 1,
+            columns: None,
+            tiles: Vec::new(),
+        }
+    }
+
+    ///
+    /// Draw each value as a square of scale pixels, by default 1.
+    ///
+    pub fn set_scale(&mut self, scale: u32) {
+        self.scale = scale.max(1);
+    }
+
+    ///
+    /// Arrange the tiles in rows of the given number of columns, by default
+    /// the nearest to a square grid.
+    ///
+    pub fn set_columns(&mut self, columns: usize) {
+        self.columns = Some(columns.max(1));
+    }
+
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    ///
+    /// Add a tile of values in row-major order, index `r * width + c`.
+    ///
+    pub fn add(
+        &mut self,
+        label: &str,
+        values: &[Fxx],
+        height: usize,
+        width: usize,
+    ) -> Result<(), Error> {
+        if values.len() != height * width {
+            return Err(Error::Shape {
+                name: format!("{} tile", label),
+                expected: height * width,
+                found: values.len(),
+            });
+        }
+        self.tiles.push(Tile {
+            label: label.to_string(),
+            values: values.to_vec(),
+            height,
+            width,
+        });
+        Ok(())
+    }
+
+    ///
+    /// Add the filters of a `Conv2d` pooler, e.g. `LinearModel::get_ws`, one
+    /// tile per filter and input channel.  Each row of ws is a filter whose
+    /// weight for channel k at (r, c) is at `channels * (r * width + c) + k`.
+    ///
+    pub fn add_filters<R, C, S>(
+        &mut self,
+        ws: &Matrix<Fxx, R, C, S>,
+        channels: usize,
+        height: usize,
+        width: usize,
+    ) -> Result<(), Error>
+    where
+        R: Dim,
+        C: Dim,
+        S: Storage<Fxx, R, C>,
+    {
+        if ws.ncols() != channels * height * width {
+            return Err(Error::Shape {
+                name: "filter".to_string(),
+                expected: channels * height * width,
+                found: ws.ncols(),
+            });
+        }
+        for i in 0..ws.nrows() {
+            let filter: Vec<Fxx> = ws.row(i).iter().cloned().collect();
+            for k in 0..channels {
+                let label = if channels == 1 {
+                    format!("F{}", i)
+                } else {
+                    format!("F{} C{}", i, k)
+                };
+                self.add(&label, &deinterleave(&filter, channels, k), height, width)?;
+            }
+        }
+        Ok(())
+    }
+
+    ///
+    /// Add the channels of an activation map, e.g. a `Conv2d` output, one tile
+    /// per channel.  The value of channel k at (r, c) is at
+    /// `channels * (r * width + c) + k`.
+    ///
+    pub fn add_channels<M, S>(
+        &mut self,
+        output: &Vector<Fxx, M, S>,
+        channels: usize,
+        height: usize,
+        width: usize,
+    ) -> Result<(), Error>
+    where
+        M: Dim,
+        S: Storage<Fxx, M>,
+    {
+        if output.len() != channels * height * width {
+            return Err(Error::Shape {
+                name: "activation map".to_string(),
+                expected: channels * height * width,
+                found: output.len(),
+            });
+        }
+        let values: Vec<Fxx> = output.iter().cloned().collect();
+        for k in 0..channels {
+            let label = format!("C{}", k);
+            self.add(&label, &deinterleave(&values, channels, k), height, width)?;
+        }
+        Ok(())
+    }
+
+    ///
+    /// Draw the tiles left to right and top to bottom, each under its label.
+    ///
+    pub fn to_image(&self) -> Result<RgbImage, Error> {
+        let n = self.tiles.len();
+        let columns = self
+            .columns
+            .unwrap_or_else(|| (n as Fxx).sqrt().ceil() as usize)
+            .clamp(1, n.max(1));
+        let rows = n.div_ceil(columns);
+        let scale = self.scale as usize;
+        let label_height = (GLYPH_HEIGHT + LABEL_GAP) as usize;
+        let cell_width = self
+            .tiles
+            .iter()
+            .map(|t| (t.width * scale).max(text_width(&t.label, 1) as usize))
+            .max()
+            .unwrap_or(0);
+        let cell_height = label_height
+            + self
+                .tiles
+                .iter()
+                .map(|t| t.height * scale)
+                .max()
+                .unwrap_or(0);
+        let padding = PADDING as usize;
+        let width = image_dim(padding + columns * (cell_width + padding))?;
+        let height = image_dim(padding + rows * (cell_height + padding))?;
+
+        let mut image = RgbImage::from_pixel(width, height, BACKGROUND);
+        for (i, tile) in self.tiles.iter().enumerate() {
+            let left = padding + (i % columns) * (cell_width + padding);
+            let top = padding + (i / columns) * (cell_height + padding);
+            draw_text(&mut image, left as i64, top as i64, &tile.label, 1, LABEL);
+            let colors = self.renderer.colors(&tile.values);
+            for y in 0..tile.height * scale {
+                for x in 0..tile.width * scale {
+                    let c = colors[(y / scale) * tile.width + x / scale];
+                    let pixel = Rgb([eight(c[0]), eight(c[1]), eight(c[2])]);
+                    image.put_pixel((left + x) as u32, (top + label_height + y) as u32, pixel);
+                }
+            }
+        }
+        Ok(image)
+    }
+
+    ///
+    /// Write the montage as an 8-bit RGB image, in the format given by the
+    /// path's extension, e.g. PNG.
+    ///
+    pub fn write(&self, path: &str) -> Result<(), Error> {
+        self.to_image()?.save(path)?;
+        Ok(())
+    }
+}
+
+fn eight(c: Fxx) -> u8 {
+    (c * 255.0).round() as u8
+}
+
+fn deinterleave(values: &[Fxx], channels: usize, k: usize) -> Vec<Fxx> {
+    values.iter().skip(k).step_by(channels).cloned().collect()
+}
+
+#[cfg(test)]
+#[path = "./montage_test.rs"]
+mod montage_test;
@@ -0,0 +1,89 @@
+use super::*;
+
+use crate::img::render::{Colormap, Mapping};
+use na::{DVector, MatrixMN, U2, U8};
+
+fn tile_pixel(image: &RgbImage, x: u32, y: u32) -> Rgb<u8> {
+    *image.get_pixel(PADDING + x, PADDING + GLYPH_HEIGHT + LABEL_GAP + y)
+}
+
+#[test]
+fn add_filters_splits_channels() {
+    // two filters of two channels over a 2x2 patch
+    let ws = MatrixMN::<Fxx, U2, U8>::from_fn(|i, j| (10 * i + j) as Fxx);
+    let mut montage = Montage::new(Renderer::new(Mapping::AutoRange));
+    montage.add_filters(&ws, 2, 2, 2).unwrap();
+    let labels: Vec<&str> = montage.tiles.iter().map(|t| t.label.as_str()).collect();
+    assert_eq!(labels, vec!["F0 C0", "F0 C1", "F1 C0", "F1 C1"]);
+    assert_eq!(montage.tiles[1].values, vec![1.0, 3.0, 5.0, 7.0]);
+    assert_eq!(montage.tiles[2].values, vec![10.0, 12.0, 14.0, 16.0]);
+
+    let mut montage = Montage::new(Renderer::new(Mapping::AutoRange));
+    montage.add_filters(&ws, 1, 2, 4).unwrap();
+    assert_eq!(montage.tiles[1].label, "F1");
+    match montage.add_filters(&ws, 2, 2, 1) {
+        Err(Error::Shape {
+            expected, found, ..
+        }) => assert_eq!((expected, found), (4, 8)),
+        result => panic!("expected shape mismatch, got {:?}", result),
+    }
+}
+
+#[test]
+fn add_channels_splits_channels() {
+    let output = DVector::from_vec((0..12).map(|v| v as Fxx).collect());
+    let mut montage = Montage::new(Renderer::new(Mapping::AutoRange));
+    montage.add_channels(&output, 3, 2, 2).unwrap();
+    assert_eq!(montage.len(), 3);
+    assert_eq!(montage.tiles[2].label, "C2");
+    assert_eq!(montage.tiles[2].values, vec![2.0, 5.0, 8.0, 11.0]);
+    assert!(montage.add_channels(&output, 5, 2, 2).is_err());
+}
+
+#[test]
+fn to_image_lays_out_grid() {
+    let mut montage = Montage::new(Renderer::new(Mapping::AutoRange));
+    for i in 0..5 {
+        montage.add(&format!("T{}", i), &[0.0; 6], 2, 3).unwrap();
+    }
+    montage.set_scale(10);
+    let image = montage.to_image().unwrap();
+    // a 3x2 grid of 30x20 tiles under labels
+    let (cell_width, cell_height) = (30, GLYPH_HEIGHT + LABEL_GAP + 20);
+    assert_eq!(
+        image.dimensions(),
+        (
+            PADDING + 3 * (cell_width + PADDING),
+            PADDING + 2 * (cell_height + PADDING)
+        )
+    );
+
+    montage.set_columns(5);
+    let image = montage.to_image().unwrap();
+    assert_eq!(
+        image.dimensions(),
+        (
+            PADDING + 5 * (cell_width + PADDING),
+            PADDING + cell_height + PADDING
+        )
+    );
+    assert!(Montage::new(Renderer::new(Mapping::AutoRange))
+        .to_image()
+        .is_ok());
+}
+
+#[test]
+fn to_image_normalizes_each_tile() {
+    let mut renderer = Renderer::new(Mapping::AutoRange);
+    renderer.set_colormap(Colormap::Gray);
+    let mut montage = Montage::new(renderer);
+    montage.add("SMALL", &[0.0, 1.0], 1, 2).unwrap();
+    montage.add("LARGE", &[-100.0, 100.0], 1, 2).unwrap();
+    montage.set_columns(1);
+    let image = montage.to_image().unwrap();
+    assert_eq!(tile_pixel(&image, 0, 0), Rgb([0, 0, 0]));
+    assert_eq!(tile_pixel(&image, 1, 0), Rgb([255, 255, 255]));
+    let below = GLYPH_HEIGHT + LABEL_GAP + 1 + PADDING;
+    assert_eq!(tile_pixel(&image, 0, below), Rgb([0, 0, 0]));
+    assert_eq!(tile_pixel(&image, 1, below), Rgb([255, 255, 255]));
+}
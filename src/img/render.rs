@@ -0,0 +1,298 @@
+extern crate nalgebra as na;
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use image::codecs::png::PngEncoder;
+use image::{ColorType, DynamicImage, GenericImageView, ImageBuffer, Luma, Rgb};
+
+use na::storage::Storage;
+use na::{Dim, Matrix, Vector};
+
+use crate::error::Error;
+use crate::img::img::image_dim;
+use crate::model::Fxx;
+
+/// The greatest value of 16-bit images, e.g. those of `read_luma`.
+const DEEP_MAX: Fxx = 65535.0;
+
+/// Viridis, from dark purple through blue and green to yellow, sampled at
+/// equal intervals.
+const VIRIDIS: [[u8; 3]; 9] = [
+    [68, 1, 84],
+    [71, 44, 122],
+    [59, 81, 139],
+    [44, 113, 142],
+    [33, 144, 141],
+    [39, 173, 129],
+    [92, 200, 99],
+    [170, 220, 50],
+    [253, 231, 37],
+];
+
+/// Blue through light grey to red, sampled at equal intervals.
+const DIVERGING: [[u8; 3]; 5] = [
+    [59, 76, 192],
+    [141, 176, 254],
+    [221, 221, 221],
+    [244, 154, 123],
+    [180, 4, 38],
+];
+
+///
+/// How values map to intensities in [0, 1].  Values outside the range mapped
+/// are clamped, and NaN and infinite values are drawn as 0.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mapping {
+    /// Clamp to [0..65535], the range of `read_luma` images.
+    Clamp,
+    /// Stretch the least value to 0 and the greatest to 1, as `range_matrix`
+    /// does.
+    AutoRange,
+    /// Clamp to [0, 1], e.g. `unit_range_from_img` images or probabilities.
+    UnitRange,
+    /// Stretch min to 0 and max to 1, e.g. to compare images on one scale.
+    Fixed { min: Fxx, max: Fxx },
+    /// Stretch the greatest magnitude to 1, or its negation to 0, mapping
+    /// zero to 0.5, e.g. signed weights with the diverging colormap.
+    Symmetric,
+}
+
+///
+/// The bits per channel of written images.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BitDepth {
+    Eight,
+    Sixteen,
+}
+
+///
+/// How intensities are colored.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Colormap {
+    /// Greyscale, written as a single channel.
+    Gray,
+    /// Perceptually uniform dark purple to yellow.
+    Viridis,
+    /// Blue below 0.5 and red above, e.g. negative and positive weights.
+    Diverging,
+}
+
+impl Colormap {
+    ///
+    /// The red, green and blue components in [0, 1] of an intensity.
+    ///
+    pub fn color(&self, t: Fxx) -> [Fxx; 3] {
+        let t = if t.is_finite() {
+            t.clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        match self {
+            Colormap::Gray => [t; 3],
+            Colormap::Viridis => interpolate(&VIRIDIS, t),
+            Colormap::Diverging => interpolate(&DIVERGING, t),
+        }
+    }
+}
+
+fn interpolate(colors: &[[u8; 3]], t: Fxx) -> [Fxx; 3] {
+    let x = t * (colors.len() - 1) as Fxx;
+    let i = (x.floor() as usize).min(colors.len() - 2);
+    let f = x - i as Fxx;
+    let mut color = [0.0; 3];
+    for (k, c) in color.iter_mut().enumerate() {
+        let (a, b) = (colors[i][k] as Fxx, colors[i + 1][k] as Fxx);
+        *c = (a + f * (b - a)) / 255.0;
+    }
+    color
+}
+
+///
+/// Write values, e.g. weights or activations, as images, mapping them to
+/// intensities and then to grey levels or colors.  By default, 8-bit grey.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Renderer {
+    mapping: Mapping,
+    depth: BitDepth,
+    colormap: Colormap,
+}
+
+impl Renderer {
+    pub fn new(mapping: Mapping) -> Self {
+        Renderer {
+            mapping,
+            depth: BitDepth::Eight,
+            colormap: Colormap::Gray,
+        }
+    }
+
+    pub fn set_depth(&mut self, depth: BitDepth) {
+        self.depth = depth;
+    }
+
+    pub fn set_colormap(&mut self, colormap: Colormap) {
+        self.colormap = colormap;
+    }
+
+    ///
+    /// Map the values to intensities in [0, 1].
+    ///
+    pub fn normalize(&self, values: &[Fxx]) -> Vec<Fxx> {
+        let finite = || values.iter().cloned().filter(|v| v.is_finite());
+        let (min, max) = match self.mapping {
+            Mapping::Clamp => (0.0, DEEP_MAX),
+            Mapping::UnitRange => (0.0, 1.0),
+            Mapping::Fixed { min, max } => (min, max),
+            Mapping::AutoRange => (
+                finite().fold(Fxx::INFINITY, Fxx::min),
+                finite().fold(Fxx::NEG_INFINITY, Fxx::max),
+            ),
+            Mapping::Symmetric => {
+                let m = finite().fold(0.0, |m: Fxx, v| m.max(v.abs()));
+                (-m, m)
+            }
+        };
+        values
+            .iter()
+            .map(|&v| {
+                if !v.is_finite() {
+                    0.0
+                } else if max > min {
+                    ((v - min) / (max - min)).clamp(0.0, 1.0)
+                } else {
+                    0.5
+                }
+            })
+            .collect()
+    }
+
+    ///
+    /// Map the values to the red, green and blue components of their colors.
+    ///
+    pub fn colors(&self, values: &[Fxx]) -> Vec<[Fxx; 3]> {
+        self.normalize(values)
+            .into_iter()
+            .map(|t| self.colormap.color(t))
+            .collect()
+    }
+
+    ///
+    /// Render values in row-major order, index `r * width + c`, as an image.
+    ///
+    pub fn to_image(
+        &self,
+        values: &[Fxx],
+        height: usize,
+        width: usize,
+    ) -> Result<DynamicImage, Error> {
+        if values.len() != height * width {
+            return Err(Error::Shape {
+                name: format!("{}x{} image", height, width),
+                expected: height * width,
+                found: values.len(),
+            });
+        }
+        let (w, h) = (image_dim(width)?, image_dim(height)?);
+        let colors = self.colors(values);
+        let at = |x: u32, y: u32| colors[y as usize * width + x as usize];
+        let eight = |c: Fxx| (c * 255.0).round() as u8;
+        let sixteen = |c: Fxx| (c * DEEP_MAX).round() as u16;
+        Ok(match (self.colormap, self.depth) {
+            (Colormap::Gray, BitDepth::Eight) => {
+                DynamicImage::ImageLuma8(ImageBuffer::from_fn(w, h, |x, y| {
+                    Luma([eight(at(x, y)[0])])
+                }))
+            }
+            (Colormap::Gray, BitDepth::Sixteen) => {
+                DynamicImage::ImageLuma16(ImageBuffer::from_fn(w, h, |x, y| {
+                    Luma([sixteen(at(x, y)[0])])
+                }))
+            }
+            (_, BitDepth::Eight) => DynamicImage::ImageRgb8(ImageBuffer::from_fn(w, h, |x, y| {
+                let c = at(x, y);
+                Rgb([eight(c[0]), eight(c[1]), eight(c[2])])
+            })),
+            (_, BitDepth::Sixteen) => {
+                DynamicImage::ImageRgb16(ImageBuffer::from_fn(w, h, |x, y| {
+                    let c = at(x, y);
+                    Rgb([sixteen(c[0]), sixteen(c[1]), sixteen(c[2])])
+                }))
+            }
+        })
+    }
+
+    ///
+    /// Write a matrix as an image of its rows and columns, in the format
+    /// given by the path's extension, e.g. PNG.
+    ///
+    pub fn write_matrix<R, C, S>(
+        &self,
+        data: &Matrix<Fxx, R, C, S>,
+        path: &str,
+    ) -> Result<(), Error>
+    where
+        R: Dim,
+        C: Dim,
+        S: Storage<Fxx, R, C>,
+    {
+        let (rows, cols) = data.shape();
+        let values: Vec<Fxx> = (0..rows * cols)
+            .map(|i| data[(i / cols, i % cols)])
+            .collect();
+        save(&self.to_image(&values, data.nrows(), data.ncols())?, path)
+    }
+
+    ///
+    /// Write a vector in row-major order, index `r * width + c`, as an image.
+    ///
+    pub fn write_vector<M, S>(
+        &self,
+        data: &Vector<Fxx, M, S>,
+        height: usize,
+        width: usize,
+        path: &str,
+    ) -> Result<(), Error>
+    where
+        M: Dim,
+        S: Storage<Fxx, M>,
+    {
+        let values: Vec<Fxx> = data.iter().cloned().collect();
+        save(&self.to_image(&values, height, width)?, path)
+    }
+}
+
+///
+/// Save an image in the format given by the path's extension.  16-bit PNGs
+/// are encoded here, since the image crate's encoder doesn't write their
+/// samples in the big-endian order PNG requires.
+///
+pub(crate) fn save(image: &DynamicImage, path: &str) -> Result<(), Error> {
+    let png = Path::new(path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("png"));
+    let (color, samples) = match image {
+        DynamicImage::ImageLuma16(buffer) if png => (ColorType::L16, buffer.as_raw()),
+        DynamicImage::ImageRgb16(buffer) if png => (ColorType::Rgb16, buffer.as_raw()),
+        _ => {
+            image.save(path)?;
+            return Ok(());
+        }
+    };
+    let mut bytes = Vec::with_capacity(2 * samples.len());
+    for sample in samples {
+        bytes.extend_from_slice(&sample.to_be_bytes());
+    }
+    let writer = BufWriter::new(File::create(path)?);
+    PngEncoder::new(writer).encode(&bytes, image.width(), image.height(), color)?;
+    Ok(())
+}
+
+#[cfg(test)]
+#[path = "./render_test.rs"]
+mod render_test;
@@ -0,0 +1,127 @@
+use super::*;
+
+use crate::img::{read_luma, write_luma_matrix};
+use na::{DMatrix, MatrixMN, U2, U3};
+use std::io::BufReader;
+
+fn temp_path(name: &str) -> String {
+    let path = std::env::temp_dir().join(format!("lair_{}_{}.png", name, std::process::id()));
+    path.to_str().unwrap().to_string()
+}
+
+#[test]
+fn normalize_maps_values() {
+    let values = [-1.0, 0.0, 0.5, 2.0, Fxx::NAN];
+    let normalize = |mapping| Renderer::new(mapping).normalize(&values);
+    assert_eq!(normalize(Mapping::UnitRange), vec![0.0, 0.0, 0.5, 1.0, 0.0]);
+    assert_eq!(
+        normalize(Mapping::AutoRange),
+        vec![0.0, 1.0 / 3.0, 0.5, 1.0, 0.0]
+    );
+    assert_eq!(
+        normalize(Mapping::Symmetric),
+        vec![0.25, 0.5, 0.625, 1.0, 0.0]
+    );
+    assert_eq!(
+        normalize(Mapping::Fixed { min: 0.0, max: 1.0 }),
+        normalize(Mapping::UnitRange)
+    );
+    assert_eq!(
+        Renderer::new(Mapping::Clamp).normalize(&[-5.0, 65535.0, 1e9]),
+        vec![0.0, 1.0, 1.0]
+    );
+}
+
+#[test]
+fn normalize_constant_is_mid_grey() {
+    let renderer = Renderer::new(Mapping::AutoRange);
+    assert_eq!(renderer.normalize(&[3.0, 3.0]), vec![0.5, 0.5]);
+    let renderer = Renderer::new(Mapping::Symmetric);
+    assert_eq!(renderer.normalize(&[0.0]), vec![0.5]);
+}
+
+#[test]
+fn colormap_ends() {
+    let scaled = |c: [Fxx; 3]| [c[0] * 255.0, c[1] * 255.0, c[2] * 255.0];
+    assert_eq!(scaled(Colormap::Viridis.color(0.0)), [68.0, 1.0, 84.0]);
+    assert_eq!(scaled(Colormap::Viridis.color(1.0)), [253.0, 231.0, 37.0]);
+    assert_eq!(
+        scaled(Colormap::Diverging.color(0.5)),
+        [221.0, 221.0, 221.0]
+    );
+    assert_eq!(Colormap::Gray.color(2.0), [1.0; 3]);
+}
+
+#[test]
+fn to_image_formats() {
+    let values = [0.0, 1.0];
+    let mut renderer = Renderer::new(Mapping::UnitRange);
+    assert_eq!(
+        renderer
+            .to_image(&values, 1, 2)
+            .unwrap()
+            .to_luma8()
+            .into_raw(),
+        vec![0, 255]
+    );
+
+    renderer.set_colormap(Colormap::Diverging);
+    let image = renderer.to_image(&values, 1, 2).unwrap();
+    assert_eq!(image.to_rgb8().into_raw(), vec![59, 76, 192, 180, 4, 38]);
+
+    renderer.set_depth(BitDepth::Sixteen);
+    let image = renderer.to_image(&values, 2, 1).unwrap();
+    assert_eq!(image.to_rgb16().into_raw()[3..], [46260, 1028, 9766]);
+
+    match renderer.to_image(&values, 2, 2) {
+        Err(Error::Shape {
+            expected, found, ..
+        }) => assert_eq!((expected, found), (4, 2)),
+        result => panic!("expected shape mismatch, got {:?}", result.map(|_| ())),
+    }
+}
+
+#[test]
+fn write_sixteen_bit_round_trips() {
+    let path = temp_path("render16");
+    let mat = MatrixMN::<Fxx, U2, U3>::new(0.0, 1.0, 256.0, 4096.0, 65534.0, 65535.0);
+    write_luma_matrix(&mat, &path).unwrap();
+    let read = read_luma::<_, U2, U3>(&mut BufReader::new(File::open(&path).unwrap()));
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(read.unwrap(), mat);
+}
+
+#[test]
+fn write_luma_clamps() {
+    let path = temp_path("clamp");
+    let mat = MatrixMN::<Fxx, U2, U2>::new(-1.0, 70000.0, 1e12, Fxx::NAN);
+    write_luma_matrix(&mat, &path).unwrap();
+    let read = read_luma::<_, U2, U2>(&mut BufReader::new(File::open(&path).unwrap()));
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(
+        read.unwrap(),
+        MatrixMN::<Fxx, U2, U2>::new(0.0, 65535.0, 65535.0, 0.0)
+    );
+}
+
+#[test]
+fn write_vector_is_row_major() {
+    let path = temp_path("vector");
+    let v = na::DVector::from_vec(vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+    Renderer::new(Mapping::AutoRange)
+        .write_vector(&v, 2, 3, &path)
+        .unwrap();
+    let image = image::open(&path).unwrap().to_luma8();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(image.dimensions(), (3, 2));
+    assert_eq!(image.get_pixel(2, 0)[0], 102);
+    assert_eq!(image.get_pixel(0, 1)[0], 153);
+
+    let mat = DMatrix::from_row_slice(2, 3, v.as_slice());
+    let renderer = Renderer::new(Mapping::AutoRange);
+    let image = renderer.to_image(v.as_slice(), 2, 3).unwrap();
+    renderer.write_matrix(&mat, &path).unwrap();
+    let written = image::open(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(written.to_luma8(), image.to_luma8());
+}
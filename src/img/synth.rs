@@ -0,0 +1,344 @@
+extern crate nalgebra as na;
+
+use image::io::Reader;
+use image::GenericImageView;
+
+use na::allocator::Allocator;
+use na::{DMatrix, DefaultAllocator, DimName, VectorN};
+
+use rand::distributions::{Distribution, Normal};
+use rand::{Rng, RngCore};
+
+use std::marker::PhantomData;
+use std::path::Path;
+
+use log::debug;
+
+use crate::data::{Example, InMemoryDataset};
+use crate::error::Error;
+use crate::img::image_folder::{channel_values, is_img, sorted_entries};
+use crate::img::{overlay_matrix, ColorMode};
+use crate::model::Fxx;
+
+/// The number of label components describing each bounding box.
+pub const BOX_COMPONENTS: usize = 5;
+
+///
+/// How placed targets may overlap one another.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Overlap {
+    /// Place targets independently, later targets covering earlier ones.
+    Allow,
+    /// Redraw the position of a target overlapping an earlier one, up to the
+    /// given number of attempts, then leave it out.
+    Avoid(usize),
+}
+
+///
+/// How the placements of targets are encoded as model outputs.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Labeling {
+    /// A rows x cols grid over the image, flattened column-major, each cell
+    /// 1 if a target intersects it, otherwise 0.
+    Grid(usize, usize),
+    /// Up to the given number of boxes, each `BOX_COMPONENTS` values: 1 for
+    /// a present target, then its top, left, height and width as fractions
+    /// of the image dimensions.  Missing boxes are zeros.
+    Boxes(usize),
+}
+
+impl Labeling {
+    /// The number of label components.
+    pub fn dim(&self) -> usize {
+        match self {
+            Labeling::Grid(rows, cols) => rows * cols,
+            Labeling::Boxes(max_targets) => BOX_COMPONENTS * max_targets,
+        }
+    }
+}
+
+///
+/// The pixel extent of a placed target.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingBox {
+    pub row: usize,
+    pub col: usize,
+    pub height: usize,
+    pub width: usize,
+}
+
+impl BoundingBox {
+    pub fn intersects(&self, other: &BoundingBox) -> bool {
+        self.row < other.row + other.height
+            && other.row < self.row + self.height
+            && self.col < other.col + other.width
+            && other.col < self.col + self.width
+    }
+}
+
+///
+/// A composed image and the boxes of the targets placed on it.
+///
+#[derive(Clone, Debug)]
+pub struct Placement {
+    pub image: DMatrix<Fxx>,
+    pub boxes: Vec<BoundingBox>,
+}
+
+///
+/// Read every image in a directory as a greyscale matrix scaled to [0, 1].
+///
+pub fn read_images<P: AsRef<Path>>(dir: P) -> Result<Vec<DMatrix<Fxx>>, Error> {
+    let mut images = Vec::new();
+    for path in sorted_entries(dir.as_ref())?
+        .into_iter()
+        .filter(|p| is_img(p))
+    {
+        debug!("reading {}", path.to_string_lossy());
+        let image = Reader::open(&path)?.with_guessed_format()?.decode()?;
+        let (w, h) = image.dimensions();
+        let values = channel_values(&image, ColorMode::Luma);
+        images.push(DMatrix::from_row_slice(h as usize, w as usize, &values));
+    }
+    Ok(images)
+}
+
+///
+/// Scale the matrix to the given dimensions by nearest-neighbor sampling.
+///
+pub fn resize_nearest(mat: &DMatrix<Fxx>, rows: usize, cols: usize) -> DMatrix<Fxx> {
+    DMatrix::from_fn(rows, cols, |r, c| {
+        mat[(r * mat.nrows() / rows, c * mat.ncols() / cols)]
+    })
+}
+
+///
+/// Generate examples of target images pasted at random positions and sizes
+/// onto background images, labeled with the placements of the targets.
+/// Inputs are the composed rows x cols images, flattened column-major as
+/// `read_luma` matrices, so M = rows * cols, and N is the dimension of the
+/// labeling.
+///
+pub struct PlacementGenerator<M, N>
+where
+    M: DimName,
+    N: DimName,
+{
+    rows: usize,
+    cols: usize,
+    backgrounds: Vec<DMatrix<Fxx>>,
+    targets: Vec<DMatrix<Fxx>>,
+    labeling: Labeling,
+    num_targets: (Fxx, Fxx),
+    target_size: (Fxx, Fxx),
+    overlap: Overlap,
+    _dims: PhantomData<(M, N)>,
+}
+
+impl<M, N> PlacementGenerator<M, N>
+where
+    M: DimName,
+    N: DimName,
+    DefaultAllocator: Allocator<Fxx, M> + Allocator<Fxx, N>,
+{
+    ///
+    /// Place one target, a quarter of the smaller image side, per example,
+    /// allowing overlap.  Fails if the dimensions don't match M and N, or
+    /// there are no backgrounds or targets.
+    ///
+    /// # Arguments
+    /// * `rows` - the height of the generated images.
+    /// * `cols` - the width of the generated images.
+    /// * `backgrounds` - images, stretched to rows x cols, to place targets on.
+    /// * `targets` - images to place.
+    /// * `labeling` - how to encode placements, N = labeling.dim().
+    ///
+    pub fn new(
+        rows: usize,
+        cols: usize,
+        backgrounds: Vec<DMatrix<Fxx>>,
+        targets: Vec<DMatrix<Fxx>>,
+        labeling: Labeling,
+    ) -> Result<Self, Error> {
+        if rows * cols != M::dim() {
+            return Err(Error::Shape {
+                name: format!("{}x{} image", rows, cols),
+                expected: M::dim(),
+                found: rows * cols,
+            });
+        }
+        if labeling.dim() != N::dim() {
+            return Err(Error::Shape {
+                name: format!("{:?} labeling", labeling),
+                expected: N::dim(),
+                found: labeling.dim(),
+            });
+        }
+        if backgrounds.is_empty() || targets.is_empty() {
+            return Err(Error::Config(
+                "placement needs backgrounds and targets".to_string(),
+            ));
+        }
+        let backgrounds = backgrounds
+            .iter()
+            .map(|b| resize_nearest(b, rows, cols))
+            .collect();
+        Ok(PlacementGenerator {
+            rows,
+            cols,
+            backgrounds,
+            targets,
+            labeling,
+            num_targets: (1.0, 0.0),
+            target_size: (rows.min(cols) as Fxx / 4.0, 0.0),
+            overlap: Overlap::Allow,
+            _dims: PhantomData,
+        })
+    }
+
+    ///
+    /// Draw the number of targets per example from a normal distribution,
+    /// rounded and clipped at zero, and at the number of boxes when labeling
+    /// boxes.
+    ///
+    pub fn set_num_targets(&mut self, mean: Fxx, stdev: Fxx) {
+        self.num_targets = (mean, stdev);
+    }
+
+    ///
+    /// Draw the longer side of each target, in pixels, from a normal
+    /// distribution, rounded and clipped to [1, min(rows, cols)].  Targets
+    /// keep their aspect ratios.
+    ///
+    pub fn set_target_size(&mut self, mean: Fxx, stdev: Fxx) {
+        self.target_size = (mean, stdev);
+    }
+
+    pub fn set_overlap(&mut self, overlap: Overlap) {
+        self.overlap = overlap;
+    }
+
+    pub fn get_labeling(&self) -> Labeling {
+        self.labeling
+    }
+
+    fn sample_normal(rng: &mut dyn RngCore, (mean, stdev): (Fxx, Fxx)) -> Fxx {
+        if stdev > 0.0 {
+            Normal::new(mean as f64, stdev as f64).sample(rng) as Fxx
+        } else {
+            mean
+        }
+    }
+
+    ///
+    /// Compose an image with randomly chosen background and targets.
+    ///
+    pub fn generate(&self, rng: &mut dyn RngCore) -> Placement {
+        let mut num_targets = Self::sample_normal(rng, self.num_targets).round().max(0.0) as usize;
+        if let Labeling::Boxes(max_targets) = self.labeling {
+            num_targets = num_targets.min(max_targets);
+        }
+        let mut image = self.backgrounds[rng.gen_range(0, self.backgrounds.len())].clone();
+        let mut boxes: Vec<BoundingBox> = Vec::with_capacity(num_targets);
+
+        for _ in 0..num_targets {
+            let target = &self.targets[rng.gen_range(0, self.targets.len())];
+            let side = Self::sample_normal(rng, self.target_size)
+                .round()
+                .max(1.0)
+                .min(self.rows.min(self.cols) as Fxx);
+            let scale = side / target.nrows().max(target.ncols()) as Fxx;
+            let height = ((target.nrows() as Fxx * scale).round() as usize).max(1);
+            let width = ((target.ncols() as Fxx * scale).round() as usize).max(1);
+
+            let attempts = match self.overlap {
+                Overlap::Allow => 1,
+                Overlap::Avoid(attempts) => attempts,
+            };
+            let placed = (0..attempts)
+                .map(|_| BoundingBox {
+                    row: rng.gen_range(0, self.rows - height + 1),
+                    col: rng.gen_range(0, self.cols - width + 1),
+                    height,
+                    width,
+                })
+                .find(|b| self.overlap == Overlap::Allow || !boxes.iter().any(|o| o.intersects(b)));
+            match placed {
+                Some(b) => {
+                    debug!(
+                        "placing {}x{} target at ({}, {})",
+                        height, width, b.row, b.col
+                    );
+                    overlay_matrix(
+                        &resize_nearest(target, height, width),
+                        b.row,
+                        b.col,
+                        &mut image,
+                    );
+                    boxes.push(b);
+                }
+                None => debug!("no room for {}x{} target", height, width),
+            }
+        }
+        Placement { image, boxes }
+    }
+
+    ///
+    /// Encode the boxes by the labeling.
+    ///
+    pub fn label(&self, boxes: &[BoundingBox]) -> VectorN<Fxx, N> {
+        let mut y = VectorN::<Fxx, N>::zeros();
+        let (rows, cols) = (self.rows as Fxx, self.cols as Fxx);
+        match self.labeling {
+            Labeling::Grid(grid_rows, grid_cols) => {
+                // the cells covering [start, end) of a side of length n
+                let cells = |start: usize, end: usize, n: Fxx, cells: usize| {
+                    let c0 = (cells as Fxx * start as Fxx / n).floor() as usize;
+                    let c1 = (cells as Fxx * end as Fxx / n).ceil() as usize;
+                    c0..c1.min(cells)
+                };
+                for b in boxes {
+                    for c in cells(b.col, b.col + b.width, cols, grid_cols) {
+                        for r in cells(b.row, b.row + b.height, rows, grid_rows) {
+                            y[c * grid_rows + r] = 1.0;
+                        }
+                    }
+                }
+            }
+            Labeling::Boxes(max_targets) => {
+                for (i, b) in boxes.iter().take(max_targets).enumerate() {
+                    let off = BOX_COMPONENTS * i;
+                    y[off] = 1.0;
+                    y[off + 1] = b.row as Fxx / rows;
+                    y[off + 2] = b.col as Fxx / cols;
+                    y[off + 3] = b.height as Fxx / rows;
+                    y[off + 4] = b.width as Fxx / cols;
+                }
+            }
+        }
+        y
+    }
+
+    ///
+    /// Generate a flattened image and its label.
+    ///
+    pub fn example(&self, rng: &mut dyn RngCore) -> Example<M, N> {
+        let placement = self.generate(rng);
+        let x = VectorN::<Fxx, M>::from_column_slice(placement.image.as_slice());
+        (x, self.label(&placement.boxes))
+    }
+
+    ///
+    /// Generate n examples.
+    ///
+    pub fn dataset(&self, n: usize, rng: &mut dyn RngCore) -> InMemoryDataset<M, N> {
+        InMemoryDataset::new((0..n).map(|_| self.example(rng)).collect())
+    }
+}
+
+#[cfg(test)]
+#[path = "./synth_test.rs"]
+mod synth_test;
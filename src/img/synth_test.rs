@@ -0,0 +1,195 @@
+use super::*;
+
+use std::fs;
+
+use image::{ImageBuffer, Luma};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use na::{U10, U16, U4, U64};
+
+use crate::data::Dataset;
+
+/// An 8x8 black background and a 2x2 white target.
+fn generator<N: DimName>(labeling: Labeling) -> PlacementGenerator<U64, N>
+where
+    DefaultAllocator: Allocator<Fxx, N>,
+{
+    PlacementGenerator::<U64, N>::new(
+        8,
+        8,
+        vec![DMatrix::zeros(4, 4)],
+        vec![DMatrix::from_element(2, 2, 1.0)],
+        labeling,
+    )
+    .unwrap()
+}
+
+#[test]
+fn rejects_invalid_generators() {
+    let target = || vec![DMatrix::from_element(2, 2, 1.0)];
+    let create = |rows, backgrounds, targets, labeling| {
+        PlacementGenerator::<U64, U16>::new(rows, 8, backgrounds, targets, labeling)
+    };
+    assert!(matches!(
+        create(
+            4,
+            vec![DMatrix::zeros(4, 4)],
+            target(),
+            Labeling::Grid(4, 4)
+        ),
+        Err(Error::Shape {
+            expected: 64,
+            found: 32,
+            ..
+        })
+    ));
+    assert!(matches!(
+        create(
+            8,
+            vec![DMatrix::zeros(4, 4)],
+            target(),
+            Labeling::Grid(2, 4)
+        ),
+        Err(Error::Shape {
+            expected: 16,
+            found: 8,
+            ..
+        })
+    ));
+    assert!(matches!(
+        create(8, Vec::new(), target(), Labeling::Grid(4, 4)),
+        Err(Error::Config(_))
+    ));
+    assert!(matches!(
+        create(
+            8,
+            vec![DMatrix::zeros(4, 4)],
+            Vec::new(),
+            Labeling::Grid(4, 4)
+        ),
+        Err(Error::Config(_))
+    ));
+}
+
+#[test]
+fn places_requested_targets() {
+    let mut rng = StdRng::seed_from_u64(1);
+    let mut gen = generator::<U16>(Labeling::Grid(4, 4));
+    gen.set_num_targets(3.0, 0.0);
+    gen.set_target_size(2.0, 0.0);
+    for _ in 0..10 {
+        let placement = gen.generate(&mut rng);
+        assert_eq!(placement.boxes.len(), 3);
+        for b in placement.boxes.iter() {
+            assert_eq!((b.height, b.width), (2, 2));
+            assert!(b.row + b.height <= 8 && b.col + b.width <= 8);
+            assert_eq!(placement.image.slice((b.row, b.col), (2, 2)).sum(), 4.0);
+        }
+    }
+}
+
+#[test]
+fn avoids_overlap() {
+    let mut rng = StdRng::seed_from_u64(2);
+    let mut gen = generator::<U16>(Labeling::Grid(4, 4));
+    gen.set_num_targets(4.0, 0.0);
+    gen.set_target_size(4.0, 0.0);
+    gen.set_overlap(Overlap::Avoid(100));
+    for _ in 0..10 {
+        let placement = gen.generate(&mut rng);
+        assert!(!placement.boxes.is_empty());
+        for (i, a) in placement.boxes.iter().enumerate() {
+            for b in placement.boxes[i + 1..].iter() {
+                assert!(!a.intersects(b), "{:?} {:?}", a, b);
+            }
+        }
+        assert_eq!(placement.image.sum(), 16.0 * placement.boxes.len() as Fxx);
+    }
+}
+
+#[test]
+fn labels_grid_cells() {
+    let gen = generator::<U16>(Labeling::Grid(4, 4));
+    let boxes = [BoundingBox {
+        row: 1,
+        col: 4,
+        height: 2,
+        width: 3,
+    }];
+    let y = gen.label(&boxes);
+    // rows 1..3 cover cells 0 and 1, columns 4..7 cells 2 and 3
+    for c in 0..4 {
+        for r in 0..4 {
+            let expected = if r < 2 && c >= 2 { 1.0 } else { 0.0 };
+            assert_eq!(y[c * 4 + r], expected, "({}, {})", r, c);
+        }
+    }
+}
+
+#[test]
+fn labels_boxes() {
+    let mut rng = StdRng::seed_from_u64(3);
+    let mut gen = generator::<U10>(Labeling::Boxes(2));
+    gen.set_num_targets(5.0, 0.0);
+    gen.set_target_size(2.0, 0.0);
+    let placement = gen.generate(&mut rng);
+    // the count is clipped at the number of boxes
+    assert_eq!(placement.boxes.len(), 2);
+
+    let y = gen.label(&placement.boxes[..1]);
+    let b = placement.boxes[0];
+    assert_eq!(
+        y.as_slice(),
+        &[
+            1.0,
+            b.row as Fxx / 8.0,
+            b.col as Fxx / 8.0,
+            0.25,
+            0.25,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0
+        ]
+    );
+}
+
+#[test]
+fn generates_datasets() {
+    let mut rng = StdRng::seed_from_u64(4);
+    let gen = generator::<U4>(Labeling::Grid(2, 2));
+    let dataset = gen.dataset(5, &mut rng);
+    assert_eq!(dataset.len(), 5);
+    let (x, y) = dataset.get(0);
+    assert_eq!(x.sum(), 4.0);
+    assert!(y.sum() >= 1.0);
+}
+
+#[test]
+fn reads_image_directories() {
+    let dir = std::env::temp_dir().join(format!("lair-synth-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    ImageBuffer::from_fn(3, 2, |x, _| Luma([if x == 0 { 255u8 } else { 0 }]))
+        .save(dir.join("a.png"))
+        .unwrap();
+    fs::write(dir.join("notes.txt"), "not an image").unwrap();
+    let images = read_images(&dir);
+    fs::remove_dir_all(&dir).unwrap();
+
+    let images = images.unwrap();
+    assert_eq!(images.len(), 1);
+    assert_eq!((images[0].nrows(), images[0].ncols()), (2, 3));
+    assert_eq!(images[0].column(0).sum(), 2.0);
+    assert_eq!(images[0].sum(), 2.0);
+}
+
+#[test]
+fn resizes_nearest() {
+    let mat = DMatrix::from_row_slice(2, 2, &[1.0, 2.0, 3.0, 4.0]);
+    let big = resize_nearest(&mat, 4, 4);
+    assert_eq!(big[(1, 1)], 1.0);
+    assert_eq!(big[(3, 2)], 4.0);
+    assert_eq!(resize_nearest(&big, 2, 2), mat);
+}
@@ -0,0 +1,179 @@
+extern crate nalgebra as na;
+
+use log::debug;
+
+use na::allocator::Allocator;
+use na::{DefaultAllocator, DimName, Dynamic, VectorN, U1};
+
+use crate::model::{Batch, Fxx, Model};
+use crate::trainer::GradientTrainer;
+
+const EPSILON: Fxx = 1e-5;
+
+///
+/// Layer normalization, Ba, Kiros and Hinton 2016.
+/// Each sample is standardized by the mean and variance of its own
+/// components, then scaled and shifted by learned parameters.  Unlike
+/// `BatchNorm`, the statistics don't depend upon the other samples in a
+/// batch, so training and evaluation behave identically.
+///
+pub struct LayerNorm<'a, N>
+where
+    N: DimName,
+    DefaultAllocator: Allocator<Fxx, N>,
+{
+    trainer: &'a mut dyn GradientTrainer<U1, N>,
+    scale: VectorN<Fxx, N>,
+    shift: VectorN<Fxx, N>,
+}
+
+impl<'a, N> LayerNorm<'a, N>
+where
+    N: DimName,
+    DefaultAllocator: Allocator<Fxx, N>,
+{
+    ///
+    /// Create a layer normalization layer with unit scale and zero shift.
+    ///
+    /// # Arguments
+    /// * `trainer` - updates the scale (as weights) and shift (as bias).
+    ///
+    pub fn new(trainer: &'a mut dyn GradientTrainer<U1, N>) -> Self {
+        LayerNorm {
+            trainer,
+            scale: VectorN::<Fxx, N>::from_element(1.0),
+            shift: VectorN::<Fxx, N>::zeros(),
+        }
+    }
+
+    pub fn get_scale(&self) -> &VectorN<Fxx, N> {
+        &self.scale
+    }
+
+    pub fn get_shift(&self) -> &VectorN<Fxx, N> {
+        &self.shift
+    }
+
+    ///
+    /// The standardized input and its standard deviation.
+    ///
+    fn standardize(&self, x: &VectorN<Fxx, N>) -> (VectorN<Fxx, N>, Fxx) {
+        let n = N::dim() as Fxx;
+        let mean = x.sum() / n;
+        let centered = x.add_scalar(-mean);
+        let std = (centered.norm_squared() / n + EPSILON).sqrt();
+        (centered / std, std)
+    }
+
+    ///
+    /// The error partial derivatives with respect to the input and scale.
+    ///
+    fn gradients(
+        &self,
+        x: &VectorN<Fxx, N>,
+        de_dy: &VectorN<Fxx, N>,
+    ) -> (VectorN<Fxx, N>, VectorN<Fxx, N>) {
+        let n = N::dim() as Fxx;
+        let (x_hat, std) = self.standardize(x);
+        let de_dx_hat = de_dy.component_mul(&self.scale);
+        let mean_de_dx_hat = de_dx_hat.sum() / n;
+        let mean_projection = de_dx_hat.dot(&x_hat) / n;
+        let de_dx = VectorN::<Fxx, N>::from_fn(|i, _| {
+            (de_dx_hat[i] - mean_de_dx_hat - x_hat[i] * mean_projection) / std
+        });
+        (de_dx, de_dy.component_mul(&x_hat))
+    }
+
+    fn train(&mut self, de_dscale: &VectorN<Fxx, N>, de_dshift: &VectorN<Fxx, N>) {
+        if let Some((scale, shift)) =
+            self.trainer
+                .train(&self.scale, &self.shift, de_dscale, de_dshift)
+        {
+            self.scale = scale;
+            self.shift = shift;
+        }
+    }
+}
+
+impl<'a, N> Model<N, N> for LayerNorm<'a, N>
+where
+    N: DimName,
+    DefaultAllocator: Allocator<Fxx, N> + Allocator<Fxx, N, Dynamic>,
+{
+    fn backpropagate(&mut self, x: &VectorN<Fxx, N>, de_dy: &VectorN<Fxx, N>) -> VectorN<Fxx, N>
+    where
+        DefaultAllocator: Allocator<Fxx, N> + Allocator<Fxx, N>,
+    {
+        debug!("layer norm backprop {}->{}", N::dim(), N::dim());
+        let (de_dx, de_dscale) = self.gradients(x, de_dy);
+        self.train(&de_dscale, de_dy);
+        de_dx
+    }
+
+    ///
+    /// Backpropagate each sample, training once on the mean gradient.
+    ///
+    fn backpropagate_batch(&mut self, x: &Batch<N>, de_dy: &Batch<N>) -> Batch<N>
+    where
+        DefaultAllocator: Allocator<Fxx, N>
+            + Allocator<Fxx, N>
+            + Allocator<Fxx, N, Dynamic>
+            + Allocator<Fxx, N, Dynamic>,
+    {
+        debug!("layer norm batch backprop {}x{}", N::dim(), x.ncols());
+        let mut de_dx = Batch::<N>::zeros(x.ncols());
+        let mut de_dscale = VectorN::<Fxx, N>::zeros();
+        for j in 0..x.ncols() {
+            let (de_dxj, de_dscalej) =
+                self.gradients(&x.column(j).into_owned(), &de_dy.column(j).into_owned());
+            de_dx.set_column(j, &de_dxj);
+            de_dscale += de_dscalej;
+        }
+        let n1 = 1.0 / x.ncols() as Fxx;
+        self.train(&(n1 * de_dscale), &(n1 * de_dy.column_sum()));
+        de_dx
+    }
+
+    #[inline]
+    fn num_inputs(&self) -> usize {
+        N::dim()
+    }
+
+    #[inline]
+    fn num_outputs(&self) -> usize {
+        N::dim()
+    }
+
+    fn predict(&self, x: &VectorN<Fxx, N>) -> VectorN<Fxx, N>
+    where
+        DefaultAllocator: Allocator<Fxx, N> + Allocator<Fxx, N>,
+    {
+        let (x_hat, _) = self.standardize(x);
+        x_hat.component_mul(&self.scale) + &self.shift
+    }
+
+    fn update(&mut self, x: &VectorN<Fxx, N>, y: &VectorN<Fxx, N>) -> VectorN<Fxx, N>
+    where
+        DefaultAllocator: Allocator<Fxx, N> + Allocator<Fxx, N>,
+    {
+        let yh = self.predict(x);
+        let err = yh - y;
+        self.backpropagate(x, &err)
+    }
+
+    fn get_params(&self, params: &mut Vec<Fxx>) {
+        params.extend(self.scale.iter());
+        params.extend(self.shift.iter());
+    }
+
+    fn set_params<'p>(&mut self, params: &'p [Fxx]) -> &'p [Fxx] {
+        let n = N::dim();
+        self.scale.copy_from_slice(&params[..n]);
+        self.shift.copy_from_slice(&params[n..2 * n]);
+        &params[2 * n..]
+    }
+}
+
+#[cfg(test)]
+#[path = "./layer_norm_test.rs"]
+mod layer_norm_test;
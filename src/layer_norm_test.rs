@@ -0,0 +1,89 @@
+use super::*;
+
+use assert_approx_eq::assert_approx_eq;
+
+use na::{Vector3, U3};
+
+use crate::{Batch, Model, SGDTrainer, UpdateParams};
+
+const FIXED_PARAMS: UpdateParams = UpdateParams {
+    step_size: 0.0,
+    l2_reg: 0.0,
+};
+
+const LEARNING_PARAMS: UpdateParams = UpdateParams {
+    step_size: 0.1,
+    l2_reg: 0.0,
+};
+
+#[test]
+fn create_layer_norm() {
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let model = LayerNorm::<U3>::new(&mut trainer);
+    assert_eq!(model.num_inputs(), 3);
+    assert_eq!(model.num_outputs(), 3);
+}
+
+#[test]
+fn normalizes_sample() {
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let model = LayerNorm::<U3>::new(&mut trainer);
+    let y = model.predict(&Vector3::<Fxx>::new(1.0, 2.0, 6.0));
+    assert_approx_eq!(y.sum(), 0.0);
+    assert_approx_eq!(y.norm_squared() / 3.0, 1.0, 1e-4);
+}
+
+#[test]
+fn computes_gradient() {
+    let mut trainer = SGDTrainer::new(&FIXED_PARAMS);
+    let mut model = LayerNorm::<U3>::new(&mut trainer);
+    let x = Vector3::<Fxx>::new(1.0, 2.0, 6.0);
+    let w = Vector3::<Fxx>::new(1.0, -2.0, 0.5);
+
+    let h = 1e-2;
+    let numeric = Vector3::<Fxx>::from_fn(|i, _| {
+        let mut x0 = x;
+        let mut x1 = x;
+        x0[i] -= h;
+        x1[i] += h;
+        (model.predict(&x1).dot(&w) - model.predict(&x0).dot(&w)) / (2.0 * h)
+    });
+
+    let de_dx = model.backpropagate(&x, &w);
+    for i in 0..3 {
+        assert_approx_eq!(de_dx[i], numeric[i], 1e-2);
+    }
+}
+
+#[test]
+fn learns_scale_and_shift() {
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut model = LayerNorm::<U3>::new(&mut trainer);
+    let x = Vector3::<Fxx>::new(1.0, 2.0, 6.0);
+    let y = model.predict(&x) * 2.0 - Vector3::<Fxx>::new(1.0, 1.0, 1.0);
+    for _ in 0..200 {
+        model.update(&x, &y);
+    }
+    let yh = model.predict(&x);
+    for i in 0..3 {
+        assert_approx_eq!(yh[i], y[i], 1e-2);
+    }
+}
+
+#[test]
+fn learns_from_batch() {
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut model = LayerNorm::<U3>::new(&mut trainer);
+    let x = Batch::<U3>::from_row_slice(&[1.0, -1.0, 2.0, 0.0, 6.0, 3.0]);
+    let y = model.predict_batch(&x).add_scalar(1.0);
+    let e0 = (model.predict_batch(&x) - &y).norm();
+    model.update_batch(&x, &y);
+    let e1 = (model.predict_batch(&x) - &y).norm();
+    assert!(e1 < e0, "failed to improve on update {} -> {}", e0, e1);
+    for i in 0..3 {
+        assert!(
+            model.get_shift()[i] > 0.0,
+            "shift not trained toward target"
+        );
+    }
+}
@@ -5,9 +5,9 @@ use log::debug;
 use na::allocator::Allocator;
 use na::storage::Owned;
 use na::Matrix;
-use na::{DefaultAllocator, DimName, VectorN};
+use na::{DefaultAllocator, DimName, Dynamic, VectorN};
 
-use crate::model::{has_nan, Fxx, Model};
+use crate::model::{Batch, Fxx, Model};
 
 pub struct LayeredModel<'a, M: DimName, P: DimName, N: DimName> {
     model0: &'a mut dyn Model<M, P>,
@@ -34,6 +34,7 @@ where
     DefaultAllocator: Allocator<Fxx, N>
         + Allocator<Fxx, N, P>
         + Allocator<Fxx, P>
+        + Allocator<Fxx, P, Dynamic>
         + Allocator<Fxx, P, M>
         + Allocator<usize, M>
         + Allocator<usize, N>
@@ -47,27 +48,8 @@ where
     where
         DefaultAllocator: Allocator<Fxx, N> + Allocator<Fxx, M>,
     {
-        debug_assert!(
-            !has_nan(&x) && !has_nan(&de_dy),
-            "layered backpropagate input has nan"
-        );
-
         let p = self.model0.predict(x);
-        debug_assert!(
-            !has_nan(&p),
-            "layered intermediate result overflow m0({}) -> {}",
-            x,
-            p
-        );
-
         let de_dp = self.model1.backpropagate(&p, de_dy);
-        debug_assert!(
-            !has_nan(&de_dp),
-            "layered intermediate error overflow at m1({}) de_dy={} -> de_dp={}",
-            p,
-            de_dy,
-            de_dp
-        );
         debug!("|de_dp|={}", Matrix::norm(&de_dp));
         debug!("|x|={}", Matrix::norm(x));
 
@@ -76,6 +58,19 @@ where
         de_dx
     }
 
+    fn backpropagate_batch(&mut self, x: &Batch<M>, de_dy: &Batch<N>) -> Batch<M>
+    where
+        DefaultAllocator: Allocator<Fxx, M>
+            + Allocator<Fxx, N>
+            + Allocator<Fxx, M, Dynamic>
+            + Allocator<Fxx, N, Dynamic>,
+    {
+        let p = self.model0.predict_batch(x);
+        let de_dp = self.model1.backpropagate_batch(&p, de_dy);
+        debug!("|de_dp|={}", Matrix::norm(&de_dp));
+        self.model0.backpropagate_batch(x, &de_dp)
+    }
+
     #[inline]
     fn num_inputs(&self) -> usize {
         M::dim()
@@ -90,30 +85,44 @@ where
     where
         DefaultAllocator: Allocator<Fxx, M> + Allocator<Fxx, N>,
     {
-        debug_assert!(!has_nan(&x), "invalid input {}", x);
         let y0 = self.model0.predict(x);
-        debug_assert!(!has_nan(&y0), "invalid intermediate input {}", y0);
         self.model1.predict(&y0)
     }
 
+    fn predict_batch(&self, x: &Batch<M>) -> Batch<N>
+    where
+        DefaultAllocator: Allocator<Fxx, M>
+            + Allocator<Fxx, N>
+            + Allocator<Fxx, M, Dynamic>
+            + Allocator<Fxx, N, Dynamic>,
+    {
+        let y0 = self.model0.predict_batch(x);
+        self.model1.predict_batch(&y0)
+    }
+
     fn update(&mut self, x: &VectorN<Fxx, M>, y: &VectorN<Fxx, N>) -> VectorN<Fxx, M>
     where
         DefaultAllocator: Allocator<Fxx, M> + Allocator<Fxx, N>,
     {
-        debug_assert!(!has_nan(&x) && !has_nan(&y), "layered update input has nan");
-
         let yh = self.predict(x);
         let err = yh - y;
-        debug_assert!(
-            !has_nan(&err) && !has_nan(&yh),
-            "error overflow {} - {} = {}",
-            yh,
-            y,
-            err
-        );
-
         self.backpropagate(x, &err)
     }
+
+    fn set_training(&mut self, training: bool) {
+        self.model0.set_training(training);
+        self.model1.set_training(training);
+    }
+
+    fn get_params(&self, params: &mut Vec<Fxx>) {
+        self.model0.get_params(params);
+        self.model1.get_params(params);
+    }
+
+    fn set_params<'p>(&mut self, params: &'p [Fxx]) -> &'p [Fxx] {
+        let params = self.model0.set_params(params);
+        self.model1.set_params(params)
+    }
 }
 
 #[cfg(test)]
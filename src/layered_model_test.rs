@@ -5,7 +5,7 @@ use na::{U1, U2, U3};
 
 use rand::distributions::{Distribution, Normal};
 
-use crate::{LinearModel, Model, SGDTrainer, setup_logging, UpdateParams};
+use crate::{setup_logging, Batch, LinearModel, Model, SGDTrainer, UpdateParams};
 
 const LEARNING_PARAMS: UpdateParams = UpdateParams {
     step_size: 1e-6,
@@ -73,3 +73,32 @@ fn computes_gradient() {
     println!("rms error {} -> {}", e0.sqrt(), e1.sqrt());
     assert!(e1 < e0);
 }
+
+#[test]
+fn computes_batch_gradient() {
+    let mut train0 = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut model0 = LinearModel::<U3, U2>::new_normal(&mut train0, 10.0);
+    let mut train1 = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut model1 = LinearModel::<U2, U1>::new_normal(&mut train1, 10.0);
+    let mut model = LayeredModel::<U3, U2, U1>::new(&mut model0, &mut model1);
+
+    let normal = Normal::new(0.0, 5.0);
+    let mut rng = rand::thread_rng();
+    let x = Batch::<U3>::from_fn(4, rand!(normal, rng));
+    let y = Batch::<U1>::from_fn(4, |_, j| f(&x.column(j).into_owned())[0]);
+
+    let yh = model.predict_batch(&x);
+    for j in 0..4 {
+        assert_eq!(yh[j], model.predict(&x.column(j).into_owned())[0]);
+    }
+    model.update_batch(&x, &y);
+    let yh1 = model.predict_batch(&x);
+    let e0 = Matrix::norm(&(yh - &y));
+    let e1 = Matrix::norm(&(yh1 - &y));
+    assert!(
+        e1 < e0,
+        "failed to improve on batch update {} -> {}",
+        e0,
+        e1
+    );
+}
@@ -1,24 +1,73 @@
 #![crate_name = "lair"]
 
+pub mod data;
+pub use data::{DataLoader, Dataset, InMemoryDataset};
+
 pub mod img;
 pub use img::conv2d::Conv2d;
 
+mod batch_norm;
+pub use batch_norm::BatchNorm;
+
+mod dropout;
+pub use dropout::Dropout;
+
+mod early_stopping;
+pub use early_stopping::{EarlyStopping, StopReason};
+
+mod error;
+pub use error::Error;
+
+mod fit;
+pub use fit::{Callback, Checkpoint, Control, Fit, FitState, Logger, StepDecay};
+
+mod health;
+pub use health::{Action, Activation, Health, LayerHealth, Monitor};
+
+mod history;
+pub use history::{History, Phase, Record};
+
 mod layered_model;
 pub use layered_model::LayeredModel;
 
+mod layer_norm;
+pub use layer_norm::LayerNorm;
+
 mod linear_model;
 pub use linear_model::LinearModel;
 
 mod logit;
 pub use logit::Logit;
 
+mod metrics;
+pub use metrics::{
+    evaluate, Average, ClassificationMetric, ConfusionMatrix, IntersectionOverUnion, LogLoss,
+    Metric, RSquared, Regression, RegressionMetric, RocCurve, Score,
+};
+
 mod model;
+pub use model::Batch;
 pub use model::Fxx;
 pub use model::Model;
 
+mod parallel_trainer;
+pub use parallel_trainer::ParallelTrainer;
+
+mod plot;
+pub use plot::{Plot, Scale, Series};
+
 mod relu;
 pub use relu::Relu;
 
+mod search;
+pub use search::{
+    grid_search, hyperband, random_search, successive_halving, Domain, Hyperparams, ParamSpace,
+    SearchResults, Trial,
+};
+
+mod tensorboard;
+pub use tensorboard::{crc32c, read_records, write_record, EventWriter};
+
 mod trainer;
 pub use trainer::BatchTrainer;
 pub use trainer::GradientTrainer;
@@ -5,12 +5,14 @@ use log::debug;
 use na::allocator::{Allocator, Reallocator};
 use na::storage::Owned;
 use na::DefaultAllocator;
-use na::{DimAdd, DimName, DimSum, U1};
+use na::{DimAdd, DimName, DimSum, Dynamic, U1};
 use na::{MatrixMN, VectorN};
 
-use rand::distributions::{Distribution, Normal};
+use rand::distributions::{Distribution, Normal, Standard};
+use rand::Rng;
 
-use crate::model::{has_nan, Fxx, Model};
+use crate::error::Error;
+use crate::model::{Batch, Fxx, Model};
 use crate::trainer::GradientTrainer;
 
 // #[derive(Clone, Copy, Debug)]
@@ -45,6 +47,9 @@ where
     M: DimName,
     N: DimName,
     DefaultAllocator: Allocator<Fxx, N>
+        + Allocator<Fxx, M, Dynamic>
+        + Allocator<Fxx, N, Dynamic>
+        + Allocator<Fxx, Dynamic, M>
         + Allocator<Fxx, N, M>
         + Allocator<Fxx, U1, M>
         + Allocator<Fxx, U1, N>
@@ -62,25 +67,12 @@ where
     Owned<usize, N, N>: Copy,
 {
     //
-    // TODO: handle NaN trouble better.  There is also trouble printing ws in
-    // implementing Mul for Copy trait
+    // Non-finite weights and errors are detected by wrapping the model in a
+    // `Monitor`.
     //
     fn backpropagate(&mut self, x: &VectorN<Fxx, M>, de_dy: &VectorN<Fxx, N>) -> VectorN<Fxx, M> {
         debug!("linear backprop {}->{}", M::dim(), N::dim());
-        debug_assert!(
-            !has_nan(&x) && !has_nan(&de_dy),
-            "backpropagate input error x={} de_dy={}",
-            x,
-            de_dy
-        );
-        debug_assert!(!has_nan(&self.ws), "backpropagate-3 NaN ws");
         let input_error = (de_dy.transpose() * self.ws).transpose();
-        debug_assert!(
-            !has_nan(&input_error),
-            "backpropagate unstable return={} from {}T * w",
-            input_error,
-            de_dy
-        ); // trouble printing self.ws
 
         let grad = de_dy * x.transpose();
         match self.trainer.train(&self.ws, &self.bs, &grad, de_dy) {
@@ -93,6 +85,35 @@ where
         input_error
     }
 
+    ///
+    /// Backpropagate a batch with one matrix product for the input error
+    /// and one for the gradient, training once on the mean gradient.
+    ///
+    fn backpropagate_batch(&mut self, x: &Batch<M>, de_dy: &Batch<N>) -> Batch<M>
+    where
+        DefaultAllocator: Allocator<Fxx, M>
+            + Allocator<Fxx, N>
+            + Allocator<Fxx, M, Dynamic>
+            + Allocator<Fxx, N, Dynamic>,
+    {
+        debug!(
+            "linear batch backprop {}->{} x{}",
+            M::dim(),
+            N::dim(),
+            x.ncols()
+        );
+        let input_error = self.ws.tr_mul(de_dy);
+
+        let n1 = 1.0 / x.ncols() as Fxx;
+        let grad = n1 * (de_dy * x.transpose());
+        let bias_grad = n1 * de_dy.column_sum();
+        if let Some((ws, bs)) = self.trainer.train(&self.ws, &self.bs, &grad, &bias_grad) {
+            self.ws = ws;
+            self.bs = bs;
+        }
+        input_error
+    }
+
     #[inline]
     fn num_inputs(&self) -> usize {
         M::dim()
@@ -110,6 +131,20 @@ where
         self.ws * x + self.bs
     }
 
+    fn predict_batch(&self, x: &Batch<M>) -> Batch<N>
+    where
+        DefaultAllocator: Allocator<Fxx, M>
+            + Allocator<Fxx, N>
+            + Allocator<Fxx, M, Dynamic>
+            + Allocator<Fxx, N, Dynamic>,
+    {
+        let mut y = self.ws * x;
+        for mut yj in y.column_iter_mut() {
+            yj += self.bs;
+        }
+        y
+    }
+
     fn update(&mut self, x: &VectorN<Fxx, M>, y: &VectorN<Fxx, N>) -> VectorN<Fxx, M>
     where
         DefaultAllocator: Allocator<Fxx, M> + Allocator<Fxx, N>,
@@ -118,6 +153,22 @@ where
         let err = yh - y;
         self.backpropagate(x, &err)
     }
+
+    ///
+    /// The weights, in column-major order, followed by the biases.
+    ///
+    fn get_params(&self, params: &mut Vec<Fxx>) {
+        params.extend(self.ws.iter());
+        params.extend(self.bs.iter());
+    }
+
+    fn set_params<'p>(&mut self, params: &'p [Fxx]) -> &'p [Fxx] {
+        let (ws, params) = params.split_at(self.ws.len());
+        let (bs, params) = params.split_at(self.bs.len());
+        self.ws.copy_from_slice(ws);
+        self.bs.copy_from_slice(bs);
+        params
+    }
 }
 
 impl<'a, M, N> LinearModel<'a, M, N>
@@ -147,20 +198,31 @@ where
     }
 
     pub fn new_normal(trainer: &'a mut dyn GradientTrainer<M, N>, std: Fxx) -> Self {
+        Self::new_normal_with_rng(trainer, std, &mut rand::thread_rng())
+    }
+
+    ///
+    /// Create a model with weights and biases drawn from a zero-mean normal
+    /// distribution using the given random number generator, e.g. a seeded
+    /// `StdRng` for reproducible training.
+    ///
+    pub fn new_normal_with_rng<R: Rng + ?Sized>(
+        trainer: &'a mut dyn GradientTrainer<M, N>,
+        std: Fxx,
+        rng: &mut R,
+    ) -> Self {
         let normal = Normal::new(0.0, std as f64);
-        let mut rng = rand::thread_rng();
 
         macro_rules! rand {
             () => {
-                |_r, _c| normal.sample(&mut rng) as Fxx
+                |_r, _c| normal.sample(rng) as Fxx
             };
         }
-        let m = LinearModel {
-            trainer: trainer,
+        LinearModel {
+            trainer,
             ws: MatrixMN::<Fxx, N, M>::from_fn(rand!()),
             bs: VectorN::<Fxx, N>::from_fn(rand!()),
-        };
-        m
+        }
     }
 
     pub fn new_random(trainer: &'a mut dyn GradientTrainer<M, N>) -> Self {
@@ -172,11 +234,26 @@ where
         m
     }
 
+    ///
+    /// Create a model with weights and biases drawn uniformly from [0, 1)
+    /// using the given random number generator.
+    ///
+    pub fn new_random_with_rng<R: Rng + ?Sized>(
+        trainer: &'a mut dyn GradientTrainer<M, N>,
+        rng: &mut R,
+    ) -> Self {
+        LinearModel {
+            trainer,
+            ws: MatrixMN::<Fxx, N, M>::from_fn(|_r, _c| Standard.sample(rng)),
+            bs: VectorN::<Fxx, N>::from_fn(|_r, _c| Standard.sample(rng)),
+        }
+    }
+
     pub fn update_bulk<D: DimName>(
         &mut self,
         x: &MatrixMN<Fxx, M, D>,
         y: &MatrixMN<Fxx, N, D>,
-    ) -> Result<(), String>
+    ) -> Result<(), Error>
     where
         DefaultAllocator: Reallocator<Fxx, M, D, DimSum<M, U1>, D>
             + Reallocator<Fxx, DimSum<M, U1>, D, D, DimSum<M, U1>>
@@ -205,8 +282,10 @@ where
             );
             Ok(())
         } else {
-            let err = format!("cannot update_bulk, no inverse for {}", xxt);
-            Err(err)
+            Err(Error::Singular(format!(
+                "cannot update_bulk, no inverse for {}",
+                xxt
+            )))
         }
     }
 
@@ -2,9 +2,11 @@ use super::*;
 
 use assert_approx_eq::assert_approx_eq;
 
-use crate::{SGDTrainer, UpdateParams};
+use crate::{Batch, BatchTrainer, SGDTrainer, UpdateParams};
 use na::{Matrix, Matrix1, Matrix1x2, Matrix1x3, Matrix2x1, Matrix2x3};
 use na::{U1, U2};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 
 const LEARNING_PARAMS: UpdateParams = UpdateParams {
     step_size: 0.001,
@@ -80,7 +82,7 @@ fn update_bulk_linear_model() {
     let mut model = LinearModel::<U2, U1>::new_random(&mut trainer);
     let x = Matrix2x3::new(2.0, 3.0, 4.0, 1.0, 4.0, 5.0);
     let y = Matrix1x3::new(6.0, 11.0, 14.0);
-    assert_eq!(model.update_bulk(&x, &y), Ok(()));
+    model.update_bulk(&x, &y).unwrap();
 
     let x0 = Matrix2x1::new(0.5, 1.0);
     let yh = model.predict(&x0);
@@ -96,6 +98,64 @@ fn update_bulk_unconstrained_linear_model() {
     let updated = model.update_bulk(&x, &y);
     match updated {
         Ok(_) => panic!("update_bulk expected error from unconstrained update matrix"),
-        Err(msg) => assert!(msg.starts_with("cannot update_bulk, no inverse for")),
+        Err(Error::Singular(msg)) => {
+            assert!(msg.starts_with("cannot update_bulk, no inverse for"))
+        }
+        Err(err) => panic!("update_bulk expected singular matrix, got {}", err),
     }
 }
+
+#[test]
+fn seeded_models_are_reproducible() {
+    let mut trainer0 = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut trainer1 = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut rng0 = StdRng::seed_from_u64(7);
+    let mut rng1 = StdRng::seed_from_u64(7);
+    let model0 = LinearModel::<U2, U1>::new_normal_with_rng(&mut trainer0, 1.0, &mut rng0);
+    let model1 = LinearModel::<U2, U1>::new_normal_with_rng(&mut trainer1, 1.0, &mut rng1);
+    assert_eq!(model0.ws, model1.ws);
+    assert_eq!(model0.bs, model1.bs);
+
+    let model2 = LinearModel::<U2, U1>::new_random_with_rng(&mut trainer0, &mut rng0);
+    let model3 = LinearModel::<U2, U1>::new_random_with_rng(&mut trainer1, &mut rng1);
+    assert_eq!(model2.ws, model3.ws);
+    assert_eq!(model2.bs, model3.bs);
+    assert!(model2.ws.iter().all(|w| (0.0..1.0).contains(w)));
+}
+
+#[test]
+fn predicts_batch() {
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let model = LinearModel::<U2, U1>::new_random(&mut trainer);
+    let x = Batch::<U2>::from_row_slice(&[2.0, 3.0, 4.0, 1.0, 4.0, 5.0]);
+    let y = model.predict_batch(&x);
+    assert_eq!(y.ncols(), 3);
+    for j in 0..3 {
+        assert_approx_eq!(y[j], model.predict(&x.column(j).into_owned())[0]);
+    }
+}
+
+#[test]
+fn backpropagate_batch_matches_batch_trainer() {
+    let x = Batch::<U2>::from_row_slice(&[2.0, 3.0, 4.0, 1.0, 4.0, 5.0]);
+    let y = Batch::<U1>::from_row_slice(&[6.0, 11.0, 14.0]);
+
+    let mut trainer0 = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut model0 =
+        LinearModel::<U2, U1>::new_random_with_rng(&mut trainer0, &mut StdRng::seed_from_u64(3));
+    let de_dx = model0.update_batch(&x, &y);
+
+    let mut trainer1 = BatchTrainer::<U2, U1>::new(&LEARNING_PARAMS, 3);
+    let mut model1 =
+        LinearModel::<U2, U1>::new_random_with_rng(&mut trainer1, &mut StdRng::seed_from_u64(3));
+    for j in 0..3 {
+        let de_dxj = model1.update(&x.column(j).into_owned(), &y.column(j).into_owned());
+        assert_approx_eq!(de_dx[(0, j)], de_dxj[0]);
+        assert_approx_eq!(de_dx[(1, j)], de_dxj[1]);
+    }
+
+    for i in 0..2 {
+        assert_approx_eq!(model0.ws[i], model1.ws[i]);
+    }
+    assert_approx_eq!(model0.bs[0], model1.bs[0]);
+}
@@ -3,9 +3,9 @@ extern crate nalgebra as na;
 use log::debug;
 
 use na::allocator::Allocator;
-use na::{DefaultAllocator, DimName, VectorN};
+use na::{DefaultAllocator, DimName, Dynamic, VectorN};
 
-use crate::model::{has_nan, Fxx, Model};
+use crate::model::{Batch, Fxx, Model};
 
 pub struct Logit<'a, M: DimName, N: DimName> {
     model: &'a mut dyn Model<M, N>,
@@ -42,17 +42,23 @@ where
         DefaultAllocator: Allocator<Fxx, M> + Allocator<Fxx, N>,
     {
         debug!("logit backprop {}->{}", M::dim(), N::dim());
-        debug_assert!(!has_nan(&x), "backprop x has_nan");
-        debug_assert!(!has_nan(&de_dy), "backprop de_dy has_nan");
-
         let p = self.model.predict(x);
-        debug_assert!(!has_nan(&p), "backprop p has_nan");
-        
         let de_dp = VectorN::<Fxx, N>::from_fn(|r, _c| dlogit(p[r]) * de_dy[r]);
-        debug_assert!(!has_nan(&de_dp), "backprop de_dp has_nan");
         self.model.backpropagate(x, &de_dp)
     }
 
+    fn backpropagate_batch(&mut self, x: &Batch<M>, de_dy: &Batch<N>) -> Batch<M>
+    where
+        DefaultAllocator: Allocator<Fxx, M>
+            + Allocator<Fxx, N>
+            + Allocator<Fxx, M, Dynamic>
+            + Allocator<Fxx, N, Dynamic>,
+    {
+        let p = self.model.predict_batch(x);
+        let de_dp = de_dy.zip_map(&p, |e, pi| dlogit(pi) * e);
+        self.model.backpropagate_batch(x, &de_dp)
+    }
+
     #[inline]
     fn num_inputs(&self) -> usize {
         M::dim()
@@ -71,10 +77,19 @@ where
         for i in 0..self.num_outputs() {
             y[i] = logit(y[i])
         }
-        debug_assert!(!has_nan(&y), "logit predict has_nan");
         y
     }
 
+    fn predict_batch(&self, x: &Batch<M>) -> Batch<N>
+    where
+        DefaultAllocator: Allocator<Fxx, M>
+            + Allocator<Fxx, N>
+            + Allocator<Fxx, M, Dynamic>
+            + Allocator<Fxx, N, Dynamic>,
+    {
+        self.model.predict_batch(x).map(logit)
+    }
+
     fn update(&mut self, x: &VectorN<Fxx, M>, y: &VectorN<Fxx, N>) -> VectorN<Fxx, M>
     where
         DefaultAllocator: Allocator<Fxx, M> + Allocator<Fxx, N>,
@@ -83,6 +98,18 @@ where
         let err = yh - y;
         self.backpropagate(x, &err)
     }
+
+    fn set_training(&mut self, training: bool) {
+        self.model.set_training(training);
+    }
+
+    fn get_params(&self, params: &mut Vec<Fxx>) {
+        self.model.get_params(params);
+    }
+
+    fn set_params<'p>(&mut self, params: &'p [Fxx]) -> &'p [Fxx] {
+        self.model.set_params(params)
+    }
 }
 
 #[cfg(test)]
@@ -5,7 +5,7 @@ use assert_approx_eq::assert_approx_eq;
 use na::{Matrix1, Matrix1x3, Matrix2x1, Matrix2x3};
 use na::{U1, U2};
 
-use crate::{LinearModel, Model, SGDTrainer, UpdateParams};
+use crate::{Batch, LinearModel, Model, SGDTrainer, UpdateParams};
 
 const LEARNING_PARAMS: UpdateParams = UpdateParams {
     step_size: 0.5,
@@ -18,7 +18,7 @@ fn applies_logit() {
     let mut underlying_model = LinearModel::<U2, U1>::new_random(&mut trainer);
     let x = Matrix2x3::new(2.0, 3.0, 4.0, 1.0, 4.0, 5.0);
     let y = Matrix1x3::new(6.0, 11.0, 14.0);
-    assert_eq!(underlying_model.update_bulk(&x, &y), Ok(()));
+    underlying_model.update_bulk(&x, &y).unwrap();
 
     let model = Logit::<U2, U1>::new(&mut underlying_model);
     let x0 = Matrix2x1::new(0.5, 1.0);
@@ -78,4 +78,25 @@ fn derivative_is_stable_with_large_values() {
 fn derivative_is_stable_with_negative_large_values() {
     let dl = dlogit(-15466372000.0);
     assert_approx_eq!(dl, 0.0);
-}
\ No newline at end of file
+}
+
+#[test]
+fn logit_updates_batch() {
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut underlying_model = LinearModel::<U1, U1>::new_random(&mut trainer);
+    let mut model = Logit::<U1, U1>::new(&mut underlying_model);
+
+    let x = Batch::<U1>::from_row_slice(&[2.0, 1.0, 0.0]);
+    let y = Batch::<U1>::from_row_slice(&[1.0, 0.0, 0.0]);
+    let y0 = model.predict_batch(&x);
+    for j in 0..3 {
+        assert_approx_eq!(y0[j], model.predict(&Matrix1::new(x[j]))[0]);
+    }
+
+    model.update_batch(&x, &y);
+    let y1 = model.predict_batch(&x);
+    assert!(
+        (y1 - &y).norm() < (y0 - &y).norm(),
+        "expect improved batch predictions"
+    );
+}
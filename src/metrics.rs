@@ -0,0 +1,564 @@
+extern crate nalgebra as na;
+
+use na::allocator::Allocator;
+use na::{DMatrix, DefaultAllocator, DimName, VectorN};
+
+use std::cmp::Ordering;
+use std::marker::PhantomData;
+
+use crate::data::{class_of, Dataset};
+use crate::error::Error;
+use crate::model::{Fxx, Model};
+
+/// Probabilities are clipped to [EPSILON, 1 - EPSILON] by `LogLoss`.
+const EPSILON: Fxx = 1e-7;
+
+///
+/// An evaluation metric accumulated over a stream of predictions and their
+/// targets.
+///
+pub trait Metric<N: DimName>
+where
+    DefaultAllocator: Allocator<Fxx, N>,
+{
+    /// A short name for reports, e.g. "mse".
+    fn name(&self) -> String;
+
+    /// Accumulate a prediction and its target.
+    fn update(&mut self, prediction: &VectorN<Fxx, N>, target: &VectorN<Fxx, N>);
+
+    /// The metric over the pairs accumulated so far, NaN if there are none.
+    fn value(&self) -> Fxx;
+
+    /// Forget the accumulated pairs.
+    fn reset(&mut self);
+}
+
+///
+/// Accumulate the predictions of the model on every example of the dataset
+/// into the metrics.
+///
+pub fn evaluate<M, N>(
+    model: &dyn Model<M, N>,
+    dataset: &dyn Dataset<M, N>,
+    metrics: &mut [&mut dyn Metric<N>],
+) where
+    M: DimName,
+    N: DimName,
+    DefaultAllocator: Allocator<Fxx, M> + Allocator<Fxx, N>,
+{
+    for i in 0..dataset.len() {
+        let (x, y) = dataset.get(i);
+        let yh = model.predict(&x);
+        for metric in metrics.iter_mut() {
+            metric.update(&yh, &y);
+        }
+    }
+}
+
+///
+/// A mean error over every component of every prediction.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Regression {
+    /// Mean squared error.
+    Mse,
+    /// Root mean squared error.
+    Rmse,
+    /// Mean absolute error.
+    Mae,
+}
+
+///
+/// A regression error accumulated over a stream of predictions.
+///
+#[derive(Clone, Debug)]
+pub struct RegressionMetric {
+    error: Regression,
+    count: usize,
+    squared: Fxx,
+    absolute: Fxx,
+}
+
+impl RegressionMetric {
+    pub fn new(error: Regression) -> Self {
+        RegressionMetric {
+            error,
+            count: 0,
+            squared: 0.0,
+            absolute: 0.0,
+        }
+    }
+}
+
+impl<N> Metric<N> for RegressionMetric
+where
+    N: DimName,
+    DefaultAllocator: Allocator<Fxx, N>,
+{
+    fn name(&self) -> String {
+        format!("{:?}", self.error).to_lowercase()
+    }
+
+    fn update(&mut self, prediction: &VectorN<Fxx, N>, target: &VectorN<Fxx, N>) {
+        let e = prediction - target;
+        self.count += N::dim();
+        self.squared += e.norm_squared();
+        self.absolute += e.lp_norm(1);
+    }
+
+    fn value(&self) -> Fxx {
+        let n = self.count as Fxx;
+        match self.error {
+            Regression::Mse => self.squared / n,
+            Regression::Rmse => (self.squared / n).sqrt(),
+            Regression::Mae => self.absolute / n,
+        }
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new(self.error);
+    }
+}
+
+///
+/// The coefficient of determination, 1 - SSE / SST, pooling the squared
+/// errors and deviations from the per-component target means of every
+/// component.
+///
+#[derive(Clone, Debug, Default)]
+pub struct RSquared {
+    count: usize,
+    squared_error: Fxx,
+    sum: Vec<Fxx>,
+    sum_squares: Vec<Fxx>,
+}
+
+impl RSquared {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<N> Metric<N> for RSquared
+where
+    N: DimName,
+    DefaultAllocator: Allocator<Fxx, N>,
+{
+    fn name(&self) -> String {
+        "r2".to_string()
+    }
+
+    fn update(&mut self, prediction: &VectorN<Fxx, N>, target: &VectorN<Fxx, N>) {
+        if self.sum.is_empty() {
+            self.sum = vec![0.0; N::dim()];
+            self.sum_squares = vec![0.0; N::dim()];
+        }
+        self.count += 1;
+        self.squared_error += (prediction - target).norm_squared();
+        for (i, &y) in target.iter().enumerate() {
+            self.sum[i] += y;
+            self.sum_squares[i] += y * y;
+        }
+    }
+
+    fn value(&self) -> Fxx {
+        let n = self.count as Fxx;
+        let total: Fxx = self
+            .sum
+            .iter()
+            .zip(self.sum_squares.iter())
+            .map(|(s, ss)| ss - s * s / n)
+            .sum();
+        1.0 - self.squared_error / total
+    }
+
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+///
+/// Counts of examples by actual class, the row, and predicted class, the
+/// column.  Classes are the greatest output components, or for single
+/// outputs 0 and 1 by thresholding at 0.5.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfusionMatrix {
+    counts: DMatrix<usize>,
+}
+
+impl ConfusionMatrix {
+    pub fn new(classes: usize) -> Self {
+        ConfusionMatrix {
+            counts: DMatrix::zeros(classes, classes),
+        }
+    }
+
+    pub fn get_counts(&self) -> &DMatrix<usize> {
+        &self.counts
+    }
+
+    pub fn num_classes(&self) -> usize {
+        self.counts.nrows()
+    }
+
+    pub fn add(&mut self, actual: usize, predicted: usize) {
+        self.counts[(actual, predicted)] += 1;
+    }
+
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    fn true_positives(&self, class: usize) -> usize {
+        self.counts[(class, class)]
+    }
+
+    fn predicted(&self, class: usize) -> usize {
+        self.counts.column(class).iter().sum()
+    }
+
+    fn actual(&self, class: usize) -> usize {
+        self.counts.row(class).iter().sum()
+    }
+
+    pub fn accuracy(&self) -> Fxx {
+        let correct: usize = (0..self.num_classes())
+            .map(|k| self.true_positives(k))
+            .sum();
+        correct as Fxx / self.total() as Fxx
+    }
+
+    /// The fraction of predictions of the class that are correct.
+    pub fn precision(&self, class: usize) -> Fxx {
+        self.true_positives(class) as Fxx / self.predicted(class) as Fxx
+    }
+
+    /// The fraction of examples of the class predicted correctly.
+    pub fn recall(&self, class: usize) -> Fxx {
+        self.true_positives(class) as Fxx / self.actual(class) as Fxx
+    }
+
+    pub fn f1(&self, class: usize) -> Fxx {
+        harmonic_mean(self.precision(class), self.recall(class))
+    }
+
+    /// Precision, recall and F1 averaged as selected.
+    pub fn score(&self, score: Score, average: Average) -> Fxx {
+        match average {
+            Average::Macro => {
+                let k = self.num_classes();
+                (0..k)
+                    .map(|class| match score {
+                        Score::Precision => self.precision(class),
+                        Score::Recall => self.recall(class),
+                        Score::F1 => self.f1(class),
+                    })
+                    .map(|s| if s.is_nan() { 0.0 } else { s })
+                    .sum::<Fxx>()
+                    / k as Fxx
+            }
+            // pooled over classes every misclassification is both a false
+            // positive and a false negative, so all are the accuracy
+            Average::Micro => self.accuracy(),
+        }
+    }
+}
+
+fn harmonic_mean(p: Fxx, r: Fxx) -> Fxx {
+    if p + r > 0.0 {
+        2.0 * p * r / (p + r)
+    } else {
+        0.0
+    }
+}
+
+///
+/// How per-class scores are combined.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Average {
+    /// The unweighted mean of the per-class scores, undefined scores, of
+    /// classes never predicted or present, counting as 0.
+    Macro,
+    /// The score of the true and false positive and negative counts pooled
+    /// over classes.
+    Micro,
+}
+
+///
+/// A classification score derived from the confusion matrix.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Score {
+    Precision,
+    Recall,
+    F1,
+}
+
+///
+/// A score, or accuracy, of the confusion matrix of a stream of
+/// classifications.  The classes are the output components, or 0 and 1 for
+/// single outputs.
+///
+#[derive(Clone, Debug)]
+pub struct ClassificationMetric {
+    confusion: ConfusionMatrix,
+    score: Option<(Score, Average)>,
+}
+
+impl ClassificationMetric {
+    pub fn accuracy() -> Self {
+        ClassificationMetric {
+            confusion: ConfusionMatrix::new(0),
+            score: None,
+        }
+    }
+
+    pub fn new(score: Score, average: Average) -> Self {
+        ClassificationMetric {
+            confusion: ConfusionMatrix::new(0),
+            score: Some((score, average)),
+        }
+    }
+
+    pub fn get_confusion(&self) -> &ConfusionMatrix {
+        &self.confusion
+    }
+}
+
+impl<N> Metric<N> for ClassificationMetric
+where
+    N: DimName,
+    DefaultAllocator: Allocator<Fxx, N>,
+{
+    fn name(&self) -> String {
+        match self.score {
+            None => "accuracy".to_string(),
+            Some((score, average)) => format!("{:?}_{:?}", average, score).to_lowercase(),
+        }
+    }
+
+    fn update(&mut self, prediction: &VectorN<Fxx, N>, target: &VectorN<Fxx, N>) {
+        let classes = N::dim().max(2);
+        if self.confusion.num_classes() != classes {
+            self.confusion = ConfusionMatrix::new(classes);
+        }
+        self.confusion.add(class_of(target), class_of(prediction));
+    }
+
+    fn value(&self) -> Fxx {
+        match self.score {
+            None => self.confusion.accuracy(),
+            Some((score, average)) => self.confusion.score(score, average),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.confusion = ConfusionMatrix::new(self.confusion.num_classes());
+    }
+}
+
+///
+/// Mean cross-entropy of predicted probabilities.  Single outputs are
+/// binary probabilities of the positive class, otherwise the outputs are
+/// class probabilities, e.g. from softmax, and targets one-hot.
+///
+#[derive(Clone, Debug, Default)]
+pub struct LogLoss {
+    count: usize,
+    sum: Fxx,
+}
+
+impl LogLoss {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<N> Metric<N> for LogLoss
+where
+    N: DimName,
+    DefaultAllocator: Allocator<Fxx, N>,
+{
+    fn name(&self) -> String {
+        "log_loss".to_string()
+    }
+
+    fn update(&mut self, prediction: &VectorN<Fxx, N>, target: &VectorN<Fxx, N>) {
+        let ln = |p: Fxx| p.clamp(EPSILON, 1.0 - EPSILON).ln();
+        self.count += 1;
+        self.sum -= if N::dim() == 1 {
+            let (p, y) = (prediction[0], target[0]);
+            y * ln(p) + (1.0 - y) * ln(1.0 - p)
+        } else {
+            prediction
+                .iter()
+                .zip(target.iter())
+                .map(|(&p, &y)| y * ln(p))
+                .sum::<Fxx>()
+        };
+    }
+
+    fn value(&self) -> Fxx {
+        self.sum / self.count as Fxx
+    }
+
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+///
+/// The receiver operating characteristic of the scores of one output
+/// component, an example being positive if its target component exceeds
+/// 0.5.  `value` is the area under the curve.
+///
+#[derive(Clone, Debug)]
+pub struct RocCurve<N: DimName> {
+    class: usize,
+    scores: Vec<(Fxx, bool)>,
+    _outputs: PhantomData<N>,
+}
+
+impl<N: DimName> RocCurve<N> {
+    ///
+    /// # Arguments
+    /// * `class` - the output component scoring the positive class.
+    ///
+    pub fn new(class: usize) -> Result<Self, Error> {
+        if class >= N::dim() {
+            return Err(Error::Config(format!(
+                "ROC class {} of {} outputs",
+                class,
+                N::dim()
+            )));
+        }
+        Ok(RocCurve {
+            class,
+            scores: Vec::new(),
+            _outputs: PhantomData,
+        })
+    }
+
+    ///
+    /// The (false positive rate, true positive rate) points from the
+    /// greatest threshold to the least, starting at (0, 0) and ending at
+    /// (1, 1).  Tied scores make a single point.
+    ///
+    pub fn curve(&self) -> Vec<(Fxx, Fxx)> {
+        let mut scores = self.scores.clone();
+        scores.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+        let positives = scores.iter().filter(|s| s.1).count() as Fxx;
+        let negatives = scores.len() as Fxx - positives;
+
+        let mut points = vec![(0.0, 0.0)];
+        let (mut tp, mut fp) = (0.0, 0.0);
+        for (i, &(score, positive)) in scores.iter().enumerate() {
+            if positive {
+                tp += 1.0;
+            } else {
+                fp += 1.0;
+            }
+            if i + 1 == scores.len() || scores[i + 1].0 != score {
+                points.push((fp / negatives, tp / positives));
+            }
+        }
+        points
+    }
+
+    /// The area under the curve by the trapezoid rule.
+    pub fn auc(&self) -> Fxx {
+        self.curve()
+            .windows(2)
+            .map(|w| (w[1].0 - w[0].0) * (w[1].1 + w[0].1) / 2.0)
+            .sum()
+    }
+}
+
+impl<N> Metric<N> for RocCurve<N>
+where
+    N: DimName,
+    DefaultAllocator: Allocator<Fxx, N>,
+{
+    fn name(&self) -> String {
+        "auc".to_string()
+    }
+
+    fn update(&mut self, prediction: &VectorN<Fxx, N>, target: &VectorN<Fxx, N>) {
+        self.scores
+            .push((prediction[self.class], target[self.class] > 0.5));
+    }
+
+    fn value(&self) -> Fxx {
+        self.auc()
+    }
+
+    fn reset(&mut self) {
+        self.scores.clear();
+    }
+}
+
+///
+/// Intersection over union of masks, e.g. the position maps of the conv2d
+/// benchmark, components being set if they exceed a threshold, 0.5 by
+/// default.  Intersections and unions are pooled over the stream.
+///
+#[derive(Clone, Debug)]
+pub struct IntersectionOverUnion {
+    threshold: Fxx,
+    intersection: usize,
+    union: usize,
+}
+
+impl IntersectionOverUnion {
+    pub fn new() -> Self {
+        IntersectionOverUnion {
+            threshold: 0.5,
+            intersection: 0,
+            union: 0,
+        }
+    }
+
+    pub fn set_threshold(&mut self, threshold: Fxx) {
+        self.threshold = threshold;
+    }
+}
+
+impl Default for IntersectionOverUnion {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<N> Metric<N> for IntersectionOverUnion
+where
+    N: DimName,
+    DefaultAllocator: Allocator<Fxx, N>,
+{
+    fn name(&self) -> String {
+        "iou".to_string()
+    }
+
+    fn update(&mut self, prediction: &VectorN<Fxx, N>, target: &VectorN<Fxx, N>) {
+        for (&p, &y) in prediction.iter().zip(target.iter()) {
+            let (p, y) = (p > self.threshold, y > self.threshold);
+            self.intersection += (p && y) as usize;
+            self.union += (p || y) as usize;
+        }
+    }
+
+    fn value(&self) -> Fxx {
+        self.intersection as Fxx / self.union as Fxx
+    }
+
+    fn reset(&mut self) {
+        self.intersection = 0;
+        self.union = 0;
+    }
+}
+
+#[cfg(test)]
+#[path = "./metrics_test.rs"]
+mod metrics_test;
@@ -0,0 +1,187 @@
+use super::*;
+
+use assert_approx_eq::assert_approx_eq;
+
+use na::{Vector1, Vector2, Vector3, Vector4, U1, U2};
+
+use crate::data::InMemoryDataset;
+use crate::{LinearModel, SGDTrainer, UpdateParams};
+
+type Pair<N> = (VectorN<Fxx, N>, VectorN<Fxx, N>);
+
+fn update_all<N>(metric: &mut dyn Metric<N>, pairs: &[Pair<N>])
+where
+    N: DimName,
+    DefaultAllocator: Allocator<Fxx, N>,
+{
+    for (p, y) in pairs {
+        metric.update(p, y);
+    }
+}
+
+fn regression_pairs() -> Vec<Pair<U2>> {
+    vec![
+        (Vector2::new(1.0, 2.0), Vector2::new(1.0, 4.0)),
+        (Vector2::new(3.0, 0.0), Vector2::new(2.0, 0.0)),
+    ]
+}
+
+#[test]
+fn computes_regression_errors() {
+    let pairs = regression_pairs();
+    let mut mse = RegressionMetric::new(Regression::Mse);
+    let mut rmse = RegressionMetric::new(Regression::Rmse);
+    let mut mae = RegressionMetric::new(Regression::Mae);
+    assert!(Metric::<U2>::value(&mse).is_nan());
+    update_all(&mut mse, &pairs);
+    update_all(&mut rmse, &pairs);
+    update_all(&mut mae, &pairs);
+    assert_eq!(Metric::<U2>::value(&mse), 5.0 / 4.0);
+    assert_approx_eq!(Metric::<U2>::value(&rmse), (1.25 as Fxx).sqrt());
+    assert_eq!(Metric::<U2>::value(&mae), 3.0 / 4.0);
+    assert_eq!(Metric::<U2>::name(&rmse), "rmse");
+
+    Metric::<U2>::reset(&mut mse);
+    assert!(Metric::<U2>::value(&mse).is_nan());
+}
+
+#[test]
+fn computes_r_squared() {
+    let mut r2 = RSquared::new();
+    let ys = [1.0, 2.0, 3.0, 4.0];
+    for &y in ys.iter() {
+        r2.update(&Vector1::new(y), &Vector1::new(y));
+    }
+    assert_eq!(Metric::<U1>::value(&r2), 1.0);
+
+    Metric::<U1>::reset(&mut r2);
+    // predicting the mean explains nothing
+    for &y in ys.iter() {
+        r2.update(&Vector1::new(2.5), &Vector1::new(y));
+    }
+    assert_approx_eq!(Metric::<U1>::value(&r2), 0.0);
+}
+
+#[test]
+fn confuses_classes() {
+    let mut confusion = ConfusionMatrix::new(3);
+    for &(actual, predicted) in [(0, 0), (0, 0), (0, 1), (1, 1), (2, 1), (2, 2)].iter() {
+        confusion.add(actual, predicted);
+    }
+    assert_eq!(confusion.total(), 6);
+    assert_eq!(confusion.get_counts()[(2, 1)], 1);
+    assert_approx_eq!(confusion.accuracy(), 4.0 / 6.0);
+    assert_approx_eq!(confusion.precision(1), 1.0 / 3.0);
+    assert_approx_eq!(confusion.recall(0), 2.0 / 3.0);
+    assert_approx_eq!(confusion.f1(1), 0.5);
+    assert_approx_eq!(
+        confusion.score(Score::Recall, Average::Macro),
+        (2.0 / 3.0 + 1.0 + 0.5) / 3.0
+    );
+    assert_approx_eq!(
+        confusion.score(Score::F1, Average::Micro),
+        confusion.accuracy()
+    );
+
+    let mut never_predicted = ConfusionMatrix::new(2);
+    never_predicted.add(1, 0);
+    assert!(never_predicted.precision(1).is_nan());
+    assert_eq!(never_predicted.score(Score::Precision, Average::Macro), 0.0);
+}
+
+#[test]
+fn classifies_streams() {
+    let mut accuracy = ClassificationMetric::accuracy();
+    let mut f1 = ClassificationMetric::new(Score::F1, Average::Macro);
+    let pairs = [
+        (Vector3::new(0.7, 0.2, 0.1), Vector3::new(1.0, 0.0, 0.0)),
+        (Vector3::new(0.1, 0.2, 0.7), Vector3::new(0.0, 1.0, 0.0)),
+    ];
+    update_all(&mut accuracy, &pairs);
+    update_all(&mut f1, &pairs);
+    assert_eq!(Metric::<na::U3>::value(&accuracy), 0.5);
+    assert_eq!(accuracy.get_confusion().get_counts()[(1, 2)], 1);
+    assert_approx_eq!(Metric::<na::U3>::value(&f1), 1.0 / 3.0);
+    assert_eq!(Metric::<na::U3>::name(&f1), "macro_f1");
+
+    // single outputs are thresholded
+    let mut binary = ClassificationMetric::accuracy();
+    binary.update(&Vector1::new(0.8), &Vector1::new(1.0));
+    binary.update(&Vector1::new(0.3), &Vector1::new(1.0));
+    assert_eq!(Metric::<U1>::value(&binary), 0.5);
+    assert_eq!(binary.get_confusion().num_classes(), 2);
+}
+
+#[test]
+fn computes_log_loss() {
+    let mut binary = LogLoss::new();
+    binary.update(&Vector1::new(0.5), &Vector1::new(1.0));
+    binary.update(&Vector1::new(0.5), &Vector1::new(0.0));
+    assert_approx_eq!(Metric::<U1>::value(&binary), (2.0 as Fxx).ln());
+
+    let mut categorical = LogLoss::new();
+    categorical.update(&Vector2::new(1.0, 0.0), &Vector2::new(1.0, 0.0));
+    assert_approx_eq!(Metric::<U2>::value(&categorical), 0.0);
+    // certainty in the wrong class is clipped
+    categorical.update(&Vector2::new(1.0, 0.0), &Vector2::new(0.0, 1.0));
+    assert!(Metric::<U2>::value(&categorical).is_finite());
+}
+
+#[test]
+fn traces_roc() {
+    let mut roc = RocCurve::<U1>::new(0).unwrap();
+    for &(score, label) in [(0.9, 1.0), (0.8, 1.0), (0.7, 0.0), (0.6, 1.0), (0.2, 0.0)].iter() {
+        roc.update(&Vector1::new(score), &Vector1::new(label));
+    }
+    let curve = roc.curve();
+    assert_eq!(curve.first(), Some(&(0.0, 0.0)));
+    assert_eq!(curve.last(), Some(&(1.0, 1.0)));
+    assert_eq!(curve.len(), 6);
+    // 5 of 6 positive-negative pairs are ordered correctly
+    assert_approx_eq!(Metric::<U1>::value(&roc), 5.0 / 6.0);
+
+    let mut ties = RocCurve::<U2>::new(1).unwrap();
+    ties.update(&Vector2::new(0.0, 0.5), &Vector2::new(0.0, 1.0));
+    ties.update(&Vector2::new(0.0, 0.5), &Vector2::new(1.0, 0.0));
+    assert_eq!(ties.curve(), vec![(0.0, 0.0), (1.0, 1.0)]);
+    assert_approx_eq!(ties.auc(), 0.5);
+
+    assert!(matches!(RocCurve::<U2>::new(2), Err(Error::Config(_))));
+}
+
+#[test]
+fn intersects_masks() {
+    let mut iou = IntersectionOverUnion::new();
+    iou.update(
+        &Vector4::new(0.9, 0.8, 0.1, 0.0),
+        &Vector4::new(1.0, 0.0, 1.0, 0.0),
+    );
+    assert_approx_eq!(Metric::<na::U4>::value(&iou), 1.0 / 3.0);
+    iou.set_threshold(0.95);
+    Metric::<na::U4>::reset(&mut iou);
+    iou.update(
+        &Vector4::new(0.9, 0.8, 0.1, 0.0),
+        &Vector4::new(1.0, 0.0, 1.0, 0.0),
+    );
+    assert_eq!(Metric::<na::U4>::value(&iou), 0.0);
+}
+
+#[test]
+fn evaluates_models() {
+    let params = UpdateParams {
+        step_size: 0.1,
+        l2_reg: 0.0,
+    };
+    let mut trainer = SGDTrainer::new(&params);
+    let mut model = LinearModel::<U2, U1>::new_random(&mut trainer);
+    model.set_params(&[1.0, -1.0, 0.5]);
+    let dataset =
+        InMemoryDataset::from_fn(vec![Vector2::new(1.0, 0.0), Vector2::new(0.0, 1.0)], |x| {
+            Vector1::new(x[0] - x[1] + 0.5)
+        });
+    let mut mse = RegressionMetric::new(Regression::Mse);
+    let mut mae = RegressionMetric::new(Regression::Mae);
+    evaluate(&model, &dataset, &mut [&mut mse, &mut mae]);
+    assert_eq!(Metric::<U1>::value(&mse), 0.0);
+    assert_eq!(Metric::<U1>::value(&mae), 0.0);
+}
@@ -5,8 +5,11 @@ pub type Fxx = f32;
 use na::allocator::Allocator;
 use nalgebra::storage::Storage;
 use na::DefaultAllocator;
-use na::DimName;
-use na::{Matrix, VectorN};
+use na::{Dim, DimName, Dynamic};
+use na::{Matrix, MatrixMN, VectorN};
+
+/// A mini-batch of samples of dimension M, one sample per column.
+pub type Batch<M> = MatrixMN<Fxx, M, Dynamic>;
 
 pub trait Model<M: DimName, N: DimName> {
     /// Apply backpropagation to this layer/module of a neural network,
@@ -22,6 +25,34 @@ pub trait Model<M: DimName, N: DimName> {
     where
         DefaultAllocator: Allocator<Fxx, N> + Allocator<Fxx, M>;
 
+    /// Apply backpropagation for a mini-batch, one sample per column,
+    /// returning the backpropagated error for each sample.
+    ///
+    /// The default implementation backpropagates each sample in turn.  Models
+    /// with parameters should override it to apply a single update from the
+    /// mean gradient over the batch.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - the inputs at which the model is being trained.
+    /// * `de_dy` - the error partial derivatives with respect to the outputs
+    ///   of this model for each input.
+    fn backpropagate_batch(&mut self, x: &Batch<M>, de_dy: &Batch<N>) -> Batch<M>
+    where
+        DefaultAllocator: Allocator<Fxx, M>
+            + Allocator<Fxx, N>
+            + Allocator<Fxx, M, Dynamic>
+            + Allocator<Fxx, N, Dynamic>,
+    {
+        let mut de_dx = Batch::<M>::zeros(x.ncols());
+        for j in 0..x.ncols() {
+            let de_dxj =
+                self.backpropagate(&x.column(j).into_owned(), &de_dy.column(j).into_owned());
+            de_dx.set_column(j, &de_dxj);
+        }
+        de_dx
+    }
+
     fn num_inputs(&self) -> usize;
     fn num_outputs(&self) -> usize;
 
@@ -34,6 +65,28 @@ pub trait Model<M: DimName, N: DimName> {
     where
         DefaultAllocator: Allocator<Fxx, M> + Allocator<Fxx, N>;
 
+    /// Run the model to predict values for a mini-batch of inputs, one
+    /// sample per column.
+    ///
+    /// The default implementation predicts each sample in turn.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - inputs for which to compute modeled values.
+    fn predict_batch(&self, x: &Batch<M>) -> Batch<N>
+    where
+        DefaultAllocator: Allocator<Fxx, M>
+            + Allocator<Fxx, N>
+            + Allocator<Fxx, M, Dynamic>
+            + Allocator<Fxx, N, Dynamic>,
+    {
+        let mut y = Batch::<N>::zeros(x.ncols());
+        for j in 0..x.ncols() {
+            y.set_column(j, &self.predict(&x.column(j).into_owned()));
+        }
+        y
+    }
+
     /// Update a model with an observation, y, from given input, x, returning
     /// the gradient of the input to be used for backpropogation.
     ///
@@ -43,12 +96,58 @@ pub trait Model<M: DimName, N: DimName> {
     fn update(&mut self, x: &VectorN<Fxx, M>, y: &VectorN<Fxx, N>) -> VectorN<Fxx, M>
     where
         DefaultAllocator: Allocator<Fxx, M> + Allocator<Fxx, N>;
+
+    /// Update a model with a mini-batch of observations, one sample per
+    /// column, returning the gradient of each input.
+    ///
+    /// # Arguments
+    /// * `x` - inputs corresponding to the observations y.
+    /// * `y` - observed/"correct" values corresponding to the inputs x.
+    fn update_batch(&mut self, x: &Batch<M>, y: &Batch<N>) -> Batch<M>
+    where
+        DefaultAllocator: Allocator<Fxx, M>
+            + Allocator<Fxx, N>
+            + Allocator<Fxx, M, Dynamic>
+            + Allocator<Fxx, N, Dynamic>,
+    {
+        let yh = self.predict_batch(x);
+        let err = yh - y;
+        self.backpropagate_batch(x, &err)
+    }
+
+    /// Switch the model between training and evaluation behavior, e.g.
+    /// disabling dropout so that `predict` is deterministic, propagating the
+    /// mode to any wrapped models.  Models behaving identically in both modes
+    /// need not implement this.
+    ///
+    /// # Arguments
+    /// * `training` - true to train, false to evaluate.
+    fn set_training(&mut self, _training: bool) {}
+
+    /// Append the trainable parameters of the model, and any wrapped models,
+    /// to params in a fixed order, e.g. to copy or average parameters between
+    /// replicas of a network.  Models without parameters need not implement
+    /// this.
+    ///
+    /// # Arguments
+    /// * `params` - the buffer to extend with the model parameters.
+    fn get_params(&self, _params: &mut Vec<Fxx>) {}
+
+    /// Overwrite the trainable parameters of the model, and any wrapped
+    /// models, from the front of params in the order of `get_params`,
+    /// returning the unused remainder.
+    ///
+    /// # Arguments
+    /// * `params` - the parameters to load.
+    fn set_params<'p>(&mut self, params: &'p [Fxx]) -> &'p [Fxx] {
+        params
+    }
 }
 
 pub fn has_nan<M, N, S>(x: &Matrix<Fxx, M, N, S>) -> bool
 where
-    M: DimName,
-    N: DimName,
+    M: Dim,
+    N: Dim,
     S: Storage<Fxx, M, N>,
 {
     for i in 0..x.len() {
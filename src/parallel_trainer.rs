@@ -0,0 +1,172 @@
+extern crate nalgebra as na;
+
+use std::any::Any;
+use std::thread;
+
+use log::debug;
+
+use na::allocator::Allocator;
+use na::storage::Owned;
+use na::{DefaultAllocator, DimName, Dynamic};
+
+use crate::error::Error;
+use crate::model::{Batch, Fxx, Model};
+
+///
+/// Data-parallel training with parameter averaging.  Each call to `train`
+/// splits a batch into one shard per worker thread.  Every worker loads the
+/// current parameters into its own replica of the network, trains it on its
+/// shard and returns the resulting parameters, which are averaged, weighted
+/// by shard size, back into the model.
+///
+/// Models borrow their trainers and wrapped models, so replicas can't be
+/// cloned generically.  Instead the caller supplies a function, run on each
+/// worker thread, that builds a replica, loads the given parameters with
+/// `Model::set_params`, trains on the shard (e.g. over several mini-batches
+/// between merges) and returns the parameters from `Model::get_params`.
+///
+pub struct ParallelTrainer {
+    num_workers: usize,
+}
+
+impl ParallelTrainer {
+    ///
+    /// # Arguments
+    /// * `num_workers` - the number of threads, and shards per batch.
+    ///
+    pub fn new(num_workers: usize) -> Result<Self, Error> {
+        if num_workers == 0 {
+            return Err(Error::Config(
+                "parallel training requires a worker".to_string(),
+            ));
+        }
+        Ok(ParallelTrainer { num_workers })
+    }
+
+    pub fn get_num_workers(&self) -> usize {
+        self.num_workers
+    }
+
+    ///
+    /// Train replicas of model on shards of the batch in parallel and
+    /// replace the model parameters with the replicas' weighted average.
+    /// The model is left unchanged if a worker panics or returns the wrong
+    /// number of parameters.
+    ///
+    /// # Arguments
+    /// * `model` - the model holding the parameters to train.
+    /// * `x` - the inputs, one sample per column.
+    /// * `y` - the observations corresponding to x.
+    /// * `train_shard` - called with the current parameters and a shard of
+    ///   x and y, returns the parameters of a replica trained on the shard.
+    ///
+    pub fn train<M, N, F>(
+        &self,
+        model: &mut dyn Model<M, N>,
+        x: &Batch<M>,
+        y: &Batch<N>,
+        train_shard: F,
+    ) -> Result<(), Error>
+    where
+        M: DimName,
+        N: DimName,
+        DefaultAllocator: Allocator<Fxx, M, Dynamic> + Allocator<Fxx, N, Dynamic>,
+        Owned<Fxx, M, Dynamic>: Send,
+        Owned<Fxx, N, Dynamic>: Send,
+        F: Fn(&[Fxx], &Batch<M>, &Batch<N>) -> Vec<Fxx> + Sync,
+    {
+        if x.ncols() != y.ncols() {
+            return Err(Error::Shape {
+                name: "observation batch".to_string(),
+                expected: x.ncols(),
+                found: y.ncols(),
+            });
+        }
+        let mut params = Vec::new();
+        model.get_params(&mut params);
+
+        let shards = shard_bounds(x.ncols(), self.num_workers);
+        debug!(
+            "parallel train {} samples in {} shards",
+            x.ncols(),
+            shards.len()
+        );
+        let params_ref = &params;
+        let train_ref = &train_shard;
+        // join every worker before reporting any failure, as the scope panics
+        // on workers left unjoined
+        let results: Vec<thread::Result<(usize, Vec<Fxx>)>> = thread::scope(|s| {
+            let workers: Vec<_> = shards
+                .iter()
+                .map(|&(start, len)| {
+                    let xs = x.columns(start, len).into_owned();
+                    let ys = y.columns(start, len).into_owned();
+                    s.spawn(move || (len, train_ref(params_ref, &xs, &ys)))
+                })
+                .collect();
+            workers.into_iter().map(|worker| worker.join()).collect()
+        });
+        let results = results
+            .into_iter()
+            .map(|result| result.map_err(panic_error))
+            .collect::<Result<Vec<_>, Error>>()?;
+        if results.is_empty() {
+            return Ok(());
+        }
+
+        let total = x.ncols() as Fxx;
+        let mut mean = vec![0.0; params.len()];
+        for (len, shard_params) in results.iter() {
+            if shard_params.len() != params.len() {
+                return Err(Error::Shape {
+                    name: "replica parameters".to_string(),
+                    expected: params.len(),
+                    found: shard_params.len(),
+                });
+            }
+            let weight = *len as Fxx / total;
+            for (mi, pi) in mean.iter_mut().zip(shard_params.iter()) {
+                *mi += weight * pi;
+            }
+        }
+        model.set_params(&mean);
+        Ok(())
+    }
+}
+
+///
+/// An error reporting the panic of a worker, with its message if it has one.
+///
+fn panic_error(payload: Box<dyn Any + Send>) -> Error {
+    let msg = if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg.to_string()
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        "parallel training worker panicked".to_string()
+    };
+    Error::Worker(msg)
+}
+
+///
+/// The (start column, length) of each non-empty shard, splitting n samples as
+/// evenly as possible among the workers.
+///
+fn shard_bounds(n: usize, num_workers: usize) -> Vec<(usize, usize)> {
+    let base = n / num_workers;
+    let extra = n % num_workers;
+    let mut start = 0;
+    let mut bounds = Vec::with_capacity(num_workers);
+    for i in 0..num_workers {
+        let len = if i < extra { base + 1 } else { base };
+        if len > 0 {
+            bounds.push((start, len));
+        }
+        start += len;
+    }
+    bounds
+}
+
+#[cfg(test)]
+#[path = "./parallel_trainer_test.rs"]
+mod parallel_trainer_test;
@@ -0,0 +1,146 @@
+use super::*;
+
+use assert_approx_eq::assert_approx_eq;
+
+use na::{Matrix, U1, U2};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::{LayeredModel, LinearModel, Relu, SGDTrainer, UpdateParams};
+
+const LEARNING_PARAMS: UpdateParams = UpdateParams {
+    step_size: 0.1,
+    l2_reg: 0.0,
+};
+
+fn train_linear(params: &[Fxx], x: &Batch<U2>, y: &Batch<U1>) -> Vec<Fxx> {
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut model = LinearModel::<U2, U1>::new_random(&mut trainer);
+    model.set_params(params);
+    model.update_batch(x, y);
+    let mut result = Vec::new();
+    model.get_params(&mut result);
+    result
+}
+
+#[test]
+fn splits_shards() {
+    assert_eq!(shard_bounds(7, 3), vec![(0, 3), (3, 2), (5, 2)]);
+    assert_eq!(shard_bounds(2, 4), vec![(0, 1), (1, 1)]);
+    assert_eq!(shard_bounds(0, 2), vec![]);
+}
+
+#[test]
+fn copies_params() {
+    let mut rng = StdRng::seed_from_u64(1);
+    let mut train0 = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut m0 = LinearModel::<U2, U2>::new_random_with_rng(&mut train0, &mut rng);
+    let mut relu = Relu::new(&mut m0);
+    let mut train1 = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut m1 = LinearModel::<U2, U1>::new_random_with_rng(&mut train1, &mut rng);
+    let model = LayeredModel::new(&mut relu, &mut m1);
+    let mut params = Vec::new();
+    model.get_params(&mut params);
+    assert_eq!(params.len(), 6 + 3);
+
+    let mut train2 = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut m2 = LinearModel::<U2, U2>::new_random(&mut train2);
+    let mut relu1 = Relu::new(&mut m2);
+    let mut train3 = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut m3 = LinearModel::<U2, U1>::new_random(&mut train3);
+    let mut replica = LayeredModel::new(&mut relu1, &mut m3);
+    assert!(replica.set_params(&params).is_empty());
+
+    let x = Batch::<U2>::from_row_slice(&[1.0, -2.0, 0.5, 3.0]);
+    assert_eq!(replica.predict_batch(&x), model.predict_batch(&x));
+}
+
+#[test]
+fn single_worker_matches_serial_update() {
+    let x = Batch::<U2>::from_row_slice(&[2.0, 3.0, 4.0, 1.0, 4.0, 5.0]);
+    let y = Batch::<U1>::from_row_slice(&[6.0, 11.0, 14.0]);
+
+    let mut train0 = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut model0 =
+        LinearModel::<U2, U1>::new_random_with_rng(&mut train0, &mut StdRng::seed_from_u64(2));
+    model0.update_batch(&x, &y);
+
+    let mut train1 = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut model1 =
+        LinearModel::<U2, U1>::new_random_with_rng(&mut train1, &mut StdRng::seed_from_u64(2));
+    ParallelTrainer::new(1)
+        .unwrap()
+        .train(&mut model1, &x, &y, train_linear)
+        .unwrap();
+    assert_eq!(model0.get_ws(), model1.get_ws());
+}
+
+#[test]
+fn averages_by_shard_size() {
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut model = LinearModel::<U2, U1>::new_random(&mut trainer);
+    let x = Batch::<U2>::from_row_slice(&[1.0, 2.0, 3.0, 0.0, 0.0, 0.0]);
+    let y = Batch::<U1>::zeros(3);
+
+    // shards hold columns {0, 1} and {2}, each replica reporting its first input
+    ParallelTrainer::new(2)
+        .unwrap()
+        .train(&mut model, &x, &y, |_, xs, _| vec![xs[0]; 3])
+        .unwrap();
+    let mut params = Vec::new();
+    model.get_params(&mut params);
+    for p in params {
+        assert_approx_eq!(p, (2.0 * 1.0 + 3.0) / 3.0);
+    }
+}
+
+#[test]
+fn parallel_training_improves_estimate() {
+    let mut rng = StdRng::seed_from_u64(3);
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut model = LinearModel::<U2, U1>::new_random_with_rng(&mut trainer, &mut rng);
+    let x = Batch::<U2>::from_fn(16, |i, j| (i + j) as Fxx / 16.0);
+    let y = Batch::<U1>::from_fn(16, |_, j| 2.0 * x[(0, j)] - x[(1, j)] + 1.0);
+
+    let e0 = Matrix::norm(&(model.predict_batch(&x) - &y));
+    let parallel = ParallelTrainer::new(4).unwrap();
+    for _ in 0..10 {
+        parallel.train(&mut model, &x, &y, train_linear).unwrap();
+    }
+    let e1 = Matrix::norm(&(model.predict_batch(&x) - &y));
+    assert!(
+        e1 < e0,
+        "failed to improve on parallel update {} -> {}",
+        e0,
+        e1
+    );
+}
+
+#[test]
+fn reports_failures() {
+    assert!(matches!(ParallelTrainer::new(0), Err(Error::Config(_))));
+
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut model = LinearModel::<U2, U1>::new_random(&mut trainer);
+    let mut params = Vec::new();
+    model.get_params(&mut params);
+    let x = Batch::<U2>::zeros(4);
+    let parallel = ParallelTrainer::new(2).unwrap();
+    assert!(matches!(
+        parallel.train(&mut model, &x, &Batch::<U1>::zeros(3), train_linear),
+        Err(Error::Shape { .. })
+    ));
+
+    let y = Batch::<U1>::zeros(4);
+    assert!(matches!(
+        parallel.train(&mut model, &x, &y, |_, _, _| vec![0.0; 2]),
+        Err(Error::Shape { .. })
+    ));
+    match parallel.train(&mut model, &x, &y, |_, _, _| panic!("replica diverged")) {
+        Err(Error::Worker(msg)) => assert_eq!(msg, "replica diverged"),
+        result => panic!("expected worker failure, got {:?}", result),
+    }
+    let mut unchanged = Vec::new();
+    model.get_params(&mut unchanged);
+    assert_eq!(unchanged, params);
+}
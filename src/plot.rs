@@ -0,0 +1,652 @@
+use std::fs;
+
+use image::{Rgb, RgbImage};
+
+use crate::error::Error;
+use crate::history::{History, Phase};
+use crate::img::font::{draw_text, text_width, GLYPH_HEIGHT};
+use crate::model::Fxx;
+
+///
+/// How values map to positions along an axis.  Log scales omit
+/// non-positive values.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scale {
+    Linear,
+    Log,
+}
+
+/// Series colors, in order of addition.
+const PALETTE: [Rgb<u8>; 8] = [
+    Rgb([148, 0, 211]),
+    Rgb([0, 158, 115]),
+    Rgb([86, 180, 233]),
+    Rgb([230, 159, 0]),
+    Rgb([240, 228, 66]),
+    Rgb([0, 114, 178]),
+    Rgb([213, 94, 0]),
+    Rgb([204, 121, 167]),
+];
+const WHITE: Rgb<u8> = Rgb([255, 255, 255]);
+const BLACK: Rgb<u8> = Rgb([0, 0, 0]);
+const GRID: Rgb<u8> = Rgb([224, 224, 224]);
+
+const MARGIN_LEFT: f64 = 80.0;
+const MARGIN_RIGHT: f64 = 20.0;
+const MARGIN_TOP: f64 = 40.0;
+const MARGIN_BOTTOM: f64 = 50.0;
+const TICK: f64 = 5.0;
+const LEGEND_LINE: f64 = 20.0;
+const LEGEND_ROW: f64 = 12.0;
+/// The most ticks drawn along an axis.
+const MAX_TICKS: usize = 10;
+
+pub struct Series {
+    pub label: String,
+    pub points: Vec<(Fxx, Fxx)>,
+}
+
+///
+/// A line plot of one or more series, e.g. learning curves from training
+/// histories, with optional log-scale axes and a legend of the labeled
+/// series, rendered as SVG or as an image through the `image` crate.
+///
+pub struct Plot {
+    width: u32,
+    height: u32,
+    title: String,
+    x_label: String,
+    y_label: String,
+    x_scale: Scale,
+    y_scale: Scale,
+    series: Vec<Series>,
+}
+
+impl Plot {
+    ///
+    /// # Arguments
+    /// * `width` - the width in pixels of the rendered plot.
+    /// * `height` - the height in pixels of the rendered plot.
+    ///
+    pub fn new(width: u32, height: u32) -> Self {
+        Plot {
+            width,
+            height,
+            title: String::new(),
+            x_label: String::new(),
+            y_label: String::new(),
+            x_scale: Scale::Linear,
+            y_scale: Scale::Linear,
+            series: Vec::new(),
+        }
+    }
+
+    pub fn set_title(&mut self, title: &str) {
+        self.title = title.to_string();
+    }
+
+    pub fn set_x_label(&mut self, label: &str) {
+        self.x_label = label.to_string();
+    }
+
+    pub fn set_y_label(&mut self, label: &str) {
+        self.y_label = label.to_string();
+    }
+
+    pub fn set_x_scale(&mut self, scale: Scale) {
+        self.x_scale = scale;
+    }
+
+    pub fn set_y_scale(&mut self, scale: Scale) {
+        self.y_scale = scale;
+    }
+
+    pub fn get_series(&self) -> &[Series] {
+        &self.series
+    }
+
+    ///
+    /// Add a series of (x, y) points, drawn in order and broken at points
+    /// that can't be drawn on the axes' scales, e.g. NaN.  Series with
+    /// empty labels are left out of the legend.
+    ///
+    pub fn add_series(&mut self, label: &str, points: &[(Fxx, Fxx)]) {
+        self.series.push(Series {
+            label: label.to_string(),
+            points: points.to_vec(),
+        });
+    }
+
+    ///
+    /// Add the values of a name recorded in a phase of a training history,
+    /// versus the step or epoch number.
+    ///
+    pub fn add_history(&mut self, label: &str, history: &History, phase: Phase, name: &str) {
+        let points: Vec<(Fxx, Fxx)> = history
+            .series(phase, name)
+            .into_iter()
+            .map(|(x, y)| (x as Fxx, y))
+            .collect();
+        self.add_series(label, &points);
+    }
+
+    pub fn to_svg(&self) -> String {
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" \
+             viewBox=\"0 0 {0} {1}\">\n",
+            self.width, self.height
+        );
+        for mark in self.marks() {
+            svg.push_str(&mark.to_svg());
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    pub fn write_svg(&self, path: &str) -> Result<(), Error> {
+        fs::write(path, self.to_svg())?;
+        Ok(())
+    }
+
+    pub fn to_image(&self) -> RgbImage {
+        let mut image = RgbImage::new(self.width, self.height);
+        for mark in self.marks() {
+            mark.draw(&mut image);
+        }
+        image
+    }
+
+    ///
+    /// Write the plot as an image, in the format given by the path's
+    /// extension, e.g. PNG.
+    ///
+    pub fn write_image(&self, path: &str) -> Result<(), Error> {
+        self.to_image().save(path)?;
+        Ok(())
+    }
+
+    fn marks(&self) -> Vec<Mark> {
+        let (width, height) = (self.width as f64, self.height as f64);
+        let left = MARGIN_LEFT;
+        let right = (width - MARGIN_RIGHT).max(left + 1.0);
+        let top = MARGIN_TOP;
+        let bottom = (height - MARGIN_BOTTOM).max(top + 1.0);
+
+        let drawn: Vec<&(Fxx, Fxx)> = self
+            .series
+            .iter()
+            .flat_map(|s| s.points.iter())
+            .filter(|p| drawable(self.x_scale, p.0) && drawable(self.y_scale, p.1))
+            .collect();
+        let x_axis = Axis::fit(self.x_scale, drawn.iter().map(|p| p.0 as f64));
+        let y_axis = Axis::fit(self.y_scale, drawn.iter().map(|p| p.1 as f64));
+        let px = |x: f64| left + x_axis.position(x) * (right - left);
+        let py = |y: f64| bottom - y_axis.position(y) * (bottom - top);
+
+        let mut marks = vec![Mark::Rect {
+            x: 0.0,
+            y: 0.0,
+            width,
+            height,
+            fill: Some(WHITE),
+            stroke: None,
+        }];
+
+        for &tick in &x_axis.ticks {
+            let x = px(tick);
+            marks.push(Mark::Line {
+                points: vec![(x, top), (x, bottom)],
+                color: GRID,
+            });
+            marks.push(Mark::Line {
+                points: vec![(x, bottom), (x, bottom + TICK)],
+                color: BLACK,
+            });
+            marks.push(Mark::text(
+                x,
+                bottom + 2.0 * TICK,
+                &x_axis.label(tick),
+                Anchor::Middle,
+                1,
+            ));
+        }
+        for &tick in &y_axis.ticks {
+            let y = py(tick);
+            marks.push(Mark::Line {
+                points: vec![(left, y), (right, y)],
+                color: GRID,
+            });
+            marks.push(Mark::Line {
+                points: vec![(left - TICK, y), (left, y)],
+                color: BLACK,
+            });
+            marks.push(Mark::text(
+                left - 2.0 * TICK,
+                y - GLYPH_HEIGHT as f64 / 2.0,
+                &y_axis.label(tick),
+                Anchor::End,
+                1,
+            ));
+        }
+
+        for (i, series) in self.series.iter().enumerate() {
+            let color = PALETTE[i % PALETTE.len()];
+            let mut run = Vec::new();
+            for &(x, y) in &series.points {
+                if drawable(self.x_scale, x) && drawable(self.y_scale, y) {
+                    run.push((px(x as f64), py(y as f64)));
+                } else if !run.is_empty() {
+                    marks.push(Mark::Line {
+                        points: run.split_off(0),
+                        color,
+                    });
+                }
+            }
+            if !run.is_empty() {
+                marks.push(Mark::Line { points: run, color });
+            }
+        }
+
+        marks.push(Mark::Rect {
+            x: left,
+            y: top,
+            width: right - left,
+            height: bottom - top,
+            fill: None,
+            stroke: Some(BLACK),
+        });
+
+        let labeled: Vec<(usize, &Series)> = self
+            .series
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| !s.label.is_empty())
+            .collect();
+        if !labeled.is_empty() {
+            let label_width = labeled
+                .iter()
+                .map(|(_, s)| text_width(&s.label, 1))
+                .max()
+                .unwrap_or(0) as f64;
+            let legend_width = label_width + LEGEND_LINE + 3.0 * TICK;
+            let legend_x = right - legend_width - TICK;
+            let legend_y = top + TICK;
+            marks.push(Mark::Rect {
+                x: legend_x,
+                y: legend_y,
+                width: legend_width,
+                height: labeled.len() as f64 * LEGEND_ROW + TICK,
+                fill: Some(WHITE),
+                stroke: Some(BLACK),
+            });
+            for (row, (i, series)) in labeled.iter().enumerate() {
+                let y = legend_y + TICK + row as f64 * LEGEND_ROW;
+                let line_y = y + GLYPH_HEIGHT as f64 / 2.0;
+                marks.push(Mark::Line {
+                    points: vec![
+                        (legend_x + TICK, line_y),
+                        (legend_x + TICK + LEGEND_LINE, line_y),
+                    ],
+                    color: PALETTE[i % PALETTE.len()],
+                });
+                marks.push(Mark::text(
+                    legend_x + 2.0 * TICK + LEGEND_LINE,
+                    y,
+                    &series.label,
+                    Anchor::Start,
+                    1,
+                ));
+            }
+        }
+
+        if !self.title.is_empty() {
+            marks.push(Mark::text(
+                (left + right) / 2.0,
+                (top - 2.0 * GLYPH_HEIGHT as f64) / 2.0,
+                &self.title,
+                Anchor::Middle,
+                2,
+            ));
+        }
+        if !self.x_label.is_empty() {
+            marks.push(Mark::text(
+                (left + right) / 2.0,
+                bottom + 4.0 * TICK + GLYPH_HEIGHT as f64,
+                &self.x_label,
+                Anchor::Middle,
+                1,
+            ));
+        }
+        if !self.y_label.is_empty() {
+            marks.push(Mark::text(
+                left,
+                top - GLYPH_HEIGHT as f64 - TICK,
+                &self.y_label,
+                Anchor::Start,
+                1,
+            ));
+        }
+        marks
+    }
+}
+
+fn drawable(scale: Scale, v: Fxx) -> bool {
+    v.is_finite() && (scale == Scale::Linear || v > 0.0)
+}
+
+///
+/// The range and ticks of an axis.  Linear ranges extend to multiples of
+/// 1, 2 or 5 times a power of 10, log ranges to powers of 10.
+///
+struct Axis {
+    scale: Scale,
+    min: f64,
+    max: f64,
+    ticks: Vec<f64>,
+}
+
+impl Axis {
+    fn fit<I: Iterator<Item = f64>>(scale: Scale, values: I) -> Self {
+        let (mut min, mut max) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| {
+            (lo.min(v), hi.max(v))
+        });
+        match scale {
+            Scale::Linear => {
+                if min > max {
+                    min = 0.0;
+                    max = 1.0;
+                } else if min == max {
+                    let pad = if min == 0.0 { 1.0 } else { min.abs() / 2.0 };
+                    min -= pad;
+                    max += pad;
+                }
+                let step = nice_step((max - min) / (MAX_TICKS / 2) as f64);
+                // tolerate rounding, e.g. of values already on a tick
+                let first = (min / step + 1e-9).floor() as i64;
+                let last = (max / step - 1e-9).ceil() as i64;
+                Axis {
+                    scale,
+                    min: first as f64 * step,
+                    max: last as f64 * step,
+                    ticks: (first..=last).map(|k| k as f64 * step).collect(),
+                }
+            }
+            Scale::Log => {
+                let (first, mut last) = if min > max {
+                    (0, 1)
+                } else {
+                    (min.log10().floor() as i32, max.log10().ceil() as i32)
+                };
+                if first == last {
+                    last += 1;
+                }
+                let stride = ((last - first) as usize).div_ceil(MAX_TICKS).max(1);
+                Axis {
+                    scale,
+                    min: 10f64.powi(first),
+                    max: 10f64.powi(last),
+                    ticks: (first..=last)
+                        .step_by(stride)
+                        .map(|e| 10f64.powi(e))
+                        .collect(),
+                }
+            }
+        }
+    }
+
+    /// The position of a value along the axis, from 0 at min to 1 at max.
+    fn position(&self, v: f64) -> f64 {
+        match self.scale {
+            Scale::Linear => (v - self.min) / (self.max - self.min),
+            Scale::Log => (v.log10() - self.min.log10()) / (self.max.log10() - self.min.log10()),
+        }
+    }
+
+    fn label(&self, tick: f64) -> String {
+        match self.scale {
+            Scale::Linear => {
+                let step = if self.ticks.len() > 1 {
+                    self.ticks[1] - self.ticks[0]
+                } else {
+                    1.0
+                };
+                let decimals = (-step.log10().floor()).max(0.0) as usize;
+                let tick = if tick.abs() < step / 2.0 { 0.0 } else { tick };
+                format!("{:.*}", decimals, tick)
+            }
+            Scale::Log => {
+                let e = tick.log10().round() as i32;
+                match e {
+                    0..=3 => format!("1{}", "0".repeat(e as usize)),
+                    -2..=-1 => format!("0.{}1", "0".repeat((-e - 1) as usize)),
+                    _ => format!("1e{}", e),
+                }
+            }
+        }
+    }
+}
+
+/// The least 1, 2 or 5 times a power of 10 at least x.
+fn nice_step(x: f64) -> f64 {
+    let magnitude = 10f64.powf(x.log10().floor());
+    let fraction = x / magnitude;
+    let nice = if fraction <= 1.0 {
+        1.0
+    } else if fraction <= 2.0 {
+        2.0
+    } else if fraction <= 5.0 {
+        5.0
+    } else {
+        10.0
+    };
+    nice * magnitude
+}
+
+enum Anchor {
+    Start,
+    Middle,
+    End,
+}
+
+///
+/// Drawing primitives shared by the SVG and image renderings, in pixel
+/// coordinates from the top left.  Text is positioned by the top of its
+/// glyphs.
+///
+enum Mark {
+    Rect {
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+        fill: Option<Rgb<u8>>,
+        stroke: Option<Rgb<u8>>,
+    },
+    Line {
+        points: Vec<(f64, f64)>,
+        color: Rgb<u8>,
+    },
+    Text {
+        x: f64,
+        y: f64,
+        text: String,
+        anchor: Anchor,
+        scale: u32,
+    },
+}
+
+fn svg_color(color: Option<Rgb<u8>>) -> String {
+    match color {
+        Some(Rgb([r, g, b])) => format!("rgb({},{},{})", r, g, b),
+        None => "none".to_string(),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+impl Mark {
+    fn text(x: f64, y: f64, text: &str, anchor: Anchor, scale: u32) -> Self {
+        Mark::Text {
+            x,
+            y,
+            text: text.to_string(),
+            anchor,
+            scale,
+        }
+    }
+
+    fn to_svg(&self) -> String {
+        match self {
+            Mark::Rect {
+                x,
+                y,
+                width,
+                height,
+                fill,
+                stroke,
+            } => format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\" stroke=\"{}\"/>\n",
+                x,
+                y,
+                width,
+                height,
+                svg_color(*fill),
+                svg_color(*stroke)
+            ),
+            Mark::Line { points, color } => {
+                let points: Vec<String> = points
+                    .iter()
+                    .map(|(x, y)| format!("{:.1},{:.1}", x, y))
+                    .collect();
+                format!(
+                    "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\"/>\n",
+                    points.join(" "),
+                    svg_color(Some(*color))
+                )
+            }
+            Mark::Text {
+                x,
+                y,
+                text,
+                anchor,
+                scale,
+            } => {
+                let anchor = match anchor {
+                    Anchor::Start => "start",
+                    Anchor::Middle => "middle",
+                    Anchor::End => "end",
+                };
+                format!(
+                    "<text x=\"{}\" y=\"{}\" font-family=\"monospace\" font-size=\"{}\" \
+                     text-anchor=\"{}\">{}</text>\n",
+                    x,
+                    y + (GLYPH_HEIGHT * scale) as f64,
+                    10 * scale,
+                    anchor,
+                    escape(text)
+                )
+            }
+        }
+    }
+
+    fn draw(&self, image: &mut RgbImage) {
+        match self {
+            Mark::Rect {
+                x,
+                y,
+                width,
+                height,
+                fill,
+                stroke,
+            } => {
+                let (x0, y0) = (x.round() as i64, y.round() as i64);
+                let (x1, y1) = ((x + width).round() as i64, (y + height).round() as i64);
+                if let Some(fill) = fill {
+                    for py in y0.max(0)..y1.min(image.height() as i64) {
+                        for px in x0.max(0)..x1.min(image.width() as i64) {
+                            image.put_pixel(px as u32, py as u32, *fill);
+                        }
+                    }
+                }
+                if let Some(stroke) = stroke {
+                    let corners = [(x0, y0), (x1, y0), (x1, y1), (x0, y1), (x0, y0)];
+                    for edge in corners.windows(2) {
+                        draw_segment(image, edge[0], edge[1], *stroke);
+                    }
+                }
+            }
+            Mark::Line { points, color } => {
+                let rounded: Vec<(i64, i64)> = points
+                    .iter()
+                    .map(|(x, y)| (x.round() as i64, y.round() as i64))
+                    .collect();
+                if rounded.len() == 1 {
+                    draw_segment(image, rounded[0], rounded[0], *color);
+                }
+                for segment in rounded.windows(2) {
+                    draw_segment(image, segment[0], segment[1], *color);
+                }
+            }
+            Mark::Text {
+                x,
+                y,
+                text,
+                anchor,
+                scale,
+            } => {
+                let width = text_width(text, *scale) as f64;
+                let left = match anchor {
+                    Anchor::Start => *x,
+                    Anchor::Middle => x - width / 2.0,
+                    Anchor::End => x - width,
+                };
+                draw_text(
+                    image,
+                    left.round() as i64,
+                    y.round() as i64,
+                    text,
+                    *scale,
+                    BLACK,
+                );
+            }
+        }
+    }
+}
+
+///
+/// Bresenham's line, clipped to the image.
+///
+fn draw_segment(image: &mut RgbImage, from: (i64, i64), to: (i64, i64), color: Rgb<u8>) {
+    let (mut x, mut y) = from;
+    let dx = (to.0 - x).abs();
+    let dy = -(to.1 - y).abs();
+    let sx = if x < to.0 { 1 } else { -1 };
+    let sy = if y < to.1 { 1 } else { -1 };
+    let mut err = dx + dy;
+    loop {
+        if x >= 0 && y >= 0 && x < image.width() as i64 && y < image.height() as i64 {
+            image.put_pixel(x as u32, y as u32, color);
+        }
+        if (x, y) == to {
+            break;
+        }
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            y += sy;
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./plot_test.rs"]
+mod plot_test;
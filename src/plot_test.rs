@@ -0,0 +1,106 @@
+use super::*;
+
+fn curves() -> Plot {
+    let mut plot = Plot::new(320, 240);
+    plot.set_title("convergence");
+    plot.set_x_label("samples");
+    plot.set_y_label("mean error");
+    plot.add_series(
+        "step=1e-2",
+        &[(0.0, 10.0), (100.0, 1.0), (200.0, 0.1), (300.0, 0.01)],
+    );
+    plot.add_series("step=1e-3", &[(0.0, 10.0), (300.0, 1.0)]);
+    plot
+}
+
+#[test]
+fn fits_linear_axes() {
+    let axis = Axis::fit(Scale::Linear, vec![0.3, 7.6].into_iter());
+    assert_eq!(axis.min, 0.0);
+    assert_eq!(axis.max, 8.0);
+    assert_eq!(axis.ticks, vec![0.0, 2.0, 4.0, 6.0, 8.0]);
+    assert_eq!(axis.label(2.0), "2");
+    assert_eq!(axis.position(4.0), 0.5);
+
+    let fine = Axis::fit(Scale::Linear, vec![-0.04, 0.05].into_iter());
+    assert_eq!(fine.label(fine.ticks[0]), "-0.04");
+    assert_eq!(fine.label(fine.ticks[2]), "0.00");
+
+    let flat = Axis::fit(Scale::Linear, vec![3.0, 3.0].into_iter());
+    assert!(flat.min < 3.0 && flat.max > 3.0);
+    let empty = Axis::fit(Scale::Linear, Vec::new().into_iter());
+    assert_eq!((empty.min, empty.max), (0.0, 1.0));
+}
+
+#[test]
+fn fits_log_axes() {
+    let axis = Axis::fit(Scale::Log, vec![0.002, 30.0].into_iter());
+    assert_eq!(axis.ticks.len(), 6);
+    assert_eq!(axis.min, 1e-3);
+    assert_eq!(axis.max, 100.0);
+    assert!((axis.position(1.0) - 0.6).abs() < 1e-9);
+    let labels: Vec<String> = axis.ticks.iter().map(|&t| axis.label(t)).collect();
+    assert_eq!(labels, vec!["1e-3", "0.01", "0.1", "1", "10", "100"]);
+
+    let wide = Axis::fit(Scale::Log, vec![1e-30, 1e30].into_iter());
+    assert!(wide.ticks.len() <= MAX_TICKS + 1);
+}
+
+#[test]
+fn renders_svg() {
+    let mut plot = curves();
+    plot.set_y_scale(Scale::Log);
+    let svg = plot.to_svg();
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"320\""));
+    assert!(svg.ends_with("</svg>\n"));
+    assert!(svg.contains(">step=1e-2</text>"));
+    assert!(svg.contains(">convergence</text>"));
+    assert!(svg.contains(">0.01</text>"));
+    // two series and two legend swatches
+    assert_eq!(svg.matches("stroke=\"rgb(148,0,211)\"").count(), 2);
+    assert_eq!(svg.matches("stroke=\"rgb(0,158,115)\"").count(), 2);
+}
+
+#[test]
+fn breaks_series_at_undrawable_points() {
+    let mut plot = Plot::new(320, 240);
+    plot.set_y_scale(Scale::Log);
+    plot.add_series(
+        "",
+        &[
+            (0.0, 1.0),
+            (1.0, 2.0),
+            (2.0, 0.0),
+            (3.0, Fxx::NAN),
+            (4.0, 3.0),
+            (5.0, 4.0),
+        ],
+    );
+    let svg = plot.to_svg();
+    assert_eq!(svg.matches("stroke=\"rgb(148,0,211)\"").count(), 2);
+    // unlabeled series have no legend
+    assert_eq!(svg.matches("<rect").count(), 2);
+}
+
+#[test]
+fn renders_images() {
+    let plot = curves();
+    let image = plot.to_image();
+    assert_eq!(image.dimensions(), (320, 240));
+    let count = |color: Rgb<u8>| image.pixels().filter(|&&p| p == color).count();
+    assert!(count(PALETTE[0]) > 100);
+    assert!(count(PALETTE[1]) > 100);
+    assert!(count(BLACK) > 0);
+}
+
+#[test]
+fn plots_histories() {
+    let csv = "phase,step,epoch,loss\nstep,1,0,4\nstep,2,0,2\nepoch,2,1,\nstep,3,1,1\n";
+    let history = History::read_csv(csv.as_bytes()).unwrap();
+    let mut plot = Plot::new(320, 240);
+    plot.add_history("run", &history, Phase::Step, "loss");
+    assert_eq!(
+        plot.get_series()[0].points,
+        vec![(1.0, 4.0), (2.0, 2.0), (3.0, 1.0)]
+    );
+}
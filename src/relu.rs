@@ -3,9 +3,9 @@ extern crate nalgebra as na;
 use log::debug;
 
 use na::allocator::Allocator;
-use na::{DefaultAllocator, DimName, VectorN};
+use na::{DefaultAllocator, DimName, Dynamic, VectorN};
 
-use crate::model::{Fxx, Model};
+use crate::model::{Batch, Fxx, Model};
 
 pub struct Relu<'a, M: DimName, N: DimName> {
     model: &'a mut dyn Model<M, N>,
@@ -41,6 +41,18 @@ where
         self.model.backpropagate(x, &de_dp)
     }
 
+    fn backpropagate_batch(&mut self, x: &Batch<M>, de_dy: &Batch<N>) -> Batch<M>
+    where
+        DefaultAllocator: Allocator<Fxx, M>
+            + Allocator<Fxx, N>
+            + Allocator<Fxx, M, Dynamic>
+            + Allocator<Fxx, N, Dynamic>,
+    {
+        let p = self.model.predict_batch(x);
+        let de_dp = de_dy.zip_map(&p, |e, pi| if pi > 0.0 { e } else { 0.0 });
+        self.model.backpropagate_batch(x, &de_dp)
+    }
+
     #[inline]
     fn num_inputs(&self) -> usize {
         M::dim()
@@ -64,6 +76,16 @@ where
         y
     }
 
+    fn predict_batch(&self, x: &Batch<M>) -> Batch<N>
+    where
+        DefaultAllocator: Allocator<Fxx, M>
+            + Allocator<Fxx, N>
+            + Allocator<Fxx, M, Dynamic>
+            + Allocator<Fxx, N, Dynamic>,
+    {
+        self.model.predict_batch(x).map(|yi| yi.max(0.0))
+    }
+
     fn update(&mut self, x: &VectorN<Fxx, M>, y: &VectorN<Fxx, N>) -> VectorN<Fxx, M>
     where
         DefaultAllocator: Allocator<Fxx, M> + Allocator<Fxx, N>,
@@ -78,6 +100,33 @@ where
         }
         self.model.update(x, &yh)
     }
+
+    fn update_batch(&mut self, x: &Batch<M>, y: &Batch<N>) -> Batch<M>
+    where
+        DefaultAllocator: Allocator<Fxx, M>
+            + Allocator<Fxx, N>
+            + Allocator<Fxx, M, Dynamic>
+            + Allocator<Fxx, N, Dynamic>,
+    {
+        // as with update, only correct the predictions that weren't thresholded
+        let yh =
+            self.model
+                .predict_batch(x)
+                .zip_map(y, |yhi, yi| if yhi <= 0.0 && yi <= 0.0 { yhi } else { yi });
+        self.model.update_batch(x, &yh)
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.model.set_training(training);
+    }
+
+    fn get_params(&self, params: &mut Vec<Fxx>) {
+        self.model.get_params(params);
+    }
+
+    fn set_params<'p>(&mut self, params: &'p [Fxx]) -> &'p [Fxx] {
+        self.model.set_params(params)
+    }
 }
 
 #[cfg(test)]
@@ -5,7 +5,7 @@ use assert_approx_eq::assert_approx_eq;
 use na::{Matrix1, Matrix1x3, Matrix2x1, Matrix2x3};
 use na::{U1, U2};
 
-use crate::{LinearModel, Model, SGDTrainer, UpdateParams};
+use crate::{Batch, LinearModel, Model, SGDTrainer, UpdateParams};
 
 const LEARNING_PARAMS: UpdateParams = UpdateParams {
     step_size: 0.01,
@@ -27,7 +27,7 @@ fn thresholds() {
     let mut underlying_model = LinearModel::<U2, U1>::new_random(&mut trainer);
     let x = Matrix2x3::new(2.0, 3.0, 4.0, 1.0, 4.0, 5.0);
     let y = Matrix1x3::new(6.0, 11.0, 14.0);
-    assert_eq!(underlying_model.update_bulk(&x, &y), Ok(()));
+    underlying_model.update_bulk(&x, &y).unwrap();
 
     let model = Relu::<U2, U1>::new(&mut underlying_model);
     let x0 = Matrix2x1::new(0.5, 1.0);
@@ -45,7 +45,7 @@ fn thresholds_update() {
     let mut underlying_model = LinearModel::<U2, U1>::new_random(&mut trainer);
     let x = Matrix2x3::new(2.0, 3.0, 4.0, 1.0, 4.0, 5.0);
     let y = Matrix1x3::new(6.0, 11.0, 14.0);
-    assert_eq!(underlying_model.update_bulk(&x, &y), Ok(()));
+    underlying_model.update_bulk(&x, &y).unwrap();
 
     let mut model = Relu::<U2, U1>::new(&mut underlying_model);
 
@@ -63,3 +63,24 @@ fn thresholds_update() {
     y0 = model.predict(&x0);
     assert!(y0[0] > 3.0);
 }
+
+#[test]
+fn thresholds_batch() {
+    let mut trainer = SGDTrainer::new(&LEARNING_PARAMS);
+    let mut underlying_model = LinearModel::<U2, U1>::new_random(&mut trainer);
+    let x = Matrix2x3::new(2.0, 3.0, 4.0, 1.0, 4.0, 5.0);
+    let y = Matrix1x3::new(6.0, 11.0, 14.0);
+    underlying_model.update_bulk(&x, &y).unwrap();
+
+    let mut model = Relu::<U2, U1>::new(&mut underlying_model);
+    let xs = Batch::<U2>::from_row_slice(&[0.5, -0.5, 1.0, -1.0]);
+    let ys = model.predict_batch(&xs);
+    assert_approx_eq!(ys[0], 3.0);
+    assert_approx_eq!(ys[1], 0.0);
+
+    // only the unthresholded prediction is updated
+    model.update_batch(&xs, &Batch::<U1>::from_row_slice(&[4.0, 0.0]));
+    let ys = model.predict_batch(&xs);
+    assert!(ys[0] > 3.0);
+    assert_approx_eq!(ys[1], 0.0);
+}
@@ -0,0 +1,413 @@
+use rand::Rng;
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use log::{debug, info};
+
+use crate::error::Error;
+use crate::model::Fxx;
+
+/// Named hyperparameter values, e.g. "step_size" and "l2_reg".  Integer
+/// parameters, e.g. "mini_batch", are rounded by the objective.
+pub type Hyperparams = BTreeMap<String, Fxx>;
+
+///
+/// The values a hyperparameter may take.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub enum Domain {
+    /// Uniform over [low, high].
+    Uniform(Fxx, Fxx),
+    /// Log-uniform over [low, high], both positive, e.g. step sizes.
+    LogUniform(Fxx, Fxx),
+    /// One of the listed values.
+    Choice(Vec<Fxx>),
+}
+
+impl Domain {
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> Fxx {
+        match self {
+            Domain::Uniform(low, high) => low + (high - low) * rng.gen::<Fxx>(),
+            Domain::LogUniform(low, high) => {
+                (low.ln() + (high.ln() - low.ln()) * rng.gen::<Fxx>()).exp()
+            }
+            Domain::Choice(values) => values[rng.gen_range(0, values.len())],
+        }
+    }
+
+    ///
+    /// Evenly spaced values, on a log scale for `LogUniform`, including the
+    /// end points, or every choice.
+    ///
+    pub fn grid(&self, points: usize) -> Vec<Fxx> {
+        let spaced = |low: Fxx, high: Fxx| -> Vec<Fxx> {
+            if points < 2 {
+                vec![low]
+            } else {
+                (0..points)
+                    .map(|i| low + (high - low) * i as Fxx / (points - 1) as Fxx)
+                    .collect()
+            }
+        };
+        match self {
+            Domain::Uniform(low, high) => spaced(*low, *high),
+            Domain::LogUniform(low, high) => spaced(low.ln(), high.ln())
+                .into_iter()
+                .map(Fxx::exp)
+                .collect(),
+            Domain::Choice(values) => values.clone(),
+        }
+    }
+}
+
+///
+/// The domains of named hyperparameters.
+///
+#[derive(Clone, Debug, Default)]
+pub struct ParamSpace {
+    domains: BTreeMap<String, Domain>,
+}
+
+impl ParamSpace {
+    pub fn new() -> Self {
+        ParamSpace {
+            domains: BTreeMap::new(),
+        }
+    }
+
+    ///
+    /// Add a hyperparameter, failing if a log-uniform domain isn't positive
+    /// or a choice is empty.
+    ///
+    pub fn add(&mut self, name: &str, domain: Domain) -> Result<(), Error> {
+        match &domain {
+            Domain::LogUniform(low, high) if !(*low > 0.0 && *high > 0.0) => {
+                return Err(Error::Config(format!(
+                    "log-uniform {} must be positive",
+                    name
+                )));
+            }
+            Domain::Choice(values) if values.is_empty() => {
+                return Err(Error::Config(format!("no choices for {}", name)));
+            }
+            _ => (),
+        }
+        self.domains.insert(name.to_string(), domain);
+        Ok(())
+    }
+
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> Hyperparams {
+        self.domains
+            .iter()
+            .map(|(name, domain)| (name.clone(), domain.sample(rng)))
+            .collect()
+    }
+
+    ///
+    /// Every combination of the grid values of the domains.
+    ///
+    /// # Arguments
+    /// * `points` - the number of values of each continuous domain.
+    ///
+    pub fn grid(&self, points: usize) -> Vec<Hyperparams> {
+        let mut configs = vec![Hyperparams::new()];
+        for (name, domain) in self.domains.iter() {
+            configs = configs
+                .iter()
+                .flat_map(|config| {
+                    domain.grid(points).into_iter().map(move |value| {
+                        let mut config = config.clone();
+                        config.insert(name.clone(), value);
+                        config
+                    })
+                })
+                .collect();
+        }
+        configs
+    }
+}
+
+///
+/// The score, lower being better, e.g. validation loss, of a configuration
+/// trained with a budget, e.g. epochs.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trial {
+    pub params: Hyperparams,
+    pub budget: usize,
+    pub score: Fxx,
+}
+
+/// Order scores ascending, NaN, e.g. from diverged training, last.
+fn cmp_scores(a: Fxx, b: Fxx) -> Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        _ => a.total_cmp(&b),
+    }
+}
+
+///
+/// The trials of a search.
+///
+#[derive(Clone, Debug, Default)]
+pub struct SearchResults {
+    pub trials: Vec<Trial>,
+}
+
+impl SearchResults {
+    ///
+    /// The trials ordered by decreasing budget, so that configurations
+    /// surviving successive halving come first, then increasing score.
+    ///
+    pub fn ranked(&self) -> Vec<&Trial> {
+        let mut ranked: Vec<&Trial> = self.trials.iter().collect();
+        ranked.sort_by(|a, b| {
+            b.budget
+                .cmp(&a.budget)
+                .then_with(|| cmp_scores(a.score, b.score))
+        });
+        ranked
+    }
+
+    /// The best trial of the greatest budget.
+    pub fn best(&self) -> Option<&Trial> {
+        self.ranked().into_iter().next()
+    }
+}
+
+impl fmt::Display for SearchResults {
+    ///
+    /// A whitespace-separated table of ranked trials with a header row.
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names: BTreeSet<&String> = self.trials.iter().flat_map(|t| t.params.keys()).collect();
+        write!(f, "rank score budget")?;
+        for name in names.iter() {
+            write!(f, " {}", name)?;
+        }
+        for (i, trial) in self.ranked().iter().enumerate() {
+            write!(f, "\n{} {} {}", i + 1, trial.score, trial.budget)?;
+            for name in names.iter() {
+                match trial.params.get(*name) {
+                    Some(value) => write!(f, " {}", value)?,
+                    None => write!(f, " -")?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn run_trial<F>(params: &Hyperparams, budget: usize, objective: &mut F) -> Trial
+where
+    F: FnMut(&Hyperparams, usize) -> Fxx,
+{
+    let score = objective(params, budget);
+    debug!("{:?} budget {} scored {}", params, budget, score);
+    Trial {
+        params: params.clone(),
+        budget,
+        score,
+    }
+}
+
+///
+/// Score every configuration of the grid.
+///
+/// Models borrow their trainers, so the objective builds a fresh model and
+/// trainer from the hyperparameters, trains it on its dataset with the
+/// budget and returns a score, e.g. its validation loss.
+///
+/// # Arguments
+/// * `space` - the hyperparameter domains.
+/// * `points` - the number of grid values of each continuous domain.
+/// * `budget` - the training budget of each configuration.
+/// * `objective` - trains and scores a configuration given a budget.
+///
+pub fn grid_search<F>(
+    space: &ParamSpace,
+    points: usize,
+    budget: usize,
+    mut objective: F,
+) -> SearchResults
+where
+    F: FnMut(&Hyperparams, usize) -> Fxx,
+{
+    SearchResults {
+        trials: space
+            .grid(points)
+            .iter()
+            .map(|params| run_trial(params, budget, &mut objective))
+            .collect(),
+    }
+}
+
+///
+/// Score n configurations sampled from the space.
+///
+pub fn random_search<F, R>(
+    space: &ParamSpace,
+    n: usize,
+    budget: usize,
+    rng: &mut R,
+    mut objective: F,
+) -> SearchResults
+where
+    F: FnMut(&Hyperparams, usize) -> Fxx,
+    R: Rng,
+{
+    SearchResults {
+        trials: (0..n)
+            .map(|_| run_trial(&space.sample(rng), budget, &mut objective))
+            .collect(),
+    }
+}
+
+///
+/// Successive halving, Jamieson and Talwalkar 2016: score the
+/// configurations with the least budget, keep the best 1/eta, multiply the
+/// budget by eta and repeat until one configuration remains or the budget
+/// reaches its maximum.  Configurations are retrained from scratch with each
+/// budget.  Fails unless eta >= 2 and 0 < min_budget <= max_budget.
+///
+/// # Arguments
+/// * `configs` - the configurations to choose from.
+/// * `min_budget` - the budget of the first round.
+/// * `max_budget` - the greatest budget of a round.
+/// * `eta` - the reduction factor, at least 2.
+/// * `objective` - trains and scores a configuration given a budget.
+///
+pub fn successive_halving<F>(
+    configs: Vec<Hyperparams>,
+    min_budget: usize,
+    max_budget: usize,
+    eta: usize,
+    mut objective: F,
+) -> Result<SearchResults, Error>
+where
+    F: FnMut(&Hyperparams, usize) -> Fxx,
+{
+    let mut results = SearchResults::default();
+    halve(
+        &mut results,
+        configs,
+        min_budget,
+        max_budget,
+        eta,
+        &mut objective,
+    )?;
+    Ok(results)
+}
+
+fn check_eta(eta: usize) -> Result<(), Error> {
+    if eta >= 2 {
+        Ok(())
+    } else {
+        Err(Error::Config(format!(
+            "reduction factor {} must be at least 2",
+            eta
+        )))
+    }
+}
+
+fn halve<F>(
+    results: &mut SearchResults,
+    mut configs: Vec<Hyperparams>,
+    min_budget: usize,
+    max_budget: usize,
+    eta: usize,
+    objective: &mut F,
+) -> Result<(), Error>
+where
+    F: FnMut(&Hyperparams, usize) -> Fxx,
+{
+    check_eta(eta)?;
+    if !(0 < min_budget && min_budget <= max_budget) {
+        return Err(Error::Config(format!(
+            "invalid budgets {}..{}",
+            min_budget, max_budget
+        )));
+    }
+    let mut budget = min_budget;
+    while !configs.is_empty() {
+        let mut trials: Vec<Trial> = configs
+            .iter()
+            .map(|params| run_trial(params, budget, objective))
+            .collect();
+        trials.sort_by(|a, b| cmp_scores(a.score, b.score));
+        info!(
+            "halving {} configurations with budget {}, best {}",
+            trials.len(),
+            budget,
+            trials[0].score
+        );
+        let keep = trials.len() / eta;
+        let done = keep == 0 || budget >= max_budget;
+        configs = trials.iter().take(keep).map(|t| t.params.clone()).collect();
+        results.trials.extend(trials);
+        if done {
+            break;
+        }
+        budget = (budget * eta).min(max_budget);
+    }
+    Ok(())
+}
+
+///
+/// Hyperband, Li et al. 2018: successive halving over random configurations
+/// in brackets trading the number of configurations against their least
+/// budget, from many configurations starting at a budget of
+/// max_budget / eta^s_max to few starting at max_budget.  Fails unless
+/// eta >= 2 and max_budget > 0.
+///
+/// # Arguments
+/// * `space` - the hyperparameter domains to sample.
+/// * `max_budget` - the greatest budget of a configuration.
+/// * `eta` - the reduction factor, at least 2.
+/// * `rng` - the source of the sampled configurations.
+/// * `objective` - trains and scores a configuration given a budget.
+///
+pub fn hyperband<F, R>(
+    space: &ParamSpace,
+    max_budget: usize,
+    eta: usize,
+    rng: &mut R,
+    mut objective: F,
+) -> Result<SearchResults, Error>
+where
+    F: FnMut(&Hyperparams, usize) -> Fxx,
+    R: Rng,
+{
+    check_eta(eta)?;
+    let mut s_max = 0;
+    while eta.pow(s_max + 1) <= max_budget {
+        s_max += 1;
+    }
+    let mut results = SearchResults::default();
+    for s in (0..=s_max).rev() {
+        let n = ((s_max + 1) as Fxx / (s + 1) as Fxx * eta.pow(s) as Fxx).ceil() as usize;
+        let budget = (max_budget / eta.pow(s)).max(1);
+        debug!(
+            "hyperband bracket {}: {} configurations from budget {}",
+            s, n, budget
+        );
+        let configs = (0..n).map(|_| space.sample(rng)).collect();
+        halve(
+            &mut results,
+            configs,
+            budget,
+            max_budget,
+            eta,
+            &mut objective,
+        )?;
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+#[path = "./search_test.rs"]
+mod search_test;
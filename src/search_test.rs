@@ -0,0 +1,148 @@
+use super::*;
+
+use assert_approx_eq::assert_approx_eq;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+fn space() -> ParamSpace {
+    let mut space = ParamSpace::new();
+    space
+        .add("step_size", Domain::LogUniform(1e-4, 1e-1))
+        .unwrap();
+    space
+        .add("mini_batch", Domain::Choice(vec![1.0, 8.0, 32.0]))
+        .unwrap();
+    space
+}
+
+/// Best at step_size 1e-2 and mini_batch 8, improving with budget.
+fn objective(params: &Hyperparams, budget: usize) -> Fxx {
+    let step = params["step_size"].log10() + 2.0;
+    let batch = (params["mini_batch"] - 8.0) / 8.0;
+    step * step + batch * batch + 1.0 / budget as Fxx
+}
+
+#[test]
+fn samples_domains() {
+    let mut rng = StdRng::seed_from_u64(1);
+    for _ in 0..100 {
+        let u = Domain::Uniform(-1.0, 1.0).sample(&mut rng);
+        assert!((-1.0..=1.0).contains(&u));
+        let l = Domain::LogUniform(1e-4, 1e-1).sample(&mut rng);
+        assert!((1e-4..=1e-1).contains(&l));
+        let c = Domain::Choice(vec![2.0, 3.0]).sample(&mut rng);
+        assert!(c == 2.0 || c == 3.0);
+    }
+    let config = space().sample(&mut rng);
+    assert_eq!(config.len(), 2);
+}
+
+#[test]
+fn grids_domains() {
+    assert_eq!(Domain::Uniform(0.0, 1.0).grid(3), vec![0.0, 0.5, 1.0]);
+    let log = Domain::LogUniform(1e-3, 1e-1).grid(3);
+    assert_approx_eq!(log[1], 1e-2, 1e-6);
+    assert_eq!(Domain::Uniform(2.0, 3.0).grid(1), vec![2.0]);
+
+    let grid = space().grid(4);
+    assert_eq!(grid.len(), 12);
+    assert_eq!(grid[0]["mini_batch"], 1.0);
+    assert_eq!(grid[1]["mini_batch"], 1.0);
+    assert!(grid[0]["step_size"] < grid[1]["step_size"]);
+}
+
+#[test]
+fn grid_searches() {
+    let results = grid_search(&space(), 4, 10, objective);
+    assert_eq!(results.trials.len(), 12);
+    let best = results.best().unwrap();
+    assert_eq!(best.params["mini_batch"], 8.0);
+    assert_approx_eq!(best.params["step_size"], 1e-2, 1e-6);
+
+    let table = format!("{}", results);
+    let lines: Vec<&str> = table.lines().collect();
+    assert_eq!(lines.len(), 13);
+    assert_eq!(lines[0], "rank score budget mini_batch step_size");
+    assert!(lines[1].starts_with("1 "));
+}
+
+#[test]
+fn random_searches() {
+    let mut rng = StdRng::seed_from_u64(2);
+    let results = random_search(&space(), 20, 10, &mut rng, objective);
+    assert_eq!(results.trials.len(), 20);
+    let ranked = results.ranked();
+    for pair in ranked.windows(2) {
+        assert!(pair[0].score <= pair[1].score);
+    }
+}
+
+#[test]
+fn ranks_nan_last() {
+    let mut space = ParamSpace::new();
+    space.add("x", Domain::Choice(vec![0.0, 1.0, 2.0])).unwrap();
+    let results = grid_search(&space, 1, 1, |params, _| {
+        if params["x"] == 0.0 {
+            Fxx::NAN
+        } else {
+            params["x"]
+        }
+    });
+    let ranked = results.ranked();
+    assert_eq!(ranked[0].params["x"], 1.0);
+    assert!(ranked[2].score.is_nan());
+}
+
+#[test]
+fn halves_configurations() {
+    let mut rng = StdRng::seed_from_u64(3);
+    let configs: Vec<Hyperparams> = (0..9).map(|_| space().sample(&mut rng)).collect();
+    let mut budgets = Vec::new();
+    let results = successive_halving(configs, 1, 9, 3, |params, budget| {
+        budgets.push(budget);
+        objective(params, budget)
+    })
+    .unwrap();
+    // 9 configurations with budget 1, 3 with 3 and 1 with 9
+    assert_eq!(results.trials.len(), 13);
+    assert_eq!(budgets.iter().filter(|&&b| b == 9).count(), 1);
+    let best = results.best().unwrap();
+    assert_eq!(best.budget, 9);
+    let first_round = results.trials[..9]
+        .iter()
+        .map(|t| t.score - 1.0)
+        .fold(Fxx::INFINITY, Fxx::min);
+    assert_approx_eq!(best.score - 1.0 / 9.0, first_round, 1e-5);
+}
+
+#[test]
+fn runs_hyperband() {
+    let mut rng = StdRng::seed_from_u64(4);
+    let results = hyperband(&space(), 9, 3, &mut rng, objective).unwrap();
+    // brackets of 9, 5 and 3 configurations from budgets 1, 3 and 9
+    let at_budget = |b: usize| results.trials.iter().filter(|t| t.budget == b).count();
+    assert_eq!(at_budget(1), 9);
+    assert_eq!(at_budget(3), 3 + 5);
+    assert_eq!(at_budget(9), 1 + 1 + 3);
+    assert_eq!(results.best().unwrap().budget, 9);
+}
+
+#[test]
+fn rejects_invalid_searches() {
+    let mut space = ParamSpace::new();
+    assert!(matches!(
+        space.add("a", Domain::LogUniform(0.0, 1.0)),
+        Err(Error::Config(_))
+    ));
+    assert!(matches!(
+        space.add("b", Domain::Choice(Vec::new())),
+        Err(Error::Config(_))
+    ));
+    let mut rng = StdRng::seed_from_u64(5);
+    let configs = vec![Hyperparams::new()];
+    assert!(successive_halving(configs.clone(), 1, 9, 1, objective).is_err());
+    assert!(successive_halving(configs.clone(), 0, 9, 3, objective).is_err());
+    assert!(successive_halving(configs, 9, 3, 3, objective).is_err());
+    assert!(hyperband(&space, 9, 1, &mut rng, objective).is_err());
+    assert!(hyperband(&space, 0, 3, &mut rng, objective).is_err());
+}
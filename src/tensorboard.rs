@@ -0,0 +1,310 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use image::{DynamicImage, GenericImageView, ImageOutputFormat};
+
+use crate::error::Error;
+use crate::history::{History, Phase};
+use crate::model::Fxx;
+
+/// The number of equal-width buckets of histograms.
+const HISTOGRAM_BUCKETS: usize = 30;
+
+/// Added to rotated CRCs in TFRecord framing.
+const CRC_MASK_DELTA: u32 = 0xa282_ead8;
+
+///
+/// The CRC-32C (Castagnoli) checksum of the data.
+///
+pub fn crc32c(data: &[u8]) -> u32 {
+    const POLY: u32 = 0x82f6_3b78;
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ POLY
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn masked_crc(data: &[u8]) -> u32 {
+    let crc = crc32c(data);
+    crc.rotate_right(15).wrapping_add(CRC_MASK_DELTA)
+}
+
+///
+/// Write a TFRecord: the data's little-endian 64-bit length and its masked
+/// CRC, then the data and its masked CRC.
+///
+pub fn write_record<W: Write>(writer: &mut W, data: &[u8]) -> Result<(), Error> {
+    let length = (data.len() as u64).to_le_bytes();
+    writer.write_all(&length)?;
+    writer.write_all(&masked_crc(&length).to_le_bytes())?;
+    writer.write_all(data)?;
+    writer.write_all(&masked_crc(data).to_le_bytes())?;
+    Ok(())
+}
+
+///
+/// Read the TFRecords written by `write_record`, checking their CRCs.
+///
+pub fn read_records<R: Read>(reader: &mut R) -> Result<Vec<Vec<u8>>, Error> {
+    let corrupt =
+        |what: &str| Error::Io(io::Error::new(io::ErrorKind::InvalidData, what.to_string()));
+    let mut records = Vec::new();
+    loop {
+        let mut length = [0u8; 8];
+        match reader.read_exact(&mut length) {
+            Ok(()) => (),
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(records),
+            Err(err) => return Err(err.into()),
+        }
+        let mut crc = [0u8; 4];
+        reader.read_exact(&mut crc)?;
+        if u32::from_le_bytes(crc) != masked_crc(&length) {
+            return Err(corrupt("record length CRC mismatch"));
+        }
+        // read up to the length rather than allocating it, which a corrupt
+        // file could make arbitrarily large
+        let len = u64::from_le_bytes(length);
+        let mut data = Vec::new();
+        reader.by_ref().take(len).read_to_end(&mut data)?;
+        if (data.len() as u64) < len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        reader.read_exact(&mut crc)?;
+        if u32::from_le_bytes(crc) != masked_crc(&data) {
+            return Err(corrupt("record data CRC mismatch"));
+        }
+        records.push(data);
+    }
+}
+
+///
+/// Protocol buffer encoding of the few fields of TensorFlow's `Event` and
+/// `Summary` messages written here.
+///
+#[derive(Default)]
+struct Proto(Vec<u8>);
+
+impl Proto {
+    fn varint(&mut self, mut v: u64) {
+        while v >= 0x80 {
+            self.0.push(v as u8 | 0x80);
+            v >>= 7;
+        }
+        self.0.push(v as u8);
+    }
+
+    fn key(&mut self, field: u32, wire_type: u8) {
+        self.varint(((field as u64) << 3) | wire_type as u64);
+    }
+
+    fn uint(&mut self, field: u32, v: u64) {
+        self.key(field, 0);
+        self.varint(v);
+    }
+
+    fn double(&mut self, field: u32, v: f64) {
+        self.key(field, 1);
+        self.0.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn float(&mut self, field: u32, v: f32) {
+        self.key(field, 5);
+        self.0.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn bytes(&mut self, field: u32, v: &[u8]) {
+        self.key(field, 2);
+        self.varint(v.len() as u64);
+        self.0.extend_from_slice(v);
+    }
+
+    fn packed_doubles(&mut self, field: u32, vs: &[f64]) {
+        let mut packed = Proto::default();
+        for &v in vs {
+            packed.0.extend_from_slice(&v.to_le_bytes());
+        }
+        self.bytes(field, &packed.0);
+    }
+}
+
+///
+/// Write scalar, histogram and image summaries to a TensorBoard event file,
+/// one TFRecord-framed `Event` per summary.
+///
+/// Event fields: wall_time 1, step 2, file_version 3, summary 5.
+/// Summary.Value fields: tag 1, simple_value 2, image 4, histo 5.
+///
+pub struct EventWriter<W: Write> {
+    writer: W,
+}
+
+impl EventWriter<BufWriter<File>> {
+    ///
+    /// Create the log directory, if necessary, and a new event file in it
+    /// named as TensorBoard expects.
+    ///
+    pub fn create<P: AsRef<Path>>(log_dir: P) -> Result<Self, Error> {
+        fs::create_dir_all(&log_dir)?;
+        let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "localhost".to_string());
+        let mut path = PathBuf::from(log_dir.as_ref());
+        path.push(format!(
+            "events.out.tfevents.{}.{}",
+            wall_time() as u64,
+            host
+        ));
+        EventWriter::new(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<W: Write> EventWriter<W> {
+    ///
+    /// Start an event stream with its file version event.
+    ///
+    pub fn new(writer: W) -> Result<Self, Error> {
+        let mut events = EventWriter { writer };
+        let mut event = Proto::default();
+        event.double(1, wall_time());
+        event.bytes(3, b"brain.Event:2");
+        write_record(&mut events.writer, &event.0)?;
+        Ok(events)
+    }
+
+    fn write_value(&mut self, step: usize, value: &Proto) -> Result<(), Error> {
+        let mut summary = Proto::default();
+        summary.bytes(1, &value.0);
+        let mut event = Proto::default();
+        event.double(1, wall_time());
+        event.uint(2, step as u64);
+        event.bytes(5, &summary.0);
+        write_record(&mut self.writer, &event.0)
+    }
+
+    pub fn add_scalar(&mut self, tag: &str, value: Fxx, step: usize) -> Result<(), Error> {
+        let mut summary_value = Proto::default();
+        summary_value.bytes(1, tag.as_bytes());
+        summary_value.float(2, value);
+        self.write_value(step, &summary_value)
+    }
+
+    ///
+    /// Add a histogram of values, e.g. `LinearModel::get_ws`, in equal-width
+    /// buckets between their finite least and greatest values.
+    ///
+    pub fn add_histogram(&mut self, tag: &str, values: &[Fxx], step: usize) -> Result<(), Error> {
+        let values: Vec<f64> = values
+            .iter()
+            .filter(|v| v.is_finite())
+            .map(|&v| v as f64)
+            .collect();
+        let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let (limits, counts) = if values.is_empty() {
+            (vec![], vec![])
+        } else {
+            let width = (max - min) / HISTOGRAM_BUCKETS as f64;
+            let mut counts = vec![0.0; HISTOGRAM_BUCKETS];
+            for v in &values {
+                let bucket = if width > 0.0 {
+                    (((v - min) / width) as usize).min(HISTOGRAM_BUCKETS - 1)
+                } else {
+                    HISTOGRAM_BUCKETS - 1
+                };
+                counts[bucket] += 1.0;
+            }
+            let limits = (1..=HISTOGRAM_BUCKETS)
+                .map(|i| {
+                    if i == HISTOGRAM_BUCKETS {
+                        max
+                    } else {
+                        min + i as f64 * width
+                    }
+                })
+                .collect();
+            (limits, counts)
+        };
+
+        let mut histogram = Proto::default();
+        histogram.double(1, if values.is_empty() { 0.0 } else { min });
+        histogram.double(2, if values.is_empty() { 0.0 } else { max });
+        histogram.double(3, values.len() as f64);
+        histogram.double(4, values.iter().sum());
+        histogram.double(5, values.iter().map(|v| v * v).sum());
+        histogram.packed_doubles(6, &limits);
+        histogram.packed_doubles(7, &counts);
+
+        let mut summary_value = Proto::default();
+        summary_value.bytes(1, tag.as_bytes());
+        summary_value.bytes(5, &histogram.0);
+        self.write_value(step, &summary_value)
+    }
+
+    ///
+    /// Add an image, e.g. a filter visualization, encoded as PNG.
+    ///
+    pub fn add_image(&mut self, tag: &str, image: &DynamicImage, step: usize) -> Result<(), Error> {
+        let mut png = Vec::new();
+        image.write_to(&mut png, ImageOutputFormat::Png)?;
+        let colorspace = match image {
+            DynamicImage::ImageLuma8(_) | DynamicImage::ImageLuma16(_) => 1,
+            DynamicImage::ImageLumaA8(_) | DynamicImage::ImageLumaA16(_) => 2,
+            DynamicImage::ImageRgba8(_) | DynamicImage::ImageRgba16(_) => 4,
+            _ => 3,
+        };
+        let mut summary_image = Proto::default();
+        summary_image.uint(1, image.height() as u64);
+        summary_image.uint(2, image.width() as u64);
+        summary_image.uint(3, colorspace);
+        summary_image.bytes(4, &png);
+
+        let mut summary_value = Proto::default();
+        summary_value.bytes(1, tag.as_bytes());
+        summary_value.bytes(4, &summary_image.0);
+        self.write_value(step, &summary_value)
+    }
+
+    ///
+    /// Add every value of a training history as a scalar tagged by phase and
+    /// name, e.g. `epoch/mean_error`, numbered by step or epoch.
+    ///
+    pub fn add_history(&mut self, history: &History) -> Result<(), Error> {
+        for record in history.get_records() {
+            let step = match record.phase {
+                Phase::Step => record.step,
+                Phase::Epoch => record.epoch,
+            };
+            for (name, &value) in &record.values {
+                self.add_scalar(&format!("{}/{}", record.phase, name), value, step)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+fn wall_time() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |d| d.as_secs_f64())
+}
+
+#[cfg(test)]
+#[path = "./tensorboard_test.rs"]
+mod tensorboard_test;
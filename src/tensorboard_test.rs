@@ -0,0 +1,192 @@
+use super::*;
+
+use image::{GrayImage, Luma};
+use std::convert::TryInto;
+
+/// A decoded protocol buffer field value.
+#[derive(Debug, PartialEq)]
+enum Field {
+    Varint(u64),
+    Fixed64([u8; 8]),
+    Bytes(Vec<u8>),
+    Fixed32([u8; 4]),
+}
+
+fn decode(mut data: &[u8]) -> Vec<(u32, Field)> {
+    fn varint(data: &mut &[u8]) -> u64 {
+        let mut v = 0;
+        let mut shift = 0;
+        loop {
+            let byte = data[0];
+            *data = &data[1..];
+            v |= ((byte & 0x7f) as u64) << shift;
+            if byte < 0x80 {
+                return v;
+            }
+            shift += 7;
+        }
+    }
+    let mut fields = Vec::new();
+    while !data.is_empty() {
+        let key = varint(&mut data);
+        let field = match key & 7 {
+            0 => Field::Varint(varint(&mut data)),
+            1 => {
+                let (v, rest) = data.split_at(8);
+                data = rest;
+                Field::Fixed64(v.try_into().unwrap())
+            }
+            2 => {
+                let n = varint(&mut data) as usize;
+                let (v, rest) = data.split_at(n);
+                data = rest;
+                Field::Bytes(v.to_vec())
+            }
+            5 => {
+                let (v, rest) = data.split_at(4);
+                data = rest;
+                Field::Fixed32(v.try_into().unwrap())
+            }
+            wire_type => panic!("unexpected wire type {}", wire_type),
+        };
+        fields.push(((key >> 3) as u32, field));
+    }
+    fields
+}
+
+fn field(fields: &[(u32, Field)], number: u32) -> &Field {
+    &fields.iter().find(|(n, _)| *n == number).unwrap().1
+}
+
+fn bytes(field: &Field) -> &[u8] {
+    match field {
+        Field::Bytes(v) => v,
+        _ => panic!("expected bytes, found {:?}", field),
+    }
+}
+
+fn double(field: &Field) -> f64 {
+    match field {
+        Field::Fixed64(v) => f64::from_le_bytes(*v),
+        _ => panic!("expected double, found {:?}", field),
+    }
+}
+
+/// The step and summary value fields of an event.
+fn summary_value(event: &[u8]) -> (u64, Vec<(u32, Field)>) {
+    let fields = decode(event);
+    let step = match field(&fields, 2) {
+        Field::Varint(step) => *step,
+        f => panic!("expected step, found {:?}", f),
+    };
+    let summary = decode(bytes(field(&fields, 5)));
+    (step, decode(bytes(field(&summary, 1))))
+}
+
+fn events<F: FnOnce(&mut EventWriter<Vec<u8>>)>(write: F) -> Vec<Vec<u8>> {
+    let mut writer = EventWriter::new(Vec::new()).unwrap();
+    write(&mut writer);
+    read_records(&mut writer.into_inner().as_slice()).unwrap()
+}
+
+#[test]
+fn checksums() {
+    assert_eq!(crc32c(b""), 0);
+    assert_eq!(crc32c(b"123456789"), 0xe306_9283);
+}
+
+#[test]
+fn round_trips_records() {
+    let mut buffer = Vec::new();
+    write_record(&mut buffer, b"first").unwrap();
+    write_record(&mut buffer, b"").unwrap();
+    write_record(&mut buffer, &[7u8; 300]).unwrap();
+    assert_eq!(buffer.len(), 3 * 16 + 5 + 300);
+    let records = read_records(&mut buffer.as_slice()).unwrap();
+    assert_eq!(records, vec![b"first".to_vec(), vec![], vec![7u8; 300]]);
+
+    let mut corrupt = buffer.clone();
+    corrupt[13] ^= 1;
+    assert!(read_records(&mut corrupt.as_slice()).is_err());
+    let mut corrupt = buffer.clone();
+    corrupt[1] ^= 1;
+    assert!(read_records(&mut corrupt.as_slice()).is_err());
+    // truncated
+    assert!(read_records(&mut &buffer[..buffer.len() - 1]).is_err());
+
+    // a huge length with a valid CRC isn't allocated
+    let length = u64::MAX.to_le_bytes();
+    let mut huge = length.to_vec();
+    huge.extend_from_slice(&masked_crc(&length).to_le_bytes());
+    huge.extend_from_slice(b"short");
+    assert!(read_records(&mut huge.as_slice()).is_err());
+}
+
+#[test]
+fn starts_with_file_version() {
+    let records = events(|_| ());
+    assert_eq!(records.len(), 1);
+    let fields = decode(&records[0]);
+    assert_eq!(bytes(field(&fields, 3)), b"brain.Event:2");
+    assert!(double(field(&fields, 1)) > 0.0);
+}
+
+#[test]
+fn writes_scalars() {
+    let records = events(|writer| {
+        writer.add_scalar("loss", 0.25, 3).unwrap();
+        writer.add_scalar("loss", 0.125, 300).unwrap();
+    });
+    assert_eq!(records.len(), 3);
+    let (step, value) = summary_value(&records[2]);
+    assert_eq!(step, 300);
+    assert_eq!(bytes(field(&value, 1)), b"loss");
+    assert_eq!(field(&value, 2), &Field::Fixed32(0.125f32.to_le_bytes()));
+}
+
+#[test]
+fn writes_histograms() {
+    let values: Vec<Fxx> = (0..60).map(|i| i as Fxx).chain(vec![Fxx::NAN]).collect();
+    let records = events(|writer| writer.add_histogram("ws", &values, 1).unwrap());
+    let (_, value) = summary_value(&records[1]);
+    let histogram = decode(bytes(field(&value, 5)));
+    assert_eq!(double(field(&histogram, 1)), 0.0);
+    assert_eq!(double(field(&histogram, 2)), 59.0);
+    assert_eq!(double(field(&histogram, 3)), 60.0);
+    assert_eq!(double(field(&histogram, 4)), (0..60).sum::<i32>() as f64);
+    let doubles = |f: &Field| -> Vec<f64> {
+        bytes(f)
+            .chunks(8)
+            .map(|c| f64::from_le_bytes(c.try_into().unwrap()))
+            .collect()
+    };
+    let limits = doubles(field(&histogram, 6));
+    let counts = doubles(field(&histogram, 7));
+    assert_eq!(limits.len(), HISTOGRAM_BUCKETS);
+    assert_eq!(*limits.last().unwrap(), 59.0);
+    assert_eq!(counts, vec![2.0; HISTOGRAM_BUCKETS]);
+}
+
+#[test]
+fn writes_images() {
+    let image = DynamicImage::ImageLuma8(GrayImage::from_fn(3, 2, |x, _| Luma([x as u8 * 100])));
+    let records = events(|writer| writer.add_image("filters", &image, 2).unwrap());
+    let (_, value) = summary_value(&records[1]);
+    let summary_image = decode(bytes(field(&value, 4)));
+    assert_eq!(field(&summary_image, 1), &Field::Varint(2));
+    assert_eq!(field(&summary_image, 2), &Field::Varint(3));
+    assert_eq!(field(&summary_image, 3), &Field::Varint(1));
+    let png = image::load_from_memory(bytes(field(&summary_image, 4))).unwrap();
+    assert_eq!(png.to_luma8(), image.to_luma8());
+}
+
+#[test]
+fn writes_histories() {
+    let csv = "phase,step,epoch,loss,mean_error\nstep,1,0,4,\nepoch,1,1,,2\n";
+    let history = History::read_csv(csv.as_bytes()).unwrap();
+    let records = events(|writer| writer.add_history(&history).unwrap());
+    assert_eq!(records.len(), 3);
+    let (step, value) = summary_value(&records[2]);
+    assert_eq!(step, 1);
+    assert_eq!(bytes(field(&value, 1)), b"epoch/mean_error");
+}
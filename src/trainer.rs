@@ -1,6 +1,6 @@
 extern crate nalgebra as na;
 
-use crate::model::{has_nan, Fxx};
+use crate::model::Fxx;
 use log::debug;
 use na::allocator::Allocator;
 use na::storage::Owned;
@@ -66,7 +66,6 @@ where
         let step_size = self.update_params.step_size / (M::dim() as Fxx);
         let bias_result = bias - step_size * bias_gradient;
         let ws_result = (1.0 - self.update_params.l2_reg) * weights - step_size * gradient;
-        debug_assert!(!has_nan(&ws_result), "NaN update weights");
         debug!(
             "update ({}x{}) |w|={} |b|={}",
             N::dim(),